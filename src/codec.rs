@@ -0,0 +1,887 @@
+//! Registre des formats.
+//!
+//! Chaque format est une implémentation de [`Codec`] ; la reconnaissance (nom, extension,
+//! signature) et l'aiguillage de la compression, de l'extraction et du listage parcourent
+//! [`codecs`]. Un nouveau format s'ajoute à ce registre, sans toucher aux points d'entrée.
+
+use std::sync::LazyLock;
+
+use super::*;
+
+/// Reçoit le flux décodé par [`Codec::decode`]
+pub(crate) type Visit<'v> = dyn FnMut(&mut dyn Read) -> io::Result<()> + 'v;
+
+/// Couches de décompression d'un format, partagées par un flux et son tar (`gz`, `tar.gz`)
+type Decode = for<'r, 'v, 'w> fn(Box<dyn BufRead + 'r>, &'w mut Visit<'v>) -> io::Result<()>;
+
+/// Compression vers un format (la même fonction sert parfois au flux et à son tar)
+type Compress = fn(&CompressOptions, &ExcludeFilter, Format, &mut EventLog) -> io::Result<()>;
+
+/// Un format que sharky sait reconnaître, et le cas échéant lire ou écrire
+pub(crate) trait Codec: Send + Sync {
+    fn format(&self) -> Format;
+
+    /// Noms et extensions du format (`--format`, `--input-format-hint`, extension de fichier),
+    /// le nom canonique en tête
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Signatures possibles en tête de fichier. Un tar compressé n'a pas les siennes : il se
+    /// présente comme le flux qui l'emballe (un `.tar.gz` est d'abord un `.gz`).
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[]
+    }
+
+    /// Le format se reconnaît-il à ses premiers octets ?
+    fn sniff(&self, head: &[u8]) -> bool {
+        self.magic().iter().any(|magic| head.starts_with(magic))
+    }
+
+    /// Chaîne de décodeurs, dans l'ordre de lecture
+    fn decoder_chain(&self) -> &'static str;
+
+    /// Extrait `opts.input()` dans `opts.output()`
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()>;
+
+    /// Extrait un flux lu sur l'entrée standard ; `sniffed` si le format vient de sa signature
+    fn decompress_reader(&self, _reader: Box<dyn BufRead + '_>, _sniffed: bool, _opts: &DecompressOptions, _ex: &mut Extractor) -> io::Result<()> {
+        Err(unsupported(format!("{} archives cannot be read from a stream", self.format().name())))
+    }
+
+    /// Ouvre les couches de décompression de `reader` et passe le flux décodé à `visit` :
+    /// le tar d'un format tar, les données d'un flux mono-fichier
+    fn decode(&self, _reader: Box<dyn BufRead + '_>, _visit: &mut Visit) -> io::Result<()> {
+        Err(unsupported(format!("{} archives are not a compressed stream", self.format().name())))
+    }
+
+    /// Entrées de l'archive, lues sans extraction
+    fn entries(&self, _opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        Err(unsupported(format!("Reading entries without extracting is not supported for {} archives", self.format().name())))
+    }
+
+    /// Écrit `opts.output()`, si `can_write`
+    fn compress(&self, _opts: &CompressOptions, _filter: &ExcludeFilter, _log: &mut EventLog) -> io::Result<()> {
+        Err(cannot_write(self.format()))
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+
+    /// Flux compressé d'un seul fichier, sans structure d'archive
+    fn is_single_stream(&self) -> bool {
+        false
+    }
+
+    /// Archive tar, compressée ou non : en-têtes PAX, filtres `--file-transform`
+    fn is_tar_based(&self) -> bool {
+        false
+    }
+
+    /// Le lecteur a besoin d'un fichier (accès aléatoire)
+    fn needs_seek(&self) -> bool {
+        false
+    }
+
+    /// `--extract-entry` sait en sortir une entrée seule
+    fn extracts_single_entry(&self) -> bool {
+        false
+    }
+
+    /// Variante tar d'un flux mono-fichier (`gz` → `tar.gz`)
+    fn tar_format(&self) -> Option<Format> {
+        None
+    }
+
+    /// Format tar à utiliser à la place de celui-ci si le fichier `path` emballe un tar que
+    /// l'extension ne laissait pas deviner
+    fn wrapped_tar(&self, _path: &Path) -> io::Result<Option<Format>> {
+        Ok(None)
+    }
+}
+
+fn cannot_write(format: Format) -> io::Error {
+    unsupported(format!("Cannot create {} archives", format.name()))
+}
+
+/// Tous les formats, dans l'ordre d'affichage de `--list-formats`
+static CODECS: LazyLock<Vec<Box<dyn Codec>>> = LazyLock::new(|| {
+    vec![
+        Box::new(CompressedTar {
+            format: Format::Sharky,
+            extensions: &["sharky"],
+            magic: &[],
+            chain: "zstd → xz → tar",
+            decode: sharky_decode,
+            compress: Some(|opts, filter, _, log| compress_sharky(opts, filter, log)),
+        }),
+        Box::new(ZipCodec),
+        Box::new(RarCodec),
+        Box::new(SevenZCodec),
+        Box::new(IsoCodec),
+        Box::new(TarCodec),
+        Box::new(CompressedTar {
+            format: Format::TarGz,
+            extensions: &["tar.gz", "tgz"],
+            magic: &[],
+            chain: "gzip → tar",
+            decode: gz_decode,
+            compress: Some(compress_tar),
+        }),
+        Box::new(SingleStream {
+            format: Format::Gz,
+            extensions: &["gz", "gzip"],
+            magic: &[&[0x1f, 0x8b]],
+            chain: "gzip",
+            tar: Format::TarGz,
+            decode: gz_decode,
+            extract: |opts, ex| decompress_single_file_gz(opts.input(), opts.output(), opts.strict, ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                decompress_stream(MultiGzDecoder::new(reader), opts.output(), sniffed, "GZ", ex)
+            },
+            compress: Some(compress_single_file),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarBz2,
+            extensions: &["tar.bz2", "tbz", "tbz2"],
+            magic: &[],
+            chain: "bzip2 → tar",
+            decode: bz2_decode,
+            compress: Some(compress_tar),
+        }),
+        Box::new(SingleStream {
+            format: Format::Bz2,
+            extensions: &["bz2", "bzip2"],
+            magic: &[b"BZh"],
+            chain: "bzip2",
+            tar: Format::TarBz2,
+            decode: bz2_decode,
+            extract: |opts, ex| decompress_single_file_bz2(opts.input(), opts.output(), opts.strict, ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                decompress_stream(bzip2::read::MultiBzDecoder::new(reader), opts.output(), sniffed, "BZ2", ex)
+            },
+            compress: Some(compress_single_file),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarXz,
+            extensions: &["tar.xz", "txz"],
+            magic: &[],
+            chain: "xz → tar",
+            decode: xz_decode,
+            compress: Some(compress_tar),
+        }),
+        Box::new(SingleStream {
+            format: Format::Xz,
+            extensions: &["xz"],
+            magic: &[&XZ_MAGIC],
+            chain: "xz",
+            tar: Format::TarXz,
+            decode: xz_decode,
+            extract: |opts, ex| decompress_single_file_xz(opts.input(), opts.output(), opts.strict, ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                decompress_stream(XzDecoder::new_multi_decoder(reader), opts.output(), sniffed, "XZ", ex)
+            },
+            compress: Some(compress_single_file),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarZst,
+            extensions: &["tar.zst", "tar.zstd", "tzst", "tzs"],
+            magic: &[],
+            chain: "zstd → tar",
+            decode: zstd_decode,
+            compress: Some(compress_tar),
+        }),
+        Box::new(SingleStream {
+            format: Format::Zst,
+            extensions: &["zst", "zstd"],
+            magic: &[&[0x28, 0xb5, 0x2f, 0xfd]],
+            chain: "zstd",
+            tar: Format::TarZst,
+            decode: zstd_decode,
+            extract: |opts, ex| decompress_single_file_zstd(opts.input(), opts.output(), opts.strict, ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                // Signature zstd sur stdin : peut-être une archive sharky (zstd → xz → tar)
+                let mut decoded = BufReader::new(zstd_decoder(reader)?);
+                if sniffed && decoded.fill_buf()?.starts_with(&XZ_MAGIC) {
+                    decompress_tar_plain(XzDecoder::new(decoded), opts.output(), ex)
+                } else {
+                    decompress_stream(decoded, opts.output(), sniffed, "ZSTD", ex)
+                }
+            },
+            compress: Some(compress_single_file),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarLzfse,
+            extensions: &["tar.lzfse"],
+            magic: &[],
+            chain: "lzfse → tar",
+            decode: lzfse_decode,
+            compress: Some(compress_lzfse),
+        }),
+        Box::new(SingleStream {
+            format: Format::Lzfse,
+            extensions: &["lzfse"],
+            // « bvx » suivi du type du premier bloc
+            magic: &[b"bvx1", b"bvx2", b"bvxn", b"bvx-", b"bvx$"],
+            chain: "lzfse",
+            tar: Format::TarLzfse,
+            decode: lzfse_decode,
+            extract: |opts, ex| decompress_single_file_lzfse(opts.input(), opts.output(), ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                let mut decoder = LzfseRingDecoder::default();
+                decompress_stream(decoder.reader(reader), opts.output(), sniffed, "LZFSE", ex)
+            },
+            compress: Some(compress_lzfse),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarLzma,
+            extensions: &["tar.lzma", "tlz"],
+            magic: &[],
+            chain: "lzma → tar",
+            decode: lzma_decode,
+            compress: None,
+        }),
+        Box::new(SingleStream {
+            format: Format::Lzma,
+            extensions: &["lzma"],
+            magic: &[],
+            chain: "lzma",
+            tar: Format::TarLzma,
+            decode: lzma_decode,
+            extract: |opts, ex| decompress_single_file_lzma(opts.input(), opts.output(), opts.strict, ex),
+            extract_reader: |mut reader, _, opts, ex| {
+                let output_file_path = opts.output().join("decompressed");
+                let mut output_file = BufWriter::new(ex.create_stream_output(&output_file_path)?);
+                lzma_members(&mut reader, &mut output_file)?;
+                output_file.flush()?;
+                ex.stream_done("LZMA", &output_file_path);
+                Ok(())
+            },
+            compress: None,
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarBrotli,
+            extensions: &["tar.br"],
+            magic: &[],
+            chain: "brotli → tar",
+            decode: brotli_decode,
+            compress: Some(compress_brotli),
+        }),
+        Box::new(SingleStream {
+            format: Format::Brotli,
+            extensions: &["br", "brotli"],
+            magic: &[],
+            chain: "brotli",
+            tar: Format::TarBrotli,
+            decode: brotli_decode,
+            extract: |opts, ex| decompress_single_file_brotli(opts.input(), opts.output(), ex),
+            extract_reader: |reader, sniffed, opts, ex| {
+                decompress_stream(BrotliDecoder::new(reader, 4096), opts.output(), sniffed, "Brotli", ex)
+            },
+            compress: Some(compress_brotli),
+        }),
+        Box::new(CompressedTar {
+            format: Format::TarLz4,
+            extensions: &["tar.lz4"],
+            // Seul tar compressé à avoir sa signature : le `.lz4` à taille préfixée n'en a pas
+            magic: &[&LZ4_FRAME_MAGIC],
+            chain: "lz4 (frame) → tar",
+            decode: |reader, visit| visit(&mut lz4_flex::frame::FrameDecoder::new(reader)),
+            compress: Some(compress_lz4),
+        }),
+        Box::new(SingleStream {
+            format: Format::Lz4,
+            extensions: &["lz4"],
+            magic: &[],
+            chain: "lz4 (size-prepended)",
+            tar: Format::TarLz4,
+            decode: lz4_decode,
+            extract: |opts, ex| decompress_single_file_lz4(opts.input(), opts.output(), ex),
+            extract_reader: |mut reader, _, opts, ex| {
+                // sharky n'écrit de trames LZ4 que pour des tar
+                if reader.fill_buf()?.starts_with(&LZ4_FRAME_MAGIC) {
+                    return decompress_tar_plain(lz4_flex::frame::FrameDecoder::new(reader), opts.output(), ex);
+                }
+                let mut input_data = Vec::new();
+                reader.read_to_end(&mut input_data)?;
+                let decompressed = lz4_flex::decompress_size_prepended(&input_data)
+                    .map_err(|e| io::Error::other(format!("LZ4 decompression error: {}", e)))?;
+                let output_file_path = opts.output().join("decompressed");
+                ex.create_stream_output(&output_file_path)?.write_all(&decompressed)?;
+                ex.stream_done("LZ4", &output_file_path);
+                Ok(())
+            },
+            compress: Some(compress_lz4),
+        }),
+        Box::new(CabCodec),
+        Box::new(DebCodec),
+        Box::new(RpmCodec),
+    ]
+});
+
+/// Formats enregistrés
+pub(crate) fn codecs() -> &'static [Box<dyn Codec>] {
+    &CODECS
+}
+
+fn gz_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut GzDecoder::new(reader))
+}
+
+fn bz2_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut BzDecoder::new(reader))
+}
+
+fn xz_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut XzDecoder::new(reader))
+}
+
+fn zstd_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut zstd_decoder(reader)?)
+}
+
+fn lzfse_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    let mut decoder = LzfseRingDecoder::default();
+    visit(&mut decoder.reader(reader))
+}
+
+fn lzma_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut lzma_reader(reader)?)
+}
+
+fn brotli_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut BrotliDecoder::new(reader, 4096))
+}
+
+/// `.lz4` : trame LZ4, ou bloc unique précédé de sa taille
+fn lz4_decode(mut reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    if reader.fill_buf()?.starts_with(&LZ4_FRAME_MAGIC) {
+        return visit(&mut lz4_flex::frame::FrameDecoder::new(reader));
+    }
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let decoded = lz4_flex::decompress_size_prepended(&data)
+        .map_err(|e| io::Error::other(format!("LZ4 decompression error: {}", e)))?;
+    visit(&mut Cursor::new(decoded))
+}
+
+fn sharky_decode(reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+    visit(&mut sharky_tar_reader(reader)?)
+}
+
+/// Tar non compressé
+struct TarCodec;
+
+impl Codec for TarCodec {
+    fn format(&self) -> Format {
+        Format::Tar
+    }
+
+    fn is_tar_based(&self) -> bool {
+        true
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["tar"]
+    }
+
+    /// La signature `ustar` est à l'octet 257, pas en tête
+    fn sniff(&self, head: &[u8]) -> bool {
+        is_tar_header(head)
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "tar"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        // Compter les en-têtes en sautant les données ne coûte presque rien
+        decompress_tar_entries(File::open(opts.input())?, opts.output(), ex, tar_entry_count(opts.input()))
+    }
+
+    fn decompress_reader(&self, reader: Box<dyn BufRead + '_>, _sniffed: bool, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_tar_plain(reader, opts.output(), ex)
+    }
+
+    fn decode(&self, mut reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+        visit(&mut reader)
+    }
+
+    fn entries(&self, opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        tar_entries(BufReader::with_capacity(opts.buffer_size, File::open(opts.input())?))
+    }
+
+    fn compress(&self, opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+        compress_tar(opts, filter, Format::Tar, log)
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn extracts_single_entry(&self) -> bool {
+        true
+    }
+}
+
+/// Tar emballé dans une ou plusieurs couches de compression
+struct CompressedTar {
+    format: Format,
+    extensions: &'static [&'static str],
+    magic: &'static [&'static [u8]],
+    chain: &'static str,
+    decode: Decode,
+    compress: Option<Compress>,
+}
+
+impl CompressedTar {
+    fn open(&self, opts: &DecompressOptions) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::with_capacity(opts.buffer_size, File::open(opts.input())?)))
+    }
+}
+
+impl Codec for CompressedTar {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn is_tar_based(&self) -> bool {
+        true
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        self.magic
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        self.chain
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        // Un seul décodage du flux : barre sans total plutôt qu'un premier passage de comptage
+        (self.decode)(self.open(opts)?, &mut |tar| decompress_tar_plain(tar, opts.output(), ex))
+    }
+
+    fn decompress_reader(&self, reader: Box<dyn BufRead + '_>, _sniffed: bool, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        (self.decode)(reader, &mut |tar| decompress_tar_plain(tar, opts.output(), ex))
+    }
+
+    fn decode(&self, reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+        (self.decode)(reader, visit)
+    }
+
+    fn entries(&self, opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        let mut entries = Vec::new();
+        (self.decode)(self.open(opts)?, &mut |tar| {
+            entries = tar_entries(tar)?;
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    fn compress(&self, opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+        match self.compress {
+            Some(compress) => compress(opts, filter, self.format, log),
+            None => Err(cannot_write(self.format)),
+        }
+    }
+
+    fn can_write(&self) -> bool {
+        self.compress.is_some()
+    }
+
+    fn extracts_single_entry(&self) -> bool {
+        true
+    }
+}
+
+/// Flux compressé d'un seul fichier
+struct SingleStream {
+    format: Format,
+    extensions: &'static [&'static str],
+    magic: &'static [&'static [u8]],
+    chain: &'static str,
+    tar: Format,
+    decode: Decode,
+    extract: fn(&DecompressOptions, &mut Extractor) -> io::Result<()>,
+    extract_reader: for<'r> fn(Box<dyn BufRead + 'r>, bool, &DecompressOptions, &mut Extractor) -> io::Result<()>,
+    compress: Option<Compress>,
+}
+
+impl Codec for SingleStream {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        self.magic
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        self.chain
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        (self.extract)(opts, ex)
+    }
+
+    fn decompress_reader(&self, reader: Box<dyn BufRead + '_>, sniffed: bool, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        (self.extract_reader)(reader, sniffed, opts, ex)
+    }
+
+    fn decode(&self, reader: Box<dyn BufRead + '_>, visit: &mut Visit) -> io::Result<()> {
+        (self.decode)(reader, visit)
+    }
+
+    fn compress(&self, opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+        match self.compress {
+            Some(compress) => compress(opts, filter, self.format, log),
+            None => Err(cannot_write(self.format)),
+        }
+    }
+
+    fn can_write(&self) -> bool {
+        self.compress.is_some()
+    }
+
+    fn is_single_stream(&self) -> bool {
+        true
+    }
+
+    fn tar_format(&self) -> Option<Format> {
+        Some(self.tar)
+    }
+
+    /// Sans signature propre (brotli, lz4, lzma), rien ne distingue de l'extérieur un tar
+    /// emballé : les premiers octets décodés tranchent
+    fn wrapped_tar(&self, path: &Path) -> io::Result<Option<Format>> {
+        if !self.magic.is_empty() {
+            return Ok(None);
+        }
+        let mut head = Vec::new();
+        (self.decode)(Box::new(BufReader::new(File::open(path)?)), &mut |decoded| {
+            head = decoded_head(decoded);
+            Ok(())
+        })?;
+        Ok(is_tar_header(&head).then_some(self.tar))
+    }
+}
+
+struct ZipCodec;
+
+impl Codec for ZipCodec {
+    fn format(&self) -> Format {
+        Format::Zip
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["zip"]
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[b"PK"]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "zip"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_zip(opts.input(), opts.output(), ex)
+    }
+
+    fn entries(&self, opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        let mut archive = ZipArchive::new(File::open(opts.input())?)?;
+        (0..archive.len())
+            .map(|i| {
+                let entry = archive.by_index_raw(i)?;
+                Ok(EntryInfo {
+                    path: entry.name().to_string(),
+                    size: entry.size(),
+                    mtime: entry
+                        .last_modified()
+                        .and_then(|dt| time::OffsetDateTime::try_from(dt).ok())
+                        .map(|dt| dt.unix_timestamp()),
+                    is_dir: entry.is_dir(),
+                })
+            })
+            .collect()
+    }
+
+    fn compress(&self, opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+        compress_zip(opts, filter, log)
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn needs_seek(&self) -> bool {
+        true
+    }
+
+    fn extracts_single_entry(&self) -> bool {
+        true
+    }
+}
+
+struct SevenZCodec;
+
+impl Codec for SevenZCodec {
+    fn format(&self) -> Format {
+        Format::SevenZ
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["7z"]
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c]]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "7z"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_7z(opts.input(), opts.output(), ex)
+    }
+
+    fn entries(&self, opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        let file = File::open(opts.input())?;
+        let len = file.metadata()?.len();
+        let reader = SevenZReader::new(file, len, sevenz_password(opts.password.as_deref()))
+            .map_err(|e| sevenz_error(opts.input(), "Failed to open 7Z archive", e))?;
+        let anti_items = sevenz_anti_items(reader.archive());
+        Ok(reader.archive().files.iter()
+            .filter(|e| !(anti_items && e.is_anti_item()))
+            .map(|e| EntryInfo {
+                path: e.name().to_string(),
+                size: e.size(),
+                mtime: e.has_last_modified_date.then(|| e.last_modified_date().to_unix_time()),
+                is_dir: e.is_directory(),
+            })
+            .collect())
+    }
+
+    fn compress(&self, opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+        compress_7z(opts, filter, log)
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn needs_seek(&self) -> bool {
+        true
+    }
+
+    fn extracts_single_entry(&self) -> bool {
+        true
+    }
+}
+
+struct RarCodec;
+
+impl Codec for RarCodec {
+    fn format(&self) -> Format {
+        Format::Rar
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rar"]
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[b"Rar!"]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "rar (unrar)"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_rar(opts.input(), opts.output(), ex)
+    }
+
+    fn needs_seek(&self) -> bool {
+        true
+    }
+}
+
+/// Image ISO 9660 : son descripteur de volume est au secteur 16, trop loin pour `sniff`
+/// (voir `sniff_format`)
+struct IsoCodec;
+
+impl Codec for IsoCodec {
+    fn format(&self) -> Format {
+        Format::Iso
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["iso"]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "iso9660"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_iso(opts.input(), opts.output(), opts.buffer_size, ex)
+    }
+
+    fn entries(&self, opts: &DecompressOptions) -> io::Result<Vec<EntryInfo>> {
+        iso_entries(opts.input(), opts.buffer_size)
+    }
+
+    fn needs_seek(&self) -> bool {
+        true
+    }
+}
+
+struct CabCodec;
+
+impl Codec for CabCodec {
+    fn format(&self) -> Format {
+        Format::Cab
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["cab"]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "cab"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, _ex: &mut Extractor) -> io::Result<()> {
+        decompress_cab(opts.input(), opts.output())
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn needs_seek(&self) -> bool {
+        true
+    }
+}
+
+struct DebCodec;
+
+impl Codec for DebCodec {
+    fn format(&self) -> Format {
+        Format::Deb
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["deb"]
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[b"!<arch>\n"]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "ar → {control,data}.tar.*"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_deb(File::open(opts.input())?, opts.output(), ex)
+    }
+
+    fn decompress_reader(&self, reader: Box<dyn BufRead + '_>, _sniffed: bool, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_deb(reader, opts.output(), ex)
+    }
+}
+
+struct RpmCodec;
+
+impl Codec for RpmCodec {
+    fn format(&self) -> Format {
+        Format::Rpm
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rpm"]
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        &[&[0xed, 0xab, 0xee, 0xdb]]
+    }
+
+    fn decoder_chain(&self) -> &'static str {
+        "rpm → cpio"
+    }
+
+    fn decompress(&self, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_rpm(BufReader::new(File::open(opts.input())?), opts.output(), ex)
+    }
+
+    fn decompress_reader(&self, reader: Box<dyn BufRead + '_>, _sniffed: bool, opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<()> {
+        decompress_rpm(reader, opts.output(), ex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_extensions_resolve_to_a_codec() {
+        let legacy = [
+            ("zip", Format::Zip),
+            ("rar", Format::Rar),
+            ("7z", Format::SevenZ),
+            ("iso", Format::Iso),
+            ("tar", Format::Tar),
+            ("gz", Format::Gz),
+            ("tgz", Format::TarGz),
+            ("tar.gz", Format::TarGz),
+            ("bz2", Format::Bz2),
+            ("tar.bz2", Format::TarBz2),
+            ("xz", Format::Xz),
+            ("tar.xz", Format::TarXz),
+            ("zst", Format::Zst),
+            ("zstd", Format::Zst),
+            ("tar.zst", Format::TarZst),
+            ("lzma", Format::Lzma),
+            ("br", Format::Brotli),
+            ("lz4", Format::Lz4),
+            ("cab", Format::Cab),
+            ("sharky", Format::Sharky),
+        ];
+        for (ext, format) in legacy {
+            let resolved = Format::from_extension(Path::new(&format!("archive.{}", ext)));
+            assert_eq!(resolved, Some(format), "{}", ext);
+            assert_eq!(format.codec().format(), format);
+        }
+        // Une extension inconnue reste lue comme le format sharky
+        assert_eq!(Format::from_path(Path::new("archive.bin")), Format::Sharky);
+    }
+
+    #[test]
+    fn every_registered_name_and_magic_resolves_to_its_codec() {
+        for codec in codecs() {
+            assert_eq!(codec.format().name(), codec.extensions()[0]);
+            for name in codec.extensions() {
+                assert_eq!(Format::from_name(name), Some(codec.format()), "{}", name);
+            }
+            for magic in codec.magic() {
+                assert_eq!(Format::from_magic(magic), Some(codec.format()), "{:?}", magic);
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Erreurs de la bibliothèque.
+//!
+//! Le code interne travaille en `io::Result` (lecteurs, écrivains, callbacks tar) ; un
+//! [`SharkyError`] y voyage emballé dans un `io::Error` et ressort intact à la frontière
+//! publique grâce à `From<io::Error>`.
+
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+/// Erreur renvoyée par les fonctions publiques de sharky
+#[derive(Debug, Error)]
+pub enum SharkyError {
+    /// Format, méthode ou option que sharky ne sait pas traiter
+    #[error("{0}")]
+    UnsupportedFormat(String),
+
+    /// Entrée d'archive dont le chemin sort du répertoire de destination (Zip Slip)
+    #[error("Refusing to extract '{}': path escapes the output directory", .path.display())]
+    PathTraversal { path: PathBuf },
+
+    /// Lien symbolique dont la cible sort du répertoire de destination
+    #[error("Refusing to create symlink '{}' → '{}': target escapes the output directory", .path.display(), .target.display())]
+    SymlinkTraversal { path: PathBuf, target: PathBuf },
+
+    /// Archive de sortie déjà présente, remplacée seulement avec `force`
+    #[error("Refusing to overwrite existing archive '{}' ({size} bytes, modified {modified}); use --force to replace it", .path.display())]
+    OutputExists { path: PathBuf, size: u64, modified: String },
+
+    /// Archive de sortie qui serait le fichier d'entrée lui-même
+    #[error("Refusing to compress '{}' onto itself", .path.display())]
+    OutputIsInput { path: PathBuf },
+
+    /// Répertoire d'extraction qui contient l'archive, accepté seulement avec `force`
+    #[error("Refusing to extract '{}' into '{}', which contains the archive; use --force to extract anyway", .input.display(), .output.display())]
+    OutputContainsInput { input: PathBuf, output: PathBuf },
+
+    /// Nombre d'entrées extraites différent de celui annoncé par l'index de l'archive
+    #[error("{format} archive declares {declared} entries but {extracted} were extracted")]
+    EntryCountMismatch { format: &'static str, declared: usize, extracted: usize },
+
+    /// Fichiers extraits dont le contenu ne correspond pas à leur `SHARKY.sha256`
+    #[error("{failed} file(s) failed embedded checksum verification")]
+    ChecksumFailures { failed: usize },
+
+    /// Entrées laissées de côté par `--keep-going`
+    #[error("{failed} entry(ies) could not be extracted")]
+    ExtractionFailures { failed: usize },
+
+    /// `--verify` : somme du fichier extrait différente de celle attendue
+    #[error("Checksum mismatch for '{}': expected {expected}, got {got}", .path.display())]
+    ChecksumMismatch { path: PathBuf, expected: String, got: String },
+
+    /// Entrée (ou archive) chiffrée, sans mot de passe fourni
+    #[error("'{0}' is encrypted; pass --password to extract it")]
+    PasswordRequired(String),
+
+    /// Mot de passe refusé pour cette entrée (ou archive)
+    #[error("Wrong password for '{0}'")]
+    WrongPassword(String),
+
+    /// Volume d'un jeu RAR multi-volume introuvable
+    #[error("RAR volume '{}' not found; the multi-volume set is incomplete", .path.display())]
+    MissingVolume { path: PathBuf },
+
+    /// Erreur de la bibliothèque unrar
+    #[error("{context}: {source}")]
+    Rar {
+        context: String,
+        #[source]
+        source: unrar::error::UnrarError,
+    },
+
+    /// Erreur de sevenz-rust
+    #[error("{context}: {source}")]
+    SevenZ {
+        context: String,
+        #[source]
+        source: sevenz_rust::Error,
+    },
+
+    /// Erreur de la crate zip
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    /// Échec sur l'une des archives d'un `batch_extract`
+    #[error("{}: {source}", .path.display())]
+    InArchive {
+        path: PathBuf,
+        #[source]
+        source: Box<SharkyError>,
+    },
+
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl SharkyError {
+    /// Format (ou méthode) non pris en charge : voir `continue_on_unsupported`
+    pub fn is_unsupported(&self) -> bool {
+        match self {
+            SharkyError::UnsupportedFormat(_) => true,
+            SharkyError::SevenZ { source, .. } => matches!(source, sevenz_rust::Error::UnsupportedCompressionMethod(_)),
+            SharkyError::Zip(e) => matches!(e, zip::result::ZipError::UnsupportedArchive(_)),
+            SharkyError::InArchive { source, .. } => source.is_unsupported(),
+            SharkyError::Io(e) => e.kind() == io::ErrorKind::Unsupported,
+            _ => false,
+        }
+    }
+
+    /// Code de sortie du binaire
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SharkyError::InArchive { source, .. } => source.exit_code(),
+            SharkyError::PathTraversal { .. } | SharkyError::SymlinkTraversal { .. } => 4,
+            _ if self.is_unsupported() => 3,
+            _ => 1,
+        }
+    }
+
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            SharkyError::PathTraversal { .. } | SharkyError::SymlinkTraversal { .. } => io::ErrorKind::InvalidData,
+            SharkyError::EntryCountMismatch { .. } => io::ErrorKind::InvalidData,
+            SharkyError::ChecksumFailures { .. } | SharkyError::ChecksumMismatch { .. } => io::ErrorKind::InvalidData,
+            SharkyError::OutputExists { .. } => io::ErrorKind::AlreadyExists,
+            SharkyError::OutputIsInput { .. } | SharkyError::OutputContainsInput { .. } => io::ErrorKind::InvalidInput,
+            SharkyError::PasswordRequired(_) | SharkyError::WrongPassword(_) => io::ErrorKind::PermissionDenied,
+            SharkyError::MissingVolume { .. } => io::ErrorKind::NotFound,
+            SharkyError::Io(e) => e.kind(),
+            _ if self.is_unsupported() => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl From<io::Error> for SharkyError {
+    fn from(e: io::Error) -> Self {
+        // Un SharkyError emballé par `From<SharkyError> for io::Error` est rendu tel quel
+        if e.get_ref().is_some_and(|inner| inner.is::<SharkyError>()) {
+            return *e.into_inner().unwrap().downcast::<SharkyError>().unwrap();
+        }
+        SharkyError::Io(e)
+    }
+}
+
+impl From<SharkyError> for io::Error {
+    fn from(e: SharkyError) -> Self {
+        match e {
+            SharkyError::Io(e) => e,
+            other => io::Error::new(other.kind(), other),
+        }
+    }
+}
@@ -0,0 +1,6009 @@
+//! Compression et extraction multi-format (ZIP, RAR, 7Z, ISO, TAR, GZ, BZ2, XZ, ZSTD, LZMA, BROTLI, LZFSE, DEB, RPM).
+//!
+//! Points d'entrée : [`compress`] avec [`CompressOptions`], [`decompress`] avec [`DecompressOptions`].
+//! Le binaire `sharky` n'est qu'une surcouche clap autour de cette bibliothèque.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Write, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    sync::{atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use walkdir::{DirEntry, WalkDir};
+
+use bzip2::read::BzDecoder;
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use flate2::bufread::GzDecoder as GzBufDecoder;
+use flate2::write::GzEncoder;
+use bzip2::write::BzEncoder;
+use tar::{Archive, Builder, Header};
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+use zip::ZipArchive;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+use zstd::stream::read::Decoder as ZstdDecoder;
+use zstd::stream::write::Encoder as ZstdEncoder;
+use unrar::Archive as UnrarArchive;
+use sevenz_rust::lzma::LZMA2Options;
+use sevenz_rust::{SevenZArchiveEntry, SevenZMethod, SevenZMethodConfiguration, SevenZReader, SevenZWriter};
+use brotli::Decompressor as BrotliDecoder;
+use lzfse_rust::{LzfseRingDecoder, LzfseRingEncoder};
+use sha2::{Digest, Sha256};
+
+mod codec;
+mod error;
+
+use codec::{Codec, codecs};
+pub use error::SharkyError;
+
+/// Réglages de la compression (`sharky -c`)
+#[derive(Debug, Clone)]
+pub struct CompressOptions {
+    /// Fichier ou répertoire à archiver
+    pub input: PathBuf,
+    /// Archive produite, `-` pour la sortie standard
+    pub output: PathBuf,
+    /// Format de sortie (sharky, tar, tar.gz, ..., zip, 7z) ; sinon déduit de l'extension
+    pub format: Option<String>,
+    /// Niveau abstrait 0–9 ramené sur l'échelle de chaque codec (voir [`level_options`]) ;
+    /// les options propres à un codec données explicitement l'emportent
+    pub level: Option<u32>,
+    /// Niveau Zstd (0–22)
+    pub zstd_level: i32,
+    /// Niveau XZ preset (0–9)
+    pub xz_preset: u32,
+    /// Qualité Brotli (0–11)
+    pub brotli_quality: u32,
+    /// Fenêtre Brotli en log2 d'octets (10–24)
+    pub brotli_window: u32,
+    /// Format sharky : abandonner la couche xz si elle ne réduit pas un échantillon de l'entrée
+    pub smart_pipeline: bool,
+    /// Threads des encodeurs zstd et xz (0 = autant que de cœurs)
+    pub threads: u32,
+    /// Correspondances longue distance zstd, fenêtre en log2 d'octets (10–31)
+    pub zstd_long: Option<u32>,
+    /// Fichier dictionnaire Zstd
+    pub dict: Option<PathBuf>,
+    /// Motifs glob d'exclusion : sans `/`, comparés au nom de chaque composant ; avec `/`,
+    /// au chemin relatif à l'entrée
+    pub exclude: Vec<String>,
+    /// Exclure les fichiers de sauvegarde/temporaires d'éditeurs
+    pub exclude_backups: bool,
+    /// Fichier de motifs au format gitignore, ancrés à la racine de l'entrée
+    pub ignore_file: Option<PathBuf>,
+    /// Fichiers écartés au-delà de cette taille, en octets
+    pub exclude_larger_than: Option<u64>,
+    /// Fichiers écartés en deçà de cette taille, en octets
+    pub exclude_smaller_than: Option<u64>,
+    /// Profondeur maximale de parcours
+    pub max_depth: Option<usize>,
+    /// Archiver le contenu du répertoire sans le dossier racine
+    pub no_wrap: bool,
+    /// Nom de la racine dans l'archive (ou du fichier seul archivé) à la place du nom de l'entrée
+    pub archive_root: Option<PathBuf>,
+    /// Méthode 7z (lzma2, lzma)
+    pub sevenz_method: String,
+    /// Mot de passe des sorties 7z (contenu et en-tête chiffrés en AES-256)
+    pub password: Option<String>,
+    /// Regrouper les fichiers similaires dans le flux tar
+    pub similarity_sort: bool,
+    /// Supprimer la sortie si elle n'est pas plus petite que l'entrée
+    pub compress_if_smaller: bool,
+    /// Journal JSON lines
+    pub log_file: Option<PathBuf>,
+    /// Stocker les longues plages de zéros comme trous (tar GNU sparse)
+    pub detect_sparse: bool,
+    /// Taille minimale d'une plage de zéros pour `detect_sparse`
+    pub sparse_threshold: u64,
+    /// Horodatage commun à toutes les entrées
+    pub archive_time: Option<ArchiveTime>,
+    /// Filtres externes `EXT:COMMANDE` (sorties tar)
+    pub file_transform: Vec<String>,
+    /// SHA-256 de chaque fichier dans un en-tête PAX `SHARKY.sha256` (sorties tar)
+    pub embed_checksums: bool,
+    /// Encodage des noms d'entrées des zip
+    pub zip_name_encoding: ZipNameEncoding,
+    /// Zip : extensions (sans point, casse ignorée) des fichiers stockés sans compression
+    pub store_extensions: Vec<String>,
+    /// Compression reprenable depuis `<sortie>.checkpoint` (tar, tar.zst)
+    pub resume: bool,
+    /// Ajouter les entrées à l'archive tar existante au lieu de la remplacer
+    pub append: bool,
+    /// Remplacer une archive de sortie déjà présente
+    pub force: bool,
+    /// Ignorer (en les signalant) les fichiers disparus ou illisibles depuis le parcours
+    pub skip_errors: bool,
+    /// Nouvelles tentatives d'ouverture ou de lecture d'un fichier source après une erreur passagère
+    pub io_retries: u32,
+    /// Suivre les liens symboliques au lieu de les archiver comme liens (zip et 7z les suivent toujours)
+    pub dereference: bool,
+    /// Manifeste des entrées archivées : `sha256  taille  chemin` par ligne
+    pub manifest: Option<PathBuf>,
+    /// Bilan JSON (tailles, ratio, durée, entrées, format, niveau), `-` pour la sortie standard
+    pub stats_json: Option<PathBuf>,
+    /// Rien d'autre que les erreurs et le bilan final (sur stderr)
+    pub quiet: bool,
+    /// Chaque entrée archivée sur sa propre ligne, à la place de la barre de progression
+    pub verbose: bool,
+    /// Taille du tampon en octets
+    pub buffer_size: usize,
+    /// Options de niveau données explicitement, signalées si le format les ignore
+    pub codec_flags_given: Vec<&'static str>,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        CompressOptions {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            format: None,
+            level: None,
+            zstd_level: 19,
+            xz_preset: 9,
+            brotli_quality: 11,
+            brotli_window: 22,
+            smart_pipeline: false,
+            threads: 1,
+            zstd_long: None,
+            dict: None,
+            exclude: Vec::new(),
+            exclude_backups: false,
+            ignore_file: None,
+            exclude_larger_than: None,
+            exclude_smaller_than: None,
+            max_depth: None,
+            no_wrap: false,
+            archive_root: None,
+            sevenz_method: "lzma2".to_string(),
+            password: None,
+            similarity_sort: false,
+            compress_if_smaller: false,
+            log_file: None,
+            detect_sparse: false,
+            sparse_threshold: 4096,
+            archive_time: None,
+            file_transform: Vec::new(),
+            embed_checksums: false,
+            zip_name_encoding: ZipNameEncoding::Utf8,
+            store_extensions: Vec::new(),
+            resume: false,
+            append: false,
+            skip_errors: false,
+            io_retries: 3,
+            dereference: false,
+            force: false,
+            manifest: None,
+            stats_json: None,
+            quiet: false,
+            verbose: false,
+            buffer_size: 4 * 1024 * 1024,
+            codec_flags_given: Vec::new(),
+        }
+    }
+}
+
+impl CompressOptions {
+    fn input(&self) -> &Path {
+        &self.input
+    }
+
+    fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// `-o -` : l'archive est écrite sur la sortie standard
+    pub fn writes_stdout(&self) -> bool {
+        self.output.as_os_str() == "-"
+    }
+
+    /// L'archive ou `--stats-json -` occupent la sortie standard : messages sur stderr
+    fn stdout_taken(&self) -> bool {
+        self.writes_stdout() || self.stats_json.as_deref() == Some(Path::new("-"))
+    }
+
+    /// `--quiet` et `--verbose` se passent de barre de progression
+    fn hides_progress(&self) -> bool {
+        self.quiet || self.verbose
+    }
+}
+
+/// Réglages de l'extraction (`sharky -d`, `--batch-extract`, `--list`)
+#[derive(Debug, Clone)]
+pub struct DecompressOptions {
+    /// Archive à lire, `-` pour l'entrée standard (répertoire pour `batch_extract`)
+    pub input: PathBuf,
+    /// Répertoire de destination
+    pub output: PathBuf,
+    /// Format imposé (ex. `tar.gz`), prioritaire sur l'extension et la signature
+    pub input_format_hint: Option<String>,
+    /// `batch_extract` : ignorer les archives de format non pris en charge
+    pub continue_on_unsupported: bool,
+    /// Conversion des fins de ligne des fichiers texte extraits
+    pub normalize_line_endings: Option<LineEnding>,
+    /// Journal JSON lines
+    pub log_file: Option<PathBuf>,
+    /// ISO : extraire toutes les versions d'un fichier
+    pub iso_all_versions: bool,
+    /// Données après la fin d'un flux compressé : erreur au lieu d'un avertissement
+    pub strict: bool,
+    /// Extraire à plat, les séparateurs de chemin remplacés par ce délimiteur
+    pub flatten_prefix: Option<String>,
+    /// Tar : ne pas restaurer les permissions et dates
+    pub no_preserve_permissions: bool,
+    /// Mot de passe des entrées chiffrées (zip, 7z)
+    pub password: Option<String>,
+    /// Parcourir et vérifier les entrées sans rien écrire, en affichant les cibles
+    pub dry_run: bool,
+    /// Tester l'archive : décoder toutes les entrées sans rien écrire ni afficher de cible
+    /// (comme `unzip -t`, `gzip -t`) ; une erreur signale la première corruption
+    pub test: bool,
+    /// Zip : threads d'extraction (0 = autant que de cœurs)
+    pub threads: u32,
+    /// Composants de tête retirés du chemin des entrées ; les entrées plus courtes sont ignorées
+    pub strip_components: usize,
+    /// Motifs glob des entrées à extraire (toutes si vide), même syntaxe que `exclude`
+    pub include: Vec<String>,
+    /// Motifs glob des entrées à ne pas extraire
+    pub exclude: Vec<String>,
+    /// Extraire même dans un répertoire qui contient l'archive (avec un avertissement)
+    pub force: bool,
+    /// Zip, 7z, tar : n'extraire que cette entrée (chemin dans l'archive), aussi vers `-o -`
+    pub extract_entry: Option<String>,
+    /// Relire chaque fichier extrait et comparer sa somme à celle des données de l'archive
+    pub verify: Option<VerifyMode>,
+    /// Traitement des fichiers déjà présents à la place d'une entrée
+    pub existing: ExistingFiles,
+    /// Entrée impossible à écrire : avertir et poursuivre, puis échouer avec un bilan à la fin
+    pub keep_going: bool,
+    /// Nouvelles tentatives de lecture d'une entrée après une erreur passagère
+    pub io_retries: u32,
+    /// Bilan JSON de l'extraction, `-` pour la sortie standard
+    pub stats_json: Option<PathBuf>,
+    /// Rien d'autre que les erreurs et le bilan final (sur stderr)
+    pub quiet: bool,
+    /// Chaque entrée extraite sur sa propre ligne, à la place de la barre de progression
+    pub verbose: bool,
+    /// Taille du tampon en octets
+    pub buffer_size: usize,
+}
+
+impl Default for DecompressOptions {
+    fn default() -> Self {
+        DecompressOptions {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            input_format_hint: None,
+            continue_on_unsupported: false,
+            normalize_line_endings: None,
+            log_file: None,
+            iso_all_versions: false,
+            strict: false,
+            flatten_prefix: None,
+            no_preserve_permissions: false,
+            password: None,
+            dry_run: false,
+            test: false,
+            threads: 1,
+            strip_components: 0,
+            force: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            extract_entry: None,
+            verify: None,
+            existing: ExistingFiles::Overwrite,
+            keep_going: false,
+            io_retries: 3,
+            stats_json: None,
+            quiet: false,
+            verbose: false,
+            buffer_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl DecompressOptions {
+    fn input(&self) -> &Path {
+        &self.input
+    }
+
+    fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// `-o -` : le flux mono-fichier décompressé part sur la sortie standard
+    pub fn writes_stdout(&self) -> bool {
+        self.output.as_os_str() == "-"
+    }
+
+    /// Le flux décompressé ou `--stats-json -` occupent la sortie standard : messages sur stderr
+    fn stdout_taken(&self) -> bool {
+        self.writes_stdout() || self.stats_json.as_deref() == Some(Path::new("-"))
+    }
+
+    /// `dry_run` ou `test` : les données sont lues puis jetées
+    fn writes_nothing(&self) -> bool {
+        self.dry_run || self.test
+    }
+
+    /// `--quiet` et `--verbose` se passent de barre de progression
+    fn hides_progress(&self) -> bool {
+        self.quiet || self.verbose
+    }
+}
+
+/// Fins de ligne cibles pour `--normalize-line-endings` (`auto` = celles de la plateforme)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    Crlf,
+    Lf,
+    Auto,
+}
+
+/// Encodage des noms pour `--zip-name-encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ZipNameEncoding {
+    Utf8,
+    Cp437,
+}
+
+/// Somme calculée par `--verify` : CRC-32 seul, ou SHA-256 en plus avec un manifeste `chemin  somme`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VerifyMode {
+    Crc32,
+    Sha256,
+}
+
+/// Fichier déjà présent à l'extraction : remplacé (par défaut), gardé (`--no-clobber`)
+/// ou renommé en `nom~` avant l'écriture (`--backup`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistingFiles {
+    Overwrite,
+    NoClobber,
+    Backup,
+}
+
+/// Source de l'horodatage commun pour `--archive-time`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ArchiveTime {
+    Newest,
+    Oldest,
+}
+
+/// Message d'état : sur stderr quand l'archive elle-même part sur stdout (`-o -`), tu par `--quiet`
+macro_rules! status {
+    ($opts:expr, $($arg:tt)*) => {
+        if $opts.quiet {
+        } else if $opts.stdout_taken() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// Bilan final : gardé par `--quiet`, mais sur stderr pour laisser la sortie standard vide
+macro_rules! summary {
+    ($opts:expr, $($arg:tt)*) => {
+        if $opts.quiet || $opts.stdout_taken() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// Options et compteurs partagés par les boucles d'extraction
+struct Extractor<'a> {
+    opts: &'a DecompressOptions,
+    converted: usize,
+    /// Fichiers vérifiés / en échec contre leur en-tête `SHARKY.sha256`
+    checksums_verified: usize,
+    checksum_failures: usize,
+    log: EventLog,
+    /// Barres regroupées sous la progression globale de `--batch-extract`
+    multi: Option<MultiProgress>,
+    /// Noms déjà produits par `--flatten-prefix`, pour éviter les collisions
+    flattened: HashSet<PathBuf>,
+    /// Sélection `--include` / `--exclude` des entrées
+    selection: EntrySelection,
+    /// Entrées en échec laissées de côté (`--keep-going`, ISO)
+    failures: Vec<(PathBuf, io::Error)>,
+    /// Flux mono-fichier écrits et leurs octets décompressés, pour `--stats-json`
+    streams: AtomicUsize,
+    stream_bytes: AtomicU64,
+    /// Tar : dates des répertoires extraits, posées en fin d'archive par `restore_dir_times`
+    dir_times: Vec<(PathBuf, i64)>,
+    /// `--verify sha256` : lignes `chemin  somme`, affichées d'un bloc par `finish`
+    /// (les threads de `extract_zip_parallel` les ajoutent en parallèle)
+    sha256_manifest: Mutex<Vec<(PathBuf, String)>>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(opts: &'a DecompressOptions) -> io::Result<Self> {
+        let log = EventLog::open(opts.log_file.as_deref())?;
+        Ok(Extractor {
+            opts,
+            converted: 0,
+            checksums_verified: 0,
+            checksum_failures: 0,
+            log,
+            multi: None,
+            flattened: HashSet::new(),
+            selection: EntrySelection::new(&opts.include, &opts.exclude)?,
+            failures: Vec::new(),
+            streams: AtomicUsize::new(0),
+            stream_bytes: AtomicU64::new(0),
+            dir_times: Vec::new(),
+            sha256_manifest: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Résultat de l'extraction d'une entrée : avec `--keep-going`, un échec est consigné
+    /// pour le bilan de `finish` au lieu d'interrompre l'archive. Les refus de chemin
+    /// (Zip Slip, lien sortant) restent fatals.
+    fn entry_result(&mut self, path: &Path, result: io::Result<()>) -> io::Result<()> {
+        match result {
+            Err(e) if self.opts.keep_going && !is_path_refusal(&e) => {
+                self.record_failure(path, e);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    /// `--extract-entry` : copie l'entrée `name` sur stdout (`-o -`) ou sous `output`
+    fn extract_named<R: Read + ?Sized>(&mut self, reader: &mut R, output: &Path, name: &str, crc: Option<u32>) -> io::Result<()> {
+        if self.opts.writes_stdout() {
+            io::copy(reader, &mut io::stdout().lock())?;
+            return Ok(());
+        }
+        let Some(outpath) = self.entry_path(output, Path::new(name), false)? else { return Ok(()) };
+        self.create_parent(&outpath)?;
+        self.write_file_crc(reader, &outpath, crc)
+    }
+
+    fn record_failure(&mut self, path: &Path, e: io::Error) {
+        eprintln!("Warning: Failed to extract '{}': {}", path.display(), e);
+        self.failures.push((path.to_path_buf(), e));
+    }
+
+    /// Chemin de sortie d'une entrée, `None` si elle n'est pas à extraire : écartée par
+    /// `target_path`, ou fichier déjà présent gardé par `--no-clobber`
+    fn entry_path(&mut self, output: &Path, entry: &Path, is_dir: bool) -> io::Result<Option<PathBuf>> {
+        let Some(path) = self.target_path(output, entry, is_dir)? else { return Ok(None) };
+        if !is_dir && !self.make_room(&path)? {
+            return Ok(None);
+        }
+        if self.opts.verbose {
+            status!(self.opts, "{}", entry.display());
+        }
+        Ok(Some(path))
+    }
+
+    /// Chemin vérifié par `sanitize_entry_path`, `None` si `--strip-components` ne lui laisse
+    /// rien ou si `--include`/`--exclude` l'écartent. Avec `--flatten-prefix`, les répertoires
+    /// se confondent avec `output` et les fichiers y sont posés à plat.
+    fn target_path(&mut self, output: &Path, entry: &Path, is_dir: bool) -> io::Result<Option<PathBuf>> {
+        if !self.selection.selects(entry, is_dir) {
+            return Ok(None);
+        }
+        let Some(path) = self.stripped_path(output, entry)? else { return Ok(None) };
+        check_symlink_parents(output, &path, entry, is_dir)?;
+        let Some(delim) = &self.opts.flatten_prefix else { return Ok(Some(path)) };
+        if is_dir {
+            return Ok(Some(output.to_path_buf()));
+        }
+        let flat = flatten_name(path.strip_prefix(output).unwrap_or(&path), delim);
+        let mut candidate = output.join(&flat);
+        // Un nom contenant déjà le délimiteur peut retomber sur un autre chemin aplati
+        let mut n = 1;
+        while self.flattened.contains(&candidate) {
+            n += 1;
+            let stem = Path::new(&flat).file_stem().map_or(flat.clone(), |s| s.to_string_lossy().into_owned());
+            candidate = output.join(match Path::new(&flat).extension() {
+                Some(ext) => format!("{}~{}.{}", stem, n, ext.to_string_lossy()),
+                None => format!("{}~{}", stem, n),
+            });
+        }
+        if n > 1 {
+            eprintln!("Warning: '{}' flattens to an existing name, extracted as {:?}", entry.display(), candidate);
+        }
+        self.flattened.insert(candidate.clone());
+        Ok(Some(candidate))
+    }
+
+    /// Chemin vérifié, privé de ses `strip_components` premiers composants
+    fn stripped_path(&self, output: &Path, entry: &Path) -> io::Result<Option<PathBuf>> {
+        let path = sanitize_entry_path(output, entry)?;
+        if self.opts.strip_components == 0 {
+            return Ok(Some(path));
+        }
+        let kept: PathBuf = path.strip_prefix(output).unwrap_or(&path).components().skip(self.opts.strip_components).collect();
+        Ok((!kept.as_os_str().is_empty()).then(|| output.join(kept)))
+    }
+
+    /// Fichier déjà présent à la place de `path` : `false` s'il faut le garder (`--no-clobber`),
+    /// renommé en `nom~` avec `--backup`. Les répertoires existants sont toujours réutilisés.
+    fn make_room(&self, path: &Path) -> io::Result<bool> {
+        let Ok(meta) = fs::symlink_metadata(path) else { return Ok(true) };
+        if meta.is_dir() {
+            return Ok(true);
+        }
+        if self.opts.existing == ExistingFiles::Overwrite {
+            // `File::create` suivrait un lien déjà présent (extrait plus tôt) : il est remplacé
+            if meta.file_type().is_symlink() && !self.opts.writes_nothing() {
+                fs::remove_file(path)?;
+            }
+            return Ok(true);
+        }
+        if self.opts.existing == ExistingFiles::NoClobber {
+            eprintln!("Skipping existing file {:?} (--no-clobber)", path);
+            return Ok(false);
+        }
+        let mut backup = path.as_os_str().to_owned();
+        backup.push("~");
+        if self.opts.writes_nothing() {
+            self.announce(format!("Would back up {} → {}", path.display(), Path::new(&backup).display()));
+        } else {
+            fs::rename(path, &backup)?;
+        }
+        Ok(true)
+    }
+
+    /// Cible annoncée par `dry_run` ; `test` n'affiche rien
+    fn announce(&self, msg: String) {
+        if !self.opts.test {
+            println!("{}", msg);
+        }
+    }
+
+    /// Rattache une barre de progression à l'affichage groupé s'il existe
+    fn progress(&self, pb: ProgressBar) -> ProgressBar {
+        if self.opts.hides_progress() {
+            pb.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        match &self.multi {
+            Some(multi) => multi.add(pb),
+            None => pb,
+        }
+    }
+
+    /// Écrit les données d'une entrée dans `outpath` et la consigne dans `--log-file`
+    fn write_file<R: Read + ?Sized>(&mut self, reader: &mut R, outpath: &Path) -> io::Result<()> {
+        self.write_file_crc(reader, outpath, None)
+    }
+
+    /// `write_file` pour une entrée dont l'archive donne le CRC-32 (zip)
+    fn write_file_crc<R: Read + ?Sized>(&mut self, reader: &mut R, outpath: &Path, crc: Option<u32>) -> io::Result<()> {
+        let (read, result) = self.copy_entry(reader, outpath, crc);
+        let result = result.map(|converted| self.converted += usize::from(converted));
+        let written = fs::metadata(outpath).map(|m| m.len()).unwrap_or(0);
+        self.log.record("extract", outpath, read, written, &result)?;
+        result
+    }
+
+    /// Écrit l'entrée puis, avec `--verify`, relit le fichier et compare sa somme à celle des
+    /// octets lus dans l'archive (et au CRC-32 `crc` s'il est connu). Renvoie le nombre d'octets
+    /// lus et, en cas de succès, si les fins de ligne ont été converties.
+    fn copy_entry<R: Read + ?Sized>(&self, reader: &mut R, outpath: &Path, crc: Option<u32>) -> (u64, io::Result<bool>) {
+        let reader = &mut RetryReader { inner: reader, retries: self.opts.io_retries };
+        let Some(mode) = self.opts.verify else {
+            let mut counted = CountingReader { inner: reader, count: 0 };
+            let result = self.write_entry(&mut counted, outpath);
+            return (counted.count, result);
+        };
+        let mut hashing = FingerprintReader { inner: reader, fingerprint: Fingerprint::new(mode) };
+        let mut counted = CountingReader { inner: &mut hashing, count: 0 };
+        let result = self.write_entry(&mut counted, outpath);
+        let read = counted.count;
+        let archived = hashing.fingerprint.finish();
+        let mismatch = |expected: &str, got: &str| -> io::Error {
+            SharkyError::ChecksumMismatch { path: outpath.to_path_buf(), expected: expected.to_string(), got: got.to_string() }.into()
+        };
+        // Le lecteur zip signale lui-même un CRC faux (InvalidData) une fois l'entrée lue
+        if let Some(crc) = crc.map(|crc| format!("{:08x}", crc))
+            && crc != archived.crc
+            && !matches!(&result, Err(e) if e.kind() != io::ErrorKind::InvalidData)
+        {
+            return (read, Err(mismatch(&crc, &archived.crc)));
+        }
+        let converted = match result {
+            Ok(converted) => converted,
+            Err(e) => return (read, Err(e)),
+        };
+        // Un fichier converti diffère volontairement des données de l'archive
+        if converted || self.opts.writes_nothing() {
+            return (read, Ok(converted));
+        }
+        let on_disk = match fingerprint_file(outpath, mode) {
+            Ok(on_disk) => on_disk,
+            Err(e) => return (read, Err(e)),
+        };
+        if on_disk != archived {
+            return (read, Err(mismatch(&archived.to_string(), &on_disk.to_string())));
+        }
+        if let Some(sha256) = on_disk.sha256 {
+            self.sha256_manifest.lock().unwrap().push((outpath.to_path_buf(), sha256));
+        }
+        (read, Ok(false))
+    }
+
+    /// Fichier de sortie ; avec `dry_run`, la cible est affichée et les données jetées
+    fn create_file(&self, outpath: &Path) -> io::Result<Box<dyn Write>> {
+        if self.opts.writes_nothing() {
+            self.announce(format!("Would write {}", outpath.display()));
+            return Ok(Box::new(io::sink()));
+        }
+        Ok(Box::new(File::create(outpath)?))
+    }
+
+    /// Sortie d'un flux mono-fichier : `path`, ou la sortie standard avec `-o -`
+    fn create_stream_output(&self, path: &Path) -> io::Result<Box<dyn Write + '_>> {
+        let out: Box<dyn Write> = if self.opts.writes_stdout() && !self.opts.writes_nothing() {
+            Box::new(io::stdout().lock())
+        } else if !self.make_room(path)? {
+            Box::new(io::sink())
+        } else {
+            self.create_parent(path)?;
+            self.create_file(path)?
+        };
+        self.streams.fetch_add(1, Ordering::Relaxed);
+        Ok(Box::new(CountingWriter { inner: out, count: &self.stream_bytes }))
+    }
+
+    /// Message de fin d'un flux mono-fichier, sur stderr quand les données sont sur stdout
+    fn stream_done(&self, label: &str, path: &Path) {
+        if self.opts.quiet {
+            return;
+        }
+        if self.opts.writes_stdout() {
+            eprintln!("{} decompression done: <stdout>", label);
+        } else {
+            status!(self.opts, "{} decompression done: {:?}", label, path);
+        }
+    }
+
+    /// Répertoire d'une entrée de l'archive (affiché seulement avec `dry_run`)
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        if self.opts.writes_nothing() {
+            self.announce(format!("Would create directory {}", path.display()));
+            return Ok(());
+        }
+        fs::create_dir_all(path)
+    }
+
+    /// Répertoire parent d'un fichier à écrire
+    fn create_parent(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if !self.opts.writes_nothing() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+    }
+
+    /// Copie l'entrée en convertissant les fins de ligne si demandé et si
+    /// l'entrée ressemble à du texte. Renvoie `true` si des fins de ligne ont été converties.
+    fn write_entry<R: Read + ?Sized>(&self, reader: &mut R, outpath: &Path) -> io::Result<bool> {
+        let mut outfile = self.create_file(outpath)?;
+        let Some(mode) = self.opts.normalize_line_endings else {
+            io::copy(reader, &mut outfile)?;
+            return Ok(false);
+        };
+        let crlf = match mode {
+            LineEnding::Crlf => true,
+            LineEnding::Lf => false,
+            LineEnding::Auto => cfg!(windows),
+        };
+        let mut writer = BufWriter::new(outfile);
+        let changed = copy_normalized(reader, &mut writer, crlf)?;
+        writer.flush()?;
+        Ok(changed)
+    }
+
+    /// Extrait une entrée tar sous `output` : répertoire, lien symbolique, lien dur ou fichier
+    fn extract_tar_entry<R: Read>(&mut self, entry: &mut tar::Entry<'_, R>, output: &Path) -> io::Result<()> {
+        let path = entry.path()?.to_path_buf();
+        let kind = entry.header().entry_type();
+        let Some(outpath) = self.entry_path(output, &path, kind.is_dir())? else { return Ok(()) };
+        if kind.is_dir() {
+            self.create_dir(&outpath)?;
+            // Écrire les fichiers du répertoire remettrait sa date à jour : elle attend la fin
+            if let Ok(mtime) = entry.header().mtime() {
+                self.dir_times.push((outpath, mtime as i64));
+            }
+            return Ok(());
+        }
+        self.create_parent(&outpath)?;
+
+        if kind.is_symlink() || kind.is_hard_link() {
+            let target = entry.link_name()?.ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Link entry '{}' has no target", path.display()),
+            ))?.to_path_buf();
+            if kind.is_hard_link() {
+                // La cible d'un lien dur est un chemin de l'archive, déjà extrait
+                if !self.selection.selects(&target, false) {
+                    eprintln!("Warning: skipping hard link '{}': its target '{}' is not extracted", path.display(), target.display());
+                    return Ok(());
+                }
+                let Some(mut source) = self.stripped_path(output, &target)? else {
+                    eprintln!("Warning: skipping hard link '{}': its target '{}' was stripped", path.display(), target.display());
+                    return Ok(());
+                };
+                if let Some(delim) = &self.opts.flatten_prefix {
+                    source = output.join(flatten_name(source.strip_prefix(output).unwrap_or(&source), delim));
+                }
+                check_symlink_parents(output, &source, &target, true)?;
+                if self.opts.writes_nothing() {
+                    self.announce(format!("Would link {} → {}", outpath.display(), source.display()));
+                    return Ok(());
+                }
+                remove_existing_file(&outpath)?;
+                if fs::hard_link(&source, &outpath).is_err() {
+                    fs::copy(&source, &outpath)?;
+                }
+                return Ok(());
+            }
+            // Cible relative au répertoire du lien : elle ne doit pas sortir de `output`
+            sanitize_entry_path(output, &path.parent().unwrap_or(Path::new("")).join(&target)).map_err(|_| {
+                io::Error::from(SharkyError::SymlinkTraversal { path: path.to_path_buf(), target: target.to_path_buf() })
+            })?;
+            if self.opts.writes_nothing() {
+                self.announce(format!("Would link {} → {}", outpath.display(), target.display()));
+                return Ok(());
+            }
+            remove_existing_file(&outpath)?;
+            #[cfg(unix)]
+            return std::os::unix::fs::symlink(&target, &outpath);
+            #[cfg(not(unix))]
+            {
+                eprintln!("Warning: skipping symlink '{}' → '{}' (not supported on this platform)", path.display(), target.display());
+                return Ok(());
+            }
+        }
+
+        match pax_checksum(entry)? {
+            Some(expected) => {
+                let mut hashing = HashingReader { inner: entry, hasher: Sha256::new() };
+                self.write_file(&mut hashing, &outpath)?;
+                let actual = hex_digest(hashing.hasher);
+                if actual.eq_ignore_ascii_case(&expected) {
+                    self.checksums_verified += 1;
+                } else {
+                    self.checksum_failures += 1;
+                    eprintln!("Checksum mismatch for '{}': expected {}, got {}", path.display(), expected, actual);
+                }
+                self.restore_metadata(hashing.inner.header(), &outpath)
+            }
+            None => {
+                self.write_file(entry, &outpath)?;
+                self.restore_metadata(entry.header(), &outpath)
+            }
+        }
+    }
+
+    /// Tar : restaure le mode Unix et la date de modification de l'en-tête sur le fichier extrait
+    fn restore_metadata(&self, header: &Header, outpath: &Path) -> io::Result<()> {
+        self.set_metadata(outpath, header.mode().ok(), header.mtime().ok().map(|t| t as i64))
+    }
+
+    /// Tar : date des répertoires, une fois tout leur contenu écrit, les plus profonds d'abord
+    /// (comme `tar -p`)
+    fn restore_dir_times(&mut self) -> io::Result<()> {
+        let mut dirs = std::mem::take(&mut self.dir_times);
+        dirs.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+        for (path, mtime) in dirs {
+            self.set_metadata(&path, None, Some(mtime))?;
+        }
+        Ok(())
+    }
+
+    /// Mode Unix et date de modification d'un fichier extrait (sauf --no-preserve-permissions)
+    fn set_metadata(&self, outpath: &Path, mode: Option<u32>, mtime: Option<i64>) -> io::Result<()> {
+        if self.opts.no_preserve_permissions || self.opts.writes_nothing() {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(outpath, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        if let Some(mtime) = mtime {
+            filetime::set_file_mtime(outpath, filetime::FileTime::from_unix_time(mtime, 0))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.opts.normalize_line_endings.is_some() {
+            summary!(self.opts, "Line endings normalized in {} text file(s)", self.converted);
+        }
+        if self.checksums_verified + self.checksum_failures > 0 {
+            summary!(self.opts, "Verified {} embedded checksum(s)", self.checksums_verified);
+        }
+        let manifest = self.sha256_manifest.get_mut().unwrap();
+        manifest.sort();
+        for (path, sha256) in manifest.iter() {
+            summary!(self.opts, "{}  {}", path.display(), sha256);
+        }
+        if !self.failures.is_empty() {
+            eprintln!("Failed to extract {} entry(ies):", self.failures.len());
+            for (path, e) in &self.failures {
+                eprintln!("  {}: {}", path.display(), e);
+            }
+        }
+        if self.checksum_failures > 0 {
+            return Err(SharkyError::ChecksumFailures { failed: self.checksum_failures }.into());
+        }
+        if !self.failures.is_empty() {
+            return Err(SharkyError::ExtractionFailures { failed: self.failures.len() }.into());
+        }
+        self.log.finish()
+    }
+}
+
+/// Chemin d'entrée comparable pour `--extract-entry` : sans `./`, `/` de tête ni `/` final
+fn entry_key(name: &str) -> String {
+    name.split('/').filter(|c| !c.is_empty() && *c != ".").collect::<Vec<_>>().join("/")
+}
+
+fn entry_not_found(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("Entry '{}' not found in the archive", name))
+}
+
+fn entry_not_a_file(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("Entry '{}' is not a regular file", name))
+}
+
+/// Refus de sécurité sur le chemin d'une entrée, que `--keep-going` ne rattrape pas
+fn is_path_refusal(e: &io::Error) -> bool {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<SharkyError>())
+        .is_some_and(|e| matches!(e, SharkyError::PathTraversal { .. } | SharkyError::SymlinkTraversal { .. }))
+}
+
+/// Une entrée déjà présente (réextraction) empêcherait la création d'un lien
+fn remove_existing_file(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Compte les octets lus à travers un lecteur emprunté
+struct CountingReader<'r, R: Read + ?Sized> {
+    inner: &'r mut R,
+    count: u64,
+}
+
+impl<R: Read + ?Sized> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Erreur d'E/S passagère (système de fichiers réseau) que `--io-retries` retente ;
+/// les autres (`NotFound`, `PermissionDenied`, ...) échouent aussitôt
+fn is_transient(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+/// Relance `op` après une erreur passagère, `retries` fois au plus, avec une attente
+/// doublée à chaque tentative (50 ms, 100 ms, ... jusqu'à 1,6 s). Un `Interrupted` (EINTR)
+/// est relancé aussitôt, sans avertissement ni tentative décomptée, comme le fait `io::copy`.
+fn retry_io<T>(retries: u32, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) if is_transient(&e) && attempt < retries => {
+                let delay = Duration::from_millis(50 << attempt.min(5));
+                attempt += 1;
+                eprintln!("Warning: {}; retrying in {:?} ({}/{})", e, delay, attempt, retries);
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Lecteur dont chaque lecture est relancée par `retry_io` : une lecture en échec
+/// n'a rien consommé, la suivante reprend au même point
+struct RetryReader<R> {
+    inner: R,
+    retries: u32,
+}
+
+impl RetryReader<File> {
+    /// Fichier source ouvert, et relu, avec `retries` nouvelles tentatives
+    fn open(path: &Path, retries: u32) -> io::Result<Self> {
+        let inner = retry_io(retries, || File::open(path))?;
+        Ok(RetryReader { inner, retries })
+    }
+}
+
+impl<R: Read> Read for RetryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        retry_io(self.retries, || inner.read(buf))
+    }
+}
+
+/// Compte les octets écrits dans un compteur partagé
+struct CountingWriter<'c, W: Write> {
+    inner: W,
+    count: &'c AtomicU64,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Somme d'un flux pour `--verify` : CRC-32, et SHA-256 en mode `sha256`
+struct Fingerprint {
+    crc: crc32fast::Hasher,
+    sha256: Option<Sha256>,
+}
+
+/// Sommes finales, en hexadécimal
+#[derive(PartialEq, Eq)]
+struct Digests {
+    crc: String,
+    sha256: Option<String>,
+}
+
+impl std::fmt::Display for Digests {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.sha256 {
+            Some(sha256) => write!(f, "sha256 {}", sha256),
+            None => write!(f, "crc32 {}", self.crc),
+        }
+    }
+}
+
+impl Fingerprint {
+    fn new(mode: VerifyMode) -> Self {
+        Fingerprint { crc: crc32fast::Hasher::new(), sha256: (mode == VerifyMode::Sha256).then(Sha256::new) }
+    }
+
+    fn update(&mut self, buf: &[u8]) {
+        self.crc.update(buf);
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(buf);
+        }
+    }
+
+    fn finish(self) -> Digests {
+        Digests { crc: format!("{:08x}", self.crc.finalize()), sha256: self.sha256.map(hex_digest) }
+    }
+}
+
+struct FingerprintReader<'r, R: Read + ?Sized> {
+    inner: &'r mut R,
+    fingerprint: Fingerprint,
+}
+
+impl<R: Read + ?Sized> Read for FingerprintReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.fingerprint.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Relit un fichier extrait pour `--verify`
+fn fingerprint_file(path: &Path, mode: VerifyMode) -> io::Result<Digests> {
+    let mut reader = FingerprintReader { inner: &mut File::open(path)?, fingerprint: Fingerprint::new(mode) };
+    io::copy(&mut reader, &mut io::sink())?;
+    Ok(reader.fingerprint.finish())
+}
+
+/// Clé PAX portant le SHA-256 (hexadécimal) du contenu d'un fichier
+const PAX_SHA256: &str = "SHARKY.sha256";
+
+/// Calcule le SHA-256 de ce qui est lu à travers lui
+struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+fn hex_digest(hasher: Sha256) -> String {
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Journal `--log-file` : une ligne JSON par entrée traitée, indépendante de l'affichage console.
+/// Tient aussi le manifeste `--manifest` et les fichiers écartés par `--skip-errors` de la compression.
+struct EventLog {
+    out: Option<BufWriter<File>>,
+    manifest: Option<BufWriter<File>>,
+    skipped: Vec<(PathBuf, String)>,
+    /// Entrées traitées sans erreur et leur taille d'origine, pour `--stats-json`
+    entries: usize,
+    bytes: u64,
+    /// Fichiers écartés par `--exclude-larger-than` / `--exclude-smaller-than` et leur taille
+    size_excluded: usize,
+    size_excluded_bytes: u64,
+}
+
+impl EventLog {
+    fn open(path: Option<&Path>) -> io::Result<Self> {
+        let out = path.map(File::create).transpose()?.map(BufWriter::new);
+        Ok(EventLog { out, manifest: None, skipped: Vec::new(), entries: 0, bytes: 0, size_excluded: 0, size_excluded_bytes: 0 })
+    }
+
+    /// `--skip-errors` : retient le fichier écarté pour le récapitulatif et le consigne
+    fn skip(&mut self, path: &Path, error: io::Error) -> io::Result<()> {
+        self.skipped.push((path.to_path_buf(), error.to_string()));
+        self.record("skip", path, 0, 0, &Err(error))
+    }
+
+    fn report_skipped(&self) {
+        if self.skipped.is_empty() {
+            return;
+        }
+        eprintln!("Skipped {} unreadable file(s):", self.skipped.len());
+        for (path, reason) in &self.skipped {
+            eprintln!("  {}: {}", path.display(), reason);
+        }
+    }
+
+    fn open_manifest(&mut self, path: Option<&Path>) -> io::Result<()> {
+        self.manifest = path.map(File::create).transpose()?.map(BufWriter::new);
+        Ok(())
+    }
+
+    /// Ligne du manifeste : SHA-256 (`-` pour un répertoire), taille d'origine, chemin dans l'archive
+    fn manifest(&mut self, name: &Path, size: u64, sha256: Option<&str>) -> io::Result<()> {
+        let Some(out) = &mut self.manifest else { return Ok(()) };
+        writeln!(out, "{}  {}  {}", sha256.unwrap_or("-"), size, name.display())
+    }
+
+    fn record(&mut self, action: &str, path: &Path, size: u64, bytes_written: u64, result: &io::Result<()>) -> io::Result<()> {
+        if result.is_ok() {
+            self.entries += 1;
+            self.bytes += size;
+        }
+        let Some(out) = &mut self.out else { return Ok(()) };
+        let mut event = serde_json::json!({
+            "action": action,
+            "path": path.display().to_string(),
+            "size": size,
+            "bytes_written": bytes_written,
+            "result": if result.is_ok() { "ok" } else { "error" },
+        });
+        if let Err(e) = result {
+            event["error"] = e.to_string().into();
+        }
+        writeln!(out, "{}", event)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        for out in [&mut self.out, &mut self.manifest].into_iter().flatten() {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Copie `reader` vers `writer` en convertissant les fins de ligne (CRLF ou LF).
+/// Un premier bloc contenant un octet NUL fait considérer le flux comme binaire :
+/// il est alors copié tel quel. Renvoie `true` si au moins un octet a changé.
+fn copy_normalized<R: Read + ?Sized, W: Write>(reader: &mut R, writer: &mut W, crlf: bool) -> io::Result<bool> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut first = true;
+    let mut changed = false;
+    let mut prev_cr = false;
+    let mut out = Vec::with_capacity(buf.len() * 2);
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if first {
+            first = false;
+            if buf[..n].contains(&0) {
+                writer.write_all(&buf[..n])?;
+                io::copy(reader, writer)?;
+                return Ok(false);
+            }
+        }
+        out.clear();
+        for &b in &buf[..n] {
+            if crlf {
+                if b == b'\n' && !prev_cr {
+                    out.push(b'\r');
+                    changed = true;
+                }
+                out.push(b);
+            } else if prev_cr {
+                // CR en attente : supprimé s'il précède un LF
+                if b == b'\n' {
+                    changed = true;
+                } else {
+                    out.push(b'\r');
+                }
+                if b != b'\r' {
+                    out.push(b);
+                }
+            } else if b != b'\r' {
+                out.push(b);
+            }
+            prev_cr = b == b'\r';
+        }
+        writer.write_all(&out)?;
+    }
+    if prev_cr && !crlf {
+        writer.write_all(b"\r")?;
+    }
+    Ok(changed)
+}
+
+/// Formats reconnus à la décompression ; les flux mono-fichier servent aussi à `compress_bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Rar,
+    SevenZ,
+    Iso,
+    Tar,
+    TarGz,
+    Gz,
+    TarBz2,
+    Bz2,
+    TarXz,
+    Xz,
+    TarZst,
+    Zst,
+    Lzma,
+    /// Tar dans un flux `.lzma` (lzma_alone)
+    TarLzma,
+    Brotli,
+    TarBrotli,
+    Lz4,
+    /// Tar dans une trame LZ4 (`lz4_flex::frame`), distincte du `.lz4` à taille préfixée
+    TarLz4,
+    Cab,
+    Deb,
+    Rpm,
+    TarLzfse,
+    Lzfse,
+    /// Format historique de sharky : tar → xz → zstd
+    Sharky,
+}
+
+impl Format {
+    /// Implémentation du format dans le registre de `codec`
+    fn codec(self) -> &'static dyn Codec {
+        codecs().iter().find(|codec| codec.format() == self).expect("every format is registered").as_ref()
+    }
+
+    fn name(self) -> &'static str {
+        self.codec().extensions()[0]
+    }
+
+    /// Nom de format tel que passé sur la ligne de commande (`tar.gz`, `tgz`, `txz`, `zst`, ...)
+    fn from_name(name: &str) -> Option<Format> {
+        let name = name.trim_start_matches('.').to_lowercase();
+        codecs().iter().find(|codec| codec.extensions().contains(&name.as_str())).map(|codec| codec.format())
+    }
+
+    /// Flux compressé d'un seul fichier, sans structure d'archive
+    fn is_single_stream(self) -> bool {
+        self.codec().is_single_stream()
+    }
+
+    /// Archive tar, compressée ou non (format sharky compris)
+    fn is_tar_based(self) -> bool {
+        self.codec().is_tar_based()
+    }
+
+    /// Format déduit de l'extension ; tout ce qui n'est pas reconnu est traité comme le format sharky
+    fn from_path(path: &Path) -> Format {
+        Format::from_extension(path).unwrap_or(Format::Sharky)
+    }
+
+    /// Format déduit de l'extension, `None` si elle est absente ou inconnue. Une extension
+    /// composée (`tar.gz`) l'emporte sur sa dernière partie (`gz`).
+    fn from_extension(path: &Path) -> Option<Format> {
+        let name = path.to_string_lossy().to_lowercase();
+        let ext = path.extension().and_then(|s| s.to_str())?.to_lowercase();
+        let matches = |candidate: &str| {
+            candidate == ext || (candidate.ends_with(&format!(".{}", ext)) && name.ends_with(&format!(".{}", candidate)))
+        };
+        codecs().iter()
+            .flat_map(|codec| codec.extensions().iter().map(move |&candidate| (candidate, codec.format())))
+            .filter(|&(candidate, _)| matches(candidate))
+            .max_by_key(|(candidate, _)| candidate.len())
+            .map(|(_, format)| format)
+    }
+
+    /// Format reconnu d'après les premiers octets : signatures de tête d'abord, puis les
+    /// formats reconnus plus loin dans l'en-tête (tar). Les flux compressés sont rapportés
+    /// comme mono-fichier : leur contenu n'est pas inspecté ici.
+    fn from_magic(head: &[u8]) -> Option<Format> {
+        let (signed, unsigned): (Vec<_>, Vec<_>) = codecs().iter().partition(|codec| !codec.magic().is_empty());
+        signed.into_iter().chain(unsigned).find(|codec| codec.sniff(head)).map(|codec| codec.format())
+    }
+
+    /// Chaîne de décodeurs construite pour ce format, dans l'ordre de lecture
+    fn decoder_chain(self) -> &'static str {
+        self.codec().decoder_chain()
+    }
+
+    /// Formats que `-c` sait produire
+    fn can_write(self) -> bool {
+        self.codec().can_write()
+    }
+
+    /// Formats dont le lecteur a besoin d'un fichier (accès aléatoire)
+    fn needs_seek(self) -> bool {
+        self.codec().needs_seek()
+    }
+
+    /// Formats dont `--extract-entry` sait sortir une entrée seule
+    fn extracts_single_entry(self) -> bool {
+        self.codec().extracts_single_entry()
+    }
+}
+
+/// Signature d'une trame LZ4
+const LZ4_FRAME_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// Signature d'un flux xz, aussi cherchée sous la couche zstd du format sharky
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+fn is_tar_header(head: &[u8]) -> bool {
+    head.len() >= 262 && &head[257..262] == b"ustar"
+}
+
+/// Fichier de sortie de la compression, ou la sortie standard pour `-o -`
+fn create_output(opts: &CompressOptions) -> io::Result<BufWriter<Box<dyn Write>>> {
+    let out: Box<dyn Write> = if opts.writes_stdout() {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(opts.output())?)
+    };
+    Ok(BufWriter::with_capacity(opts.buffer_size, out))
+}
+
+/// Chemin absolu résolu ; pour un fichier pas encore créé, son répertoire résolu suivi de son nom
+fn resolved_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return Some(resolved);
+    }
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+/// Archive de sortie située dans l'arborescence d'entrée, sous la forme que lui donnera le
+/// parcours (`input/rel`) : elle grossirait pendant sa propre lecture. Une sortie qui serait
+/// l'entrée elle-même est refusée.
+fn output_in_input(opts: &CompressOptions) -> io::Result<Option<PathBuf>> {
+    if opts.writes_stdout() || opts.output().as_os_str().is_empty() {
+        return Ok(None);
+    }
+    let (Some(input), Some(output)) = (resolved_path(opts.input()), resolved_path(opts.output())) else {
+        return Ok(None);
+    };
+    if output == input {
+        return Err(SharkyError::OutputIsInput { path: opts.output().to_path_buf() }.into());
+    }
+    let Ok(rel) = output.strip_prefix(&input) else { return Ok(None) };
+    if opts.verbose {
+        status!(opts, "Output {:?} is inside the input directory and will not be archived", opts.output());
+    }
+    Ok(Some(opts.input().join(rel)))
+}
+
+/// Refuse d'extraire dans un répertoire qui contient l'archive, ce qui pourrait l'écraser en
+/// cours de lecture ; `force` ramène le refus à un avertissement
+fn check_output_contains_input(opts: &DecompressOptions) -> Result<(), SharkyError> {
+    if opts.writes_stdout() || opts.writes_nothing() || opts.input().as_os_str() == "-" {
+        return Ok(());
+    }
+    // Un répertoire encore absent ne peut pas contenir l'archive
+    let (Ok(input), Ok(output)) = (fs::canonicalize(opts.input()), fs::canonicalize(opts.output())) else {
+        return Ok(());
+    };
+    if !input.starts_with(&output) {
+        return Ok(());
+    }
+    if !opts.force {
+        return Err(SharkyError::OutputContainsInput { input: opts.input().to_path_buf(), output: opts.output().to_path_buf() });
+    }
+    eprintln!("Warning: extracting into {:?}, which contains the archive {:?}", opts.output(), opts.input());
+    Ok(())
+}
+
+/// Refuse d'écraser une archive existante sans `force` ; une reprise `--resume`
+/// avec son point de contrôle, ou `--append`, continue au contraire le fichier en place
+fn check_existing_output(opts: &CompressOptions) -> Result<(), SharkyError> {
+    if opts.force || opts.append || opts.writes_stdout() || (opts.resume && Checkpoint::path(opts.output()).exists()) {
+        return Ok(());
+    }
+    let Ok(meta) = fs::metadata(opts.output()) else { return Ok(()) };
+    if meta.is_dir() {
+        return Ok(());
+    }
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|d| format_mtime(d.as_secs() as i64))
+        .unwrap_or_else(|| "-".to_string());
+    Err(SharkyError::OutputExists { path: opts.output().to_path_buf(), size: meta.len(), modified })
+}
+
+/// Motifs ajoutés par `--exclude-backups`, comparés au nom de fichier
+const BACKUP_PATTERNS: &[&str] = &["*~", "*.bak", "*.swp", "*.tmp", "#*#", ".DS_Store", "Thumbs.db"];
+
+/// Filtre d'exclusion appliqué pendant le parcours de l'entrée. Un chemin est exclu dès que
+/// l'une des sources l'exclut (`--exclude`, `--exclude-backups`, `--ignore-file`) ; un motif `!`
+/// du fichier d'ignorés ne rattrape que ses propres exclusions. Un répertoire exclu emporte son contenu.
+struct ExcludeFilter {
+    root: PathBuf,
+    /// Archive de sortie écrite dans l'arborescence d'entrée, jamais archivée elle-même
+    output: Option<PathBuf>,
+    /// Motifs sans `/`, comparés au nom d'un composant
+    names: GlobSet,
+    /// Motifs avec `/`, comparés au chemin relatif à `root`
+    paths: GlobSet,
+    ignore: Option<Gitignore>,
+}
+
+impl ExcludeFilter {
+    fn from_args(opts: &CompressOptions) -> io::Result<Self> {
+        let invalid = |e: globset::Error| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        if opts.exclude_backups {
+            for pat in BACKUP_PATTERNS {
+                names.add(Glob::new(pat).map_err(invalid)?);
+            }
+        }
+        for pat in &opts.exclude {
+            add_glob(&mut names, &mut paths, pat)?;
+        }
+        let ignore = match &opts.ignore_file {
+            Some(file) => {
+                let mut builder = GitignoreBuilder::new(opts.input());
+                if let Some(e) = builder.add(file) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Cannot read ignore file: {}", e),
+                    ));
+                }
+                Some(builder.build().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?)
+            }
+            None => None,
+        };
+        Ok(ExcludeFilter {
+            root: opts.input().to_path_buf(),
+            output: output_in_input(opts)?,
+            names: names.build().map_err(invalid)?,
+            paths: paths.build().map_err(invalid)?,
+            ignore,
+        })
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.output.as_deref() == Some(path) {
+            return true;
+        }
+        let rel = path.strip_prefix(&self.root).unwrap_or(path);
+        if rel.as_os_str().is_empty() {
+            // Racine de l'entrée (fichier unique) : seul son nom compte
+            return path.file_name().is_some_and(|name| self.names.is_match(name));
+        }
+        let excluded = rel.ancestors()
+            .filter(|a| !a.as_os_str().is_empty())
+            .any(|a| a.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(a));
+        excluded || self.ignore.as_ref().is_some_and(|ignore| {
+            ignore.matched_path_or_any_parents(rel, path.is_dir()).is_ignore()
+        })
+    }
+}
+
+/// Range un motif `--exclude`/`--include` : avec `/`, il vise le chemin relatif (sans que `*`
+/// franchisse les `/`) ; sinon, le nom d'un composant
+fn add_glob(names: &mut GlobSetBuilder, paths: &mut GlobSetBuilder, pat: &str) -> io::Result<()> {
+    let invalid = |e: globset::Error| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let pat = pat.trim_end_matches('/');
+    if pat.contains('/') {
+        let glob = GlobBuilder::new(pat.trim_start_matches('/')).literal_separator(true).build();
+        paths.add(glob.map_err(invalid)?);
+    } else {
+        names.add(Glob::new(pat).map_err(invalid)?);
+    }
+    Ok(())
+}
+
+/// Motifs d'un côté de la sélection `--include`/`--exclude` à l'extraction
+struct GlobPair {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl GlobPair {
+    fn new(patterns: &[String]) -> io::Result<Self> {
+        let invalid = |e: globset::Error| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pat in patterns {
+            add_glob(&mut names, &mut paths, pat)?;
+        }
+        Ok(GlobPair { names: names.build().map_err(invalid)?, paths: paths.build().map_err(invalid)? })
+    }
+
+    /// Vrai si le chemin ou l'un de ses répertoires parents correspond
+    fn matches(&self, rel: &Path) -> bool {
+        rel.ancestors()
+            .filter(|a| !a.as_os_str().is_empty())
+            .any(|a| a.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(a))
+    }
+}
+
+/// Sélection des entrées extraites, sur leur chemin dans l'archive et avec les règles de
+/// `--exclude` à la compression. Dès qu'un filtre est donné, les répertoires ne sont plus
+/// créés que comme parents des fichiers retenus, pour ne pas en laisser de vides.
+struct EntrySelection {
+    include: Option<GlobPair>,
+    exclude: Option<GlobPair>,
+}
+
+impl EntrySelection {
+    fn new(include: &[String], exclude: &[String]) -> io::Result<Self> {
+        Ok(EntrySelection {
+            include: (!include.is_empty()).then(|| GlobPair::new(include)).transpose()?,
+            exclude: (!exclude.is_empty()).then(|| GlobPair::new(exclude)).transpose()?,
+        })
+    }
+
+    fn selects(&self, entry: &Path, is_dir: bool) -> bool {
+        if self.include.is_none() && self.exclude.is_none() {
+            return true;
+        }
+        if is_dir {
+            return false;
+        }
+        let rel: PathBuf = entry.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+        self.include.as_ref().is_none_or(|set| set.matches(&rel)) && !self.exclude.as_ref().is_some_and(|set| set.matches(&rel))
+    }
+}
+
+/// Erreur signalant un format (ou une méthode) que sharky ne sait pas traiter
+fn unsupported(message: impl Into<String>) -> io::Error {
+    SharkyError::UnsupportedFormat(message.into()).into()
+}
+
+/// Récapitulatif des archives ignorées par `continue_on_unsupported`
+pub fn report_skipped(skipped: &[(PathBuf, String)]) {
+    if skipped.is_empty() {
+        return;
+    }
+    eprintln!("Skipped {} unsupported archive(s):", skipped.len());
+    for (path, reason) in skipped {
+        eprintln!("  {}: {}", path.display(), reason);
+    }
+}
+
+/// Emballe une erreur sevenz-rust (le cas « non supporté » reste reconnaissable) ; un
+/// mot de passe absent ou refusé pour `input` devient une erreur explicite
+fn sevenz_error(input: &Path, context: &str, e: sevenz_rust::Error) -> io::Error {
+    let name = || input.display().to_string();
+    match e {
+        sevenz_rust::Error::PasswordRequired => SharkyError::PasswordRequired(name()).into(),
+        sevenz_rust::Error::MaybeBadPassword(_) => SharkyError::WrongPassword(name()).into(),
+        e => SharkyError::SevenZ { context: context.to_string(), source: e }.into(),
+    }
+}
+
+/// Mot de passe `--password` au format de sevenz-rust (vide sans option)
+fn sevenz_password(password: Option<&str>) -> sevenz_rust::Password {
+    password.map_or_else(sevenz_rust::Password::empty, sevenz_rust::Password::from)
+}
+
+/// Emballe une erreur unrar
+fn rar_error(context: impl Into<String>, e: unrar::error::UnrarError) -> io::Error {
+    SharkyError::Rar { context: context.into(), source: e }.into()
+}
+
+/// Archive `opts.input` dans `opts.output`, au format choisi par `opts.format` ou l'extension
+pub fn compress(opts: &CompressOptions) -> Result<(), SharkyError> {
+    let start = Instant::now();
+    if !opts.verbose {
+        status!(opts, "© 2025, Matheo Simard");
+    }
+    let opts = &level_options(opts);
+    let filter = ExcludeFilter::from_args(opts)?;
+    check_input_size(opts)?;
+    if opts.verbose && opts.exclude_backups {
+        status!(opts, "Excluding backup files: {}", BACKUP_PATTERNS.join(" "));
+    }
+
+    check_existing_output(opts)?;
+    let mut log = EventLog::open(opts.log_file.as_deref())?;
+    let format = output_format(opts)?;
+    warn_unused_codec_flags(opts, format);
+    if matches!(format, Format::Bz2 | Format::TarBz2) {
+        bzip2_level(opts)?;
+    }
+    let memory = encoder_memory(opts, format);
+    if memory > 0 {
+        status!(opts, "Estimated encoder memory: {}", HumanBytes(memory));
+    }
+    if opts.writes_stdout() {
+        let problem = if format.needs_seek() {
+            Some(format!("{} archives need a seekable output file", format.name()))
+        } else if opts.resume {
+            Some("--resume needs an output file to checkpoint".to_string())
+        } else if opts.compress_if_smaller {
+            Some("--compress-if-smaller needs an output file it can remove".to_string())
+        } else if opts.append {
+            Some("--append needs an existing archive file".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = problem {
+            return Err(SharkyError::UnsupportedFormat(format!("Cannot write to stdout: {}", reason)));
+        }
+    }
+    if !opts.file_transform.is_empty() && !format.is_tar_based() {
+        return Err(SharkyError::UnsupportedFormat(format!(
+            "--file-transform is only supported for tar-based outputs, not {}",
+            format.name()
+        )));
+    }
+    if opts.embed_checksums && !format.is_tar_based() {
+        return Err(SharkyError::UnsupportedFormat(format!(
+            "--embed-checksums is only supported for tar-based outputs, not {}",
+            format.name()
+        )));
+    }
+    if opts.manifest.is_some() && matches!(format, Format::Zip | Format::SevenZ) {
+        return Err(SharkyError::UnsupportedFormat(format!(
+            "--manifest is only supported for tar-based outputs, not {}",
+            format.name()
+        )));
+    }
+    if opts.password.is_some() && format != Format::SevenZ {
+        return Err(SharkyError::UnsupportedFormat(format!(
+            "--password is only supported for 7z outputs, not {}",
+            format.name()
+        )));
+    }
+    if opts.resume && !matches!(format, Format::Tar | Format::TarZst) {
+        return Err(SharkyError::UnsupportedFormat(format!(
+            "--resume is only supported for tar and tar.zst outputs, not {}",
+            format.name()
+        )));
+    }
+    let appending = opts.append && opts.output().is_file();
+    if appending {
+        check_appendable(opts, format)?;
+    }
+    log.open_manifest(opts.manifest.as_deref())?;
+
+    // Les tar compressés sont réécrits : l'ancienne archive, mise de côté, est recopiée
+    // en tête de la nouvelle par `write_tar`, puis supprimée (ou restaurée en cas d'échec)
+    let previous = appending && format != Format::Tar;
+    if previous {
+        fs::rename(opts.output(), append_backup_path(opts.output()))?;
+    }
+    let result = write_archive(opts, &filter, format, &mut log).and_then(|()| log.finish());
+    log.report_skipped();
+    if log.size_excluded > 0 {
+        summary!(opts, "Excluded {} file(s) by size ({} bytes)", log.size_excluded, log.size_excluded_bytes);
+    }
+    if previous {
+        let backup = append_backup_path(opts.output());
+        match &result {
+            Ok(()) => fs::remove_file(backup)?,
+            Err(_) => fs::rename(backup, opts.output())?,
+        }
+    }
+    result?;
+    if let Some(path) = &opts.stats_json {
+        // Sortie absente : écrite sur stdout, ou supprimée par `--compress-if-smaller`
+        let output_bytes = (!opts.writes_stdout()).then(|| fs::metadata(opts.output()).ok()).flatten().map(|m| m.len());
+        let stats = Stats {
+            operation: "compress",
+            format,
+            level: format_level(opts, format),
+            input_bytes: Some(log.bytes),
+            output_bytes,
+            elapsed: start.elapsed(),
+            entries: log.entries,
+        };
+        stats.write(path)?;
+    }
+    Ok(())
+}
+
+/// Bilan `--stats-json` d'une compression ou d'une extraction
+struct Stats {
+    operation: &'static str,
+    format: Format,
+    level: Option<i64>,
+    input_bytes: Option<u64>,
+    output_bytes: Option<u64>,
+    elapsed: Duration,
+    entries: usize,
+}
+
+impl Stats {
+    /// Un objet JSON sur une ligne ; `ratio` = octets de sortie / octets d'entrée,
+    /// `null` avec une taille inconnue (stdin, stdout) ou une entrée vide
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let ratio = match (self.input_bytes, self.output_bytes) {
+            (Some(input), Some(output)) if input > 0 => Some(output as f64 / input as f64),
+            _ => None,
+        };
+        let json = serde_json::json!({
+            "operation": self.operation,
+            "format": self.format.name(),
+            "level": self.level,
+            "input_bytes": self.input_bytes,
+            "output_bytes": self.output_bytes,
+            "ratio": ratio,
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+            "entries": self.entries,
+        });
+        if path.as_os_str() == "-" {
+            println!("{}", json);
+            Ok(())
+        } else {
+            fs::write(path, format!("{}\n", json))
+        }
+    }
+}
+
+/// Niveau effectif du codec de `format` (`None` pour les formats sans niveau)
+fn format_level(opts: &CompressOptions, format: Format) -> Option<i64> {
+    match format {
+        Format::Sharky | Format::TarZst | Format::Zst => Some(opts.zstd_level.into()),
+        Format::TarXz | Format::Xz | Format::SevenZ => Some(opts.xz_preset.into()),
+        Format::TarBz2 | Format::Bz2 => bzip2_level(opts).ok().map(|level| level.level().into()),
+        Format::TarGz | Format::Gz => Some(deflate_level(opts).level().into()),
+        Format::Zip => Some(zip_level(opts).into()),
+        Format::Brotli | Format::TarBrotli => Some(opts.brotli_quality.into()),
+        _ => None,
+    }
+}
+
+/// Écrit l'archive `format` ; le journal est finalisé par l'appelant
+fn write_archive(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    format.codec().compress(opts, filter, log)
+}
+
+/// Format sharky : tar → xz → zstd, ou tar → zstd quand `--smart-pipeline` juge la couche xz inutile
+fn compress_sharky(opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+    status!(
+        opts,
+        "Compression: {:?} → {:?} (XZ preset {}, Zstd lvl {})",
+        opts.input(), opts.output(), opts.xz_preset, opts.zstd_level
+    );
+
+    let zstd = ZstdSettings::from_args(opts)?;
+    // Sous `--append`, on garde le pipeline de l'archive existante (avec ou sans couche xz)
+    let previous = append_backup_path(opts.output());
+    let use_xz = if opts.append && previous.is_file() {
+        sniff_format(&previous)? != Some(Format::TarZst)
+    } else {
+        !opts.smart_pipeline || xz_layer_helps(opts, filter, &zstd)?
+    };
+    let outfile = create_output(opts)?;
+    let mut zstd_encoder = zstd.encoder(outfile)?;
+
+    if use_xz {
+        let mut xz_encoder = xz_encoder(&mut zstd_encoder, opts)?;
+        write_tar(opts, filter, &mut xz_encoder, log)?;
+        xz_encoder.finish()?;
+    } else {
+        write_tar(opts, filter, &mut zstd_encoder, log)?;
+    }
+    zstd_encoder.finish()?;
+
+    report_output_size(opts, filter)
+}
+
+/// Taille de l'échantillon comparé par `--smart-pipeline`
+const SMART_SAMPLE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// `--smart-pipeline` : compresse les premiers Mo des fichiers de l'entrée en zstd seul puis
+/// en xz → zstd, et ne garde la couche xz que si elle rend l'échantillon plus petit.
+/// Sans xz, la sortie est un simple tar.zst, que le décodeur sharky reconnaît aussi.
+fn xz_layer_helps(opts: &CompressOptions, filter: &ExcludeFilter, zstd: &ZstdSettings) -> io::Result<bool> {
+    let mut sample = Vec::new();
+    let walker = WalkDir::new(opts.input()).max_depth(opts.max_depth.unwrap_or(usize::MAX));
+    for entry in walker.into_iter().filter_map(Result::ok) {
+        let remaining = SMART_SAMPLE_SIZE - sample.len() as u64;
+        if remaining == 0 {
+            break;
+        }
+        if entry.file_type().is_file() && !filter.is_excluded(entry.path()) {
+            File::open(entry.path())?.take(remaining).read_to_end(&mut sample)?;
+        }
+    }
+
+    let zstd_size = |data: &[u8]| -> io::Result<usize> {
+        let mut encoder = zstd.encoder(Vec::new())?;
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?.len())
+    };
+    let mut xz = XzEncoder::new(Vec::new(), opts.xz_preset);
+    xz.write_all(&sample)?;
+    let zstd_only = zstd_size(&sample)?;
+    let with_xz = zstd_size(&xz.finish()?)?;
+
+    let keep = with_xz < zstd_only;
+    status!(
+        opts,
+        "Smart pipeline: {} the xz layer (sample of {} bytes: zstd {} bytes, xz → zstd {} bytes)",
+        if keep { "keeping" } else { "dropping" }, sample.len(), zstd_only, with_xz
+    );
+    Ok(keep)
+}
+
+/// Options de niveau réellement utilisées par chaque format de sortie
+fn codec_flags(format: Format) -> &'static [&'static str] {
+    match format {
+        Format::Sharky => &["--level", "--zstd-level", "--zstd-long", "--xz-preset", "--dict", "--threads"],
+        Format::TarZst => &["--level", "--zstd-level", "--zstd-long", "--dict", "--threads"],
+        Format::TarXz | Format::Xz => &["--level", "--xz-preset", "--threads"],
+        // le preset xz sert de niveau bzip2 (1–9)
+        Format::TarBz2 | Format::Bz2 => &["--level", "--xz-preset"],
+        Format::Zst => &["--level", "--zstd-level", "--zstd-long", "--dict", "--threads"],
+        Format::TarGz | Format::Gz => &["--level"],
+        // le niveau zstd est ramené sur l'échelle deflate 0–9
+        Format::Zip => &["--level", "--zstd-level", "--store-extensions"],
+        Format::SevenZ => &["--level", "--xz-preset", "--7z-method"],
+        Format::Brotli | Format::TarBrotli => &["--level", "--brotli-quality", "--brotli-window"],
+        _ => &[],
+    }
+}
+
+/// Reporte `--level` sur les niveaux zstd, xz et brotli qui n'ont pas été donnés explicitement :
+///
+/// | `--level` | 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 |
+/// |-----------|---|---|---|---|---|---|---|---|---|---|
+/// | zstd      | 1 | 3 | 5 | 7 | 9 |11 |13 |15 |17 |19 |
+/// | xz        | 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 |
+/// | deflate   | 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 |
+/// | bzip2     | 1 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9 |
+/// | brotli    | 0 | 1 | 2 | 4 | 5 | 6 | 7 | 9 |10 |11 |
+///
+/// Zstd s'arrête à 19 : les niveaux « ultra » (20–22) réservent des fenêtres de 32 à 128 Mio
+/// et restent accessibles par `--zstd-level`. Deflate (gz, zip) et bzip2 lisent `level` eux-mêmes.
+fn level_options(opts: &CompressOptions) -> CompressOptions {
+    let mut opts = opts.clone();
+    if let Some(level) = opts.level {
+        let given = |flag| opts.codec_flags_given.contains(&flag);
+        if !given("--zstd-level") {
+            opts.zstd_level = 1 + 2 * level as i32;
+        }
+        if !given("--xz-preset") {
+            opts.xz_preset = level;
+        }
+        if !given("--brotli-quality") {
+            opts.brotli_quality = (level * 11 + 4) / 9;
+        }
+    }
+    opts
+}
+
+/// Racine des entrées dans l'archive : `--archive-root`, sinon le nom de l'entrée
+fn archive_root(opts: &CompressOptions) -> &Path {
+    opts.archive_root.as_deref().unwrap_or_else(|| Path::new(opts.input().file_name().unwrap_or_default()))
+}
+
+/// Profondeur minimale des parcours zip / 7z : `--no-wrap` saute la racine d'un répertoire,
+/// mais un fichier seul reste la seule entrée
+fn walk_min_depth(opts: &CompressOptions) -> usize {
+    if opts.no_wrap && opts.input().is_dir() { 1 } else { 0 }
+}
+
+/// Chemin dans l'archive d'un fichier parcouru : relatif à l'entrée, sous la racine sauf avec
+/// `--no-wrap` ; un fichier seul prend le nom de la racine
+fn walked_entry_name(opts: &CompressOptions, path: &Path) -> PathBuf {
+    let rel = path.strip_prefix(opts.input()).unwrap_or(path);
+    if rel.as_os_str().is_empty() {
+        archive_root(opts).to_path_buf()
+    } else if opts.no_wrap {
+        rel.to_path_buf()
+    } else {
+        archive_root(opts).join(rel)
+    }
+}
+
+/// Niveau deflate des sorties gz et tar.gz : `--level`, sinon le niveau par défaut de zlib (6)
+fn deflate_level(opts: &CompressOptions) -> flate2::Compression {
+    opts.level.map_or_else(flate2::Compression::default, flate2::Compression::new)
+}
+
+/// Niveau bzip2 (1–9), repris de `--xz-preset` ou de `--level` (0 devient 1)
+fn bzip2_level(opts: &CompressOptions) -> io::Result<bzip2::Compression> {
+    if let Some(level) = opts.level && !opts.codec_flags_given.contains(&"--xz-preset") {
+        return Ok(bzip2::Compression::new(level.max(1)));
+    }
+    if !(1..=9).contains(&opts.xz_preset) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("bzip2 level must be between 1 and 9 (got --xz-preset {})", opts.xz_preset),
+        ));
+    }
+    Ok(bzip2::Compression::new(opts.xz_preset))
+}
+
+/// Prévient quand une option de niveau donnée explicitement est ignorée par le format choisi
+fn warn_unused_codec_flags(opts: &CompressOptions, format: Format) {
+    let relevant = codec_flags(format);
+    for flag in opts.codec_flags_given.iter().filter(|flag| !relevant.contains(flag)) {
+        let hint = match relevant {
+            [] => "this format has no level options".to_string(),
+            flags => format!("relevant options: {}", flags.join(", ")),
+        };
+        eprintln!("Warning: {} has no effect on {} output ({})", flag, format.name(), hint);
+    }
+}
+
+/// Format produit par -c : `--format` s'il est donné, sinon celui de l'extension de sortie
+/// (`.tar.gz`/`.tgz`, `.tar.zst`, `.zip`, `.7z`, ...). Le format sharky historique reste celui
+/// des extensions inconnues (ou absentes, dont `-o -`) et des répertoires envoyés vers une
+/// extension mono-fichier (`.gz`, `.bz2`, `.xz`, `.zst`), comme avant l'inférence.
+fn output_format(opts: &CompressOptions) -> io::Result<Format> {
+    if let Some(name) = &opts.format {
+        return Format::from_name(name).filter(|f| f.can_write()).ok_or_else(|| {
+            let known: Vec<&str> = codecs().iter().filter(|c| c.can_write()).map(|c| c.format().name()).collect();
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown output format '{}' (expected one of: {})", name, known.join(", ")),
+            )
+        });
+    }
+    Ok(match Format::from_path(opts.output()) {
+        Format::Gz | Format::Bz2 | Format::Xz | Format::Zst if opts.input().is_dir() => Format::Sharky,
+        format if format.can_write() => format,
+        _ => Format::Sharky,
+    })
+}
+
+/// Écrit l'arborescence d'entrée en tar dans `writer` et le rend pour que l'appelant finalise l'encodeur.
+/// Sous `--append`, les entrées de l'archive mise de côté sont recopiées d'abord.
+fn write_tar<W: Write>(opts: &CompressOptions, filter: &ExcludeFilter, writer: W, log: &mut EventLog) -> io::Result<W> {
+    let mut tar_builder = Builder::new(writer);
+    let previous = append_backup_path(opts.output());
+    if opts.append && previous.is_file() {
+        let count = copy_previous_entries(&mut tar_builder, opts, &previous)?;
+        status!(opts, "Appending after {} existing entries", count);
+    }
+    let (pb, mtime) = build_progress(opts, filter)?;
+    traverse_and_append(&mut tar_builder, &pb, filter, opts, mtime, log, None)?;
+    pb.finish_and_clear();
+    tar_builder.into_inner()
+}
+
+/// Archive existante mise de côté pendant un `--append` sur un tar compressé
+fn append_backup_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".append-old");
+    PathBuf::from(name)
+}
+
+/// `--append` ne vise que les formats à base de tar ; les autres sont refusés
+fn check_appendable(opts: &CompressOptions, format: Format) -> io::Result<()> {
+    let appendable = matches!(
+        format,
+        Format::Sharky
+            | Format::Tar
+            | Format::TarGz
+            | Format::TarBz2
+            | Format::TarXz
+            | Format::TarZst
+            | Format::TarLzfse
+            | Format::TarLz4
+            | Format::TarBrotli
+    );
+    if !appendable {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Cannot append to {} archives (only tar-based formats support --append)", format.name()),
+        ));
+    }
+    // Un reste d'ajout interrompu serait sinon recopié dans l'archive
+    let backup = append_backup_path(opts.output());
+    if backup.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' is left over from an interrupted --append; restore or remove it first", backup.display()),
+        ));
+    }
+    match sniff_format(opts.output())? {
+        // Brotli n'a pas de signature : seul l'en-tête tar décodé le trahira
+        None if matches!(format, Format::TarBrotli) => Ok(()),
+        Some(found) if found == format => Ok(()),
+        // `--smart-pipeline` a pu omettre la couche xz : le lecteur sharky l'accepte aussi
+        Some(Format::TarZst) if format == Format::Sharky => Ok(()),
+        found => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Cannot append {} entries to '{}': existing archive is {}",
+                format.name(),
+                opts.output().display(),
+                found.map_or("not a recognized archive", |f| f.name())
+            ),
+        )),
+    }
+}
+
+/// Recopie dans `builder` les entrées de l'archive `path`, du même format que la sortie
+fn copy_previous_entries<W: Write>(builder: &mut Builder<W>, opts: &CompressOptions, path: &Path) -> io::Result<usize> {
+    let reader = BufReader::with_capacity(opts.buffer_size, File::open(path)?);
+    let mut copied = 0;
+    output_format(opts)?.codec().decode(Box::new(reader), &mut |tar| {
+        copied = copy_tar_entries(builder, tar)?;
+        Ok(())
+    })?;
+    Ok(copied)
+}
+
+/// Recopie les entrées d'un flux tar. Les en-têtes sont repris (dates, droits, propriétaires),
+/// les fichiers GNU sparse deviennent des fichiers pleins et la somme PAX `SHARKY.sha256` suit l'entrée.
+fn copy_tar_entries<W: Write>(builder: &mut Builder<W>, reader: impl Read) -> io::Result<usize> {
+    let mut archive = Archive::new(reader);
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if let Some(digest) = pax_checksum(&mut entry)? {
+            append_pax_checksum(builder, &digest)?;
+        }
+        let path = entry.path()?.into_owned();
+        let mut header = entry.header().clone();
+        if header.entry_type() == tar::EntryType::GNUSparse {
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(entry.size());
+        }
+        let kind = header.entry_type();
+        match entry.link_name()? {
+            Some(target) if kind.is_symlink() || kind.is_hard_link() => {
+                let target = target.into_owned();
+                builder.append_link(&mut header, path, target)?;
+            }
+            _ => builder.append_data(&mut header, path, &mut entry)?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Fin des entrées d'un tar nu : position du premier bloc nul de fin, où `--append` reprend
+fn tar_end_offset(file: &mut File) -> io::Result<u64> {
+    let len = file.metadata()?.len();
+    let mut header = Header::new_old();
+    let mut pos = 0;
+    while pos + 512 <= len {
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(header.as_mut_bytes())?;
+        let bytes = header.as_bytes();
+        if bytes.iter().all(|&b| b == 0) {
+            break;
+        }
+        // Somme de l'en-tête, le champ de somme lui-même compté comme des espaces
+        let sum: u32 = bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { u32::from(b' ') } else { u32::from(b) })
+            .sum();
+        if header.cksum().ok() != Some(sum) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Not a tar archive: bad header checksum at offset {}", pos),
+            ));
+        }
+        pos += 512;
+        if header.entry_type() == tar::EntryType::GNUSparse && header.as_gnu().is_some_and(|gnu| gnu.is_extended()) {
+            let mut ext = tar::GnuExtSparseHeader::new();
+            loop {
+                file.read_exact(ext.as_mut_bytes())?;
+                pos += 512;
+                if !ext.is_extended() {
+                    break;
+                }
+            }
+        }
+        pos += header.entry_size()?.div_ceil(512) * 512;
+    }
+    Ok(pos.min(len))
+}
+
+/// Archives tar standard à un seul codec, lisibles par `tar` et `7z`
+fn compress_tar(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    status!(opts, "Compression: {:?} → {:?} ({})", opts.input(), opts.output(), format.name());
+    if opts.resume {
+        compress_tar_resumable(opts, filter, format, log)?;
+        return report_output_size(opts, filter);
+    }
+
+    if opts.append && format == Format::Tar && opts.output().is_file() {
+        // Tar nu : les nouvelles entrées remplacent les blocs nuls de fin, sur place
+        let mut file = fs::OpenOptions::new().read(true).write(true).open(opts.output())?;
+        let end = tar_end_offset(&mut file)?;
+        status!(opts, "Appending to {:?} at offset {}", opts.output(), end);
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        write_tar(opts, filter, BufWriter::with_capacity(opts.buffer_size, file), log)?.flush()?;
+        return report_output_size(opts, filter);
+    }
+
+    let outfile = create_output(opts)?;
+    let mut outfile = match format {
+        Format::TarGz => write_tar(opts, filter, GzEncoder::new(outfile, deflate_level(opts)), log)?.finish()?,
+        Format::TarBz2 => write_tar(opts, filter, BzEncoder::new(outfile, bzip2_level(opts)?), log)?.finish()?,
+        Format::TarXz => write_tar(opts, filter, xz_encoder(outfile, opts)?, log)?.finish()?,
+        Format::TarZst => write_tar(opts, filter, ZstdSettings::from_args(opts)?.encoder(outfile)?, log)?.finish()?,
+        _ => write_tar(opts, filter, outfile, log)?,
+    };
+    outfile.flush()?;
+
+    report_output_size(opts, filter)
+}
+
+/// Niveaux essayés par `--compare-levels` sans liste explicite, sur l'échelle du codec
+fn default_compare_levels(format: Format) -> &'static [u32] {
+    match format {
+        Format::TarZst | Format::Zst => &[1, 3, 9, 15, 19, 22],
+        Format::Sharky | Format::TarXz | Format::Xz => &[0, 3, 6, 9],
+        Format::TarBz2 | Format::Bz2 => &[1, 5, 9],
+        Format::TarGz | Format::Gz => &[1, 3, 6, 9],
+        Format::Brotli | Format::TarBrotli => &[0, 4, 9, 11],
+        _ => &[],
+    }
+}
+
+/// `--compare-levels` : compresse l'entrée à chaque niveau du codec de sortie (preset xz pour
+/// le format sharky, la couche zstd ne gagnant presque rien derrière xz) et affiche taille,
+/// ratio et durée. Rien n'est gardé : le flux compressé part dans `io::sink()` et seuls ses
+/// octets sont comptés.
+pub fn compare_levels(opts: &CompressOptions, levels: &[u32]) -> Result<(), SharkyError> {
+    let filter = ExcludeFilter::from_args(opts)?;
+    check_input_size(opts)?;
+    let format = output_format(opts)?;
+    let defaults = default_compare_levels(format);
+    if defaults.is_empty() {
+        return Err(unsupported_compare_levels(format).into());
+    }
+    let levels = if levels.is_empty() { defaults } else { levels };
+    let range = match format {
+        Format::TarZst | Format::Zst => 1..=22,
+        Format::TarBz2 | Format::Bz2 => 1..=9,
+        Format::Brotli | Format::TarBrotli => 0..=11,
+        Format::Sharky | Format::TarXz | Format::Xz | Format::TarGz | Format::Gz => 0..=9,
+        other => return Err(unsupported_compare_levels(other).into()),
+    };
+    if let Some(level) = levels.iter().find(|&level| !range.contains(level)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Level {} is out of range for {} ({}–{})", level, format.name(), range.start(), range.end()),
+        ).into());
+    }
+    status!(opts, "Comparing {} levels on {:?}", format.name(), opts.input());
+
+    println!("{:>5}  {:>12}  {:>7}  {:>9}", "Level", "Size", "Ratio", "Time");
+    for &level in levels {
+        let start = Instant::now();
+        let (input_bytes, output_bytes) = compressed_size(&level_variant(opts, format, level), &filter, format)?;
+        let ratio = if input_bytes == 0 { 0.0 } else { output_bytes as f64 / input_bytes as f64 };
+        println!("{:>5}  {:>12}  {:>7.4}  {:>8.2}s", level, output_bytes, ratio, start.elapsed().as_secs_f64());
+    }
+    Ok(())
+}
+
+/// Format de sortie sans niveaux à comparer
+fn unsupported_compare_levels(format: Format) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("--compare-levels needs an output format with compression levels (sharky, zst, xz, bz2, gz, br), not {}", format.name()),
+    )
+}
+
+/// Options de compression au niveau `level` du codec de `format`, sans `--level` ni affichage
+fn level_variant(opts: &CompressOptions, format: Format, level: u32) -> CompressOptions {
+    let mut opts = opts.clone();
+    opts.level = None;
+    opts.quiet = true;
+    opts.verbose = false;
+    match format {
+        Format::TarZst | Format::Zst => opts.zstd_level = level as i32,
+        Format::Sharky | Format::TarXz | Format::Xz | Format::TarBz2 | Format::Bz2 => opts.xz_preset = level,
+        Format::TarGz | Format::Gz => opts.level = Some(level),
+        Format::Brotli | Format::TarBrotli => opts.brotli_quality = level,
+        // écarté par `compare_levels`, faute de niveaux
+        _ => {}
+    }
+    opts
+}
+
+/// Octets lus et octets produits par une compression au format `format`, écrite dans `io::sink()`
+fn compressed_size(opts: &CompressOptions, filter: &ExcludeFilter, format: Format) -> io::Result<(u64, u64)> {
+    let count = AtomicU64::new(0);
+    let sink = CountingWriter { inner: io::sink(), count: &count };
+    let wrap_tar = opts.input().is_dir() || !format.is_single_stream();
+    let mut log = EventLog::open(None)?;
+    let log = &mut log;
+    match format {
+        Format::Sharky => {
+            let mut zstd_encoder = ZstdSettings::from_args(opts)?.encoder(sink)?;
+            uncompressed_input(opts, filter, wrap_tar, xz_encoder(&mut zstd_encoder, opts)?, log)?.finish()?;
+            zstd_encoder.finish()?;
+        }
+        Format::TarZst | Format::Zst => {
+            let encoder = ZstdSettings::from_args(opts)?.encoder(sink)?;
+            uncompressed_input(opts, filter, wrap_tar, encoder, log)?.finish()?;
+        }
+        Format::TarXz | Format::Xz => {
+            uncompressed_input(opts, filter, wrap_tar, xz_encoder(sink, opts)?, log)?.finish()?;
+        }
+        Format::TarBz2 | Format::Bz2 => {
+            uncompressed_input(opts, filter, wrap_tar, BzEncoder::new(sink, bzip2_level(opts)?), log)?.finish()?;
+        }
+        Format::TarGz | Format::Gz => {
+            uncompressed_input(opts, filter, wrap_tar, GzEncoder::new(sink, deflate_level(opts)), log)?.finish()?;
+        }
+        Format::Brotli | Format::TarBrotli => {
+            let encoder = brotli::CompressorWriter::new(sink, 4096, opts.brotli_quality, opts.brotli_window);
+            let mut writer = uncompressed_input(opts, filter, wrap_tar, encoder, log)?;
+            writer.flush()?;
+            writer.into_inner();
+        }
+        other => return Err(unsupported_compare_levels(other)),
+    }
+    Ok((log.bytes, count.load(Ordering::Relaxed)))
+}
+
+/// Écrit l'entrée dans `writer`, en tar ou telle quelle (fichier seul), et le rend pour que
+/// l'appelant finalise l'encodeur
+fn uncompressed_input<W: Write>(opts: &CompressOptions, filter: &ExcludeFilter, wrap_tar: bool, writer: W, log: &mut EventLog) -> io::Result<W> {
+    if wrap_tar {
+        return write_tar(opts, filter, writer, log);
+    }
+    let (size, writer) = copy_into(&mut RetryReader::open(opts.input(), opts.io_retries)?, writer)?;
+    log.bytes += size;
+    Ok(writer)
+}
+
+/// `--train-dict` : octets lus au plus par fichier (les blocs de 128 Kio de `zstd --train`)
+/// et au total sur l'ensemble des échantillons
+const DICT_SAMPLE_SIZE: u64 = 128 * 1024;
+const DICT_SAMPLES_TOTAL: u64 = 256 * 1024 * 1024;
+/// En dessous, zstd refuse d'entraîner un dictionnaire
+const MIN_DICT_SAMPLES: usize = 5;
+
+/// Entraîne un dictionnaire zstd d'au plus `dict_size` octets sur les fichiers de `--input`
+/// (hors exclusions) et l'écrit dans `dict_out`. Chaque fichier donne un échantillon.
+pub fn train_dict(opts: &CompressOptions, dict_out: &Path, dict_size: usize) -> Result<(), SharkyError> {
+    let filter = ExcludeFilter::from_args(opts)?;
+    let mut samples = Vec::new();
+    let mut total = 0u64;
+    let walker = WalkDir::new(opts.input()).max_depth(opts.max_depth.unwrap_or(usize::MAX));
+    for entry in walker.into_iter().filter_map(Result::ok) {
+        if total >= DICT_SAMPLES_TOTAL {
+            status!(opts, "Sample limit reached ({} bytes); remaining files are not used", DICT_SAMPLES_TOTAL);
+            break;
+        }
+        if !entry.file_type().is_file() || filter.is_excluded(entry.path()) {
+            continue;
+        }
+        let mut sample = Vec::new();
+        let limit = DICT_SAMPLE_SIZE.min(DICT_SAMPLES_TOTAL - total);
+        File::open(entry.path())?.take(limit).read_to_end(&mut sample)?;
+        if !sample.is_empty() {
+            total += sample.len() as u64;
+            samples.push(sample);
+        }
+    }
+    if samples.len() < MIN_DICT_SAMPLES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Dictionary training needs at least {} non-empty files, found {}", MIN_DICT_SAMPLES, samples.len()),
+        ).into());
+    }
+    status!(opts, "Training a {} byte dictionary on {} samples ({} bytes)...", dict_size, samples.len(), total);
+    let dict = zstd::dict::from_samples(&samples, dict_size)
+        .map_err(|e| io::Error::new(e.kind(), format!("Dictionary training failed: {}", e)))?;
+    fs::write(dict_out, &dict)?;
+    summary!(opts, "Dictionary written to {:?} ({} bytes); use it with --dict", dict_out, dict.len());
+    Ok(())
+}
+
+/// Niveau, dictionnaire et threads de l'encodeur zstd, communs à toutes les sorties zstd
+struct ZstdSettings {
+    level: i32,
+    dict: Option<Vec<u8>>,
+    threads: u32,
+    long: Option<u32>,
+}
+
+impl ZstdSettings {
+    fn from_args(opts: &CompressOptions) -> io::Result<Self> {
+        Ok(ZstdSettings {
+            level: opts.zstd_level,
+            dict: opts.dict.as_ref().map(fs::read).transpose()?,
+            threads: encoder_threads(opts),
+            long: opts.zstd_long,
+        })
+    }
+
+    fn encoder<W: Write>(&self, writer: W) -> io::Result<ZstdEncoder<'static, W>> {
+        let mut encoder = match &self.dict {
+            Some(dict) => ZstdEncoder::with_dictionary(writer, self.level, dict)?,
+            None => ZstdEncoder::new(writer, self.level)?,
+        };
+        // 1 thread : mode historique, sans le découpage en tâches de zstdmt
+        if self.threads > 1 {
+            encoder.multithread(self.threads)?;
+        }
+        if let Some(window_log) = self.long {
+            encoder.long_distance_matching(true)?;
+            encoder.window_log(window_log)?;
+        }
+        Ok(encoder)
+    }
+}
+
+/// `--threads` des encodeurs, 0 valant le nombre de cœurs
+fn encoder_threads(opts: &CompressOptions) -> u32 {
+    match opts.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+        n => n,
+    }
+}
+
+/// Encodeur xz au preset `--xz-preset`. Avec plusieurs threads, liblzma découpe l'entrée en
+/// blocs (3 × le dictionnaire, 192 Mio au preset 9) compressés en parallèle : le flux reste
+/// un .xz standard, mais chaque thread réserve la mémoire d'un encodeur complet.
+fn xz_encoder<W: Write>(writer: W, opts: &CompressOptions) -> io::Result<XzEncoder<W>> {
+    let threads = encoder_threads(opts);
+    if threads <= 1 {
+        return Ok(XzEncoder::new(writer, opts.xz_preset));
+    }
+    let stream = xz2::stream::MtStreamBuilder::new()
+        .threads(threads)
+        .preset(opts.xz_preset)
+        .check(xz2::stream::Check::Crc64)
+        .encoder()?;
+    Ok(XzEncoder::new_stream(writer, stream))
+}
+
+/// Log2 de la fenêtre, de la table de chaîne et de la table de hachage de zstd aux niveaux
+/// 1 à 22, pour les grandes entrées (`clevels.h` de zstd 1.5)
+const ZSTD_LEVEL_LOGS: [(u32, u32, u32); 22] = [
+    (19, 13, 14), (20, 15, 16), (21, 16, 17), (21, 18, 18), (21, 18, 19), (21, 18, 19),
+    (21, 19, 20), (21, 19, 20), (22, 20, 21), (22, 21, 22), (22, 21, 22), (22, 22, 23),
+    (22, 22, 22), (22, 22, 23), (22, 23, 23), (22, 22, 22), (23, 23, 22), (23, 23, 22),
+    (23, 24, 22), (25, 25, 23), (26, 26, 24), (27, 27, 25),
+];
+
+/// Mémoire des encodeurs xz aux presets 0 à 9, en Mio (tableau de xz(1))
+const XZ_PRESET_MIB: [u64; 10] = [3, 9, 17, 32, 48, 94, 94, 186, 370, 674];
+
+/// Estimation grossière de la mémoire réservée par les encodeurs du format, couches
+/// additionnées (zstd + xz pour le format sharky) ; 0 pour un tar nu
+fn encoder_memory(opts: &CompressOptions, format: Format) -> u64 {
+    // deflate : fenêtre de 32 Kio, tables de hachage et tampons (niveaux par défaut de zlib)
+    const DEFLATE: u64 = 256 << 10;
+    match format {
+        Format::Sharky => zstd_memory(opts) + xz_memory(opts),
+        Format::TarZst | Format::Zst => zstd_memory(opts),
+        Format::TarXz | Format::Xz => xz_memory(opts),
+        Format::SevenZ => XZ_PRESET_MIB[opts.xz_preset.min(9) as usize] << 20,
+        // 400 Kio + 8 fois le bloc de 100 Kio × niveau (documentation de bzip2)
+        Format::TarBz2 | Format::Bz2 => (400 << 10) + 8 * 100_000 * u64::from(opts.xz_preset.clamp(1, 9)),
+        Format::TarGz | Format::Gz | Format::Zip => DEFLATE,
+        Format::TarBrotli | Format::Brotli => {
+            // Fenêtre circulaire, et arbre binaire de recherche (8 octets par position) aux qualités 10–11
+            let window = 1u64 << opts.brotli_window;
+            if opts.brotli_quality >= 10 { 9 * window + (3 << 20) } else { 2 * window + (1 << 20) }
+        }
+        Format::TarLz4 | Format::Lz4 => DEFLATE,
+        Format::TarLzfse | Format::Lzfse => 1 << 20,
+        _ => 0,
+    }
+}
+
+/// Encodeur zstd : fenêtre et tables du niveau, table LDM (1/16 de la fenêtre) avec
+/// `--zstd-long`, dictionnaire ; en multithread, un contexte et deux tampons de tâche
+/// (4 fenêtres chacun, 512 Mio au plus) par thread
+fn zstd_memory(opts: &CompressOptions) -> u64 {
+    let level = match opts.zstd_level {
+        0 => 3,
+        level => level.clamp(1, 22) as usize,
+    };
+    let (window_log, chain_log, hash_log) = ZSTD_LEVEL_LOGS[level - 1];
+    let window = 1u64 << opts.zstd_long.map_or(window_log, |long| long.max(window_log));
+    // Les stratégies « fast » (niveaux 1–2) n'utilisent pas de table de chaîne
+    let chain = if level <= 2 { 0 } else { 4u64 << chain_log };
+    let ldm = if opts.zstd_long.is_some() { window / 16 } else { 0 };
+    let dict = opts.dict.as_deref().map_or(0, file_size);
+    let context = window + (4u64 << hash_log) + chain + ldm + dict + (1 << 20);
+    match u64::from(encoder_threads(opts)) {
+        threads if threads > 1 => threads * (context + 2 * (4 * window).min(512 << 20)),
+        _ => context,
+    }
+}
+
+/// Encodeur xz : tableau de xz(1), ou calcul de liblzma pour l'encodeur multithread
+fn xz_memory(opts: &CompressOptions) -> u64 {
+    let threads = encoder_threads(opts);
+    if threads > 1 {
+        return xz2::stream::MtStreamBuilder::new().threads(threads).preset(opts.xz_preset).memusage();
+    }
+    XZ_PRESET_MIB[opts.xz_preset.min(9) as usize] << 20
+}
+
+/// Décodeur zstd acceptant les fenêtres de `--zstd-long` jusqu'à 2 Gio ; la limite par
+/// défaut (128 Mio, log 27) refuserait les flux écrits avec une fenêtre plus grande
+fn zstd_decoder<R: BufRead>(reader: R) -> io::Result<ZstdDecoder<'static, R>> {
+    let mut decoder = ZstdDecoder::with_buffer(reader)?;
+    decoder.window_log_max(31)?;
+    Ok(decoder)
+}
+
+/// Volume de tar écrit entre deux points de contrôle `--resume`
+const CHECKPOINT_INTERVAL: u64 = 64 * 1024 * 1024;
+
+/// Point de contrôle `--resume` : entrées du parcours déjà archivées et longueur
+/// valide de la sortie à ce moment-là
+#[derive(Debug, PartialEq, Eq)]
+struct Checkpoint {
+    format: Format,
+    entries: usize,
+    offset: u64,
+}
+
+impl Checkpoint {
+    fn path(output: &Path) -> PathBuf {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".checkpoint");
+        PathBuf::from(name)
+    }
+
+    fn load(path: &Path) -> io::Result<Option<Self>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Corrupt checkpoint file {:?}", path));
+        let value: serde_json::Value = serde_json::from_slice(&data).map_err(|_| invalid())?;
+        Ok(Some(Checkpoint {
+            format: value["format"].as_str().and_then(Format::from_name).ok_or_else(invalid)?,
+            entries: value["entries"].as_u64().ok_or_else(invalid)? as usize,
+            offset: value["offset"].as_u64().ok_or_else(invalid)?,
+        }))
+    }
+
+    /// Écriture atomique : fichier temporaire puis renommage
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::json!({
+            "format": self.format.name(),
+            "entries": self.entries,
+            "offset": self.offset,
+        });
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json.to_string())?;
+        fs::rename(&tmp, path)
+    }
+}
+
+/// Sortie de `--resume`. En tar.zst, chaque point de contrôle termine la trame zstd
+/// en cours : la sortie tronquée à cet offset reste un flux valide de trames concaténées.
+struct ResumableSink {
+    inner: SinkInner,
+    /// réglages zstd, `None` pour un tar brut
+    zstd: Option<ZstdSettings>,
+    /// octets de tar reçus depuis le dernier point de contrôle
+    pending: u64,
+}
+
+enum SinkInner {
+    Plain(BufWriter<File>),
+    Zstd(ZstdEncoder<'static, BufWriter<File>>),
+    Closed,
+}
+
+impl ResumableSink {
+    fn new(out: BufWriter<File>, zstd: Option<ZstdSettings>) -> io::Result<Self> {
+        let mut sink = ResumableSink { inner: SinkInner::Closed, zstd, pending: 0 };
+        sink.inner = sink.wrap(out)?;
+        Ok(sink)
+    }
+
+    fn wrap(&self, out: BufWriter<File>) -> io::Result<SinkInner> {
+        Ok(match &self.zstd {
+            Some(zstd) => SinkInner::Zstd(zstd.encoder(out)?),
+            None => SinkInner::Plain(out),
+        })
+    }
+
+    /// Termine la trame en cours, synchronise le fichier et renvoie la longueur valide
+    fn checkpoint(&mut self) -> io::Result<u64> {
+        let mut out = self.close()?;
+        let offset = out.stream_position()?;
+        self.inner = self.wrap(out)?;
+        self.pending = 0;
+        Ok(offset)
+    }
+
+    fn close(&mut self) -> io::Result<BufWriter<File>> {
+        let mut out = match std::mem::replace(&mut self.inner, SinkInner::Closed) {
+            SinkInner::Plain(out) => out,
+            SinkInner::Zstd(encoder) => encoder.finish()?,
+            SinkInner::Closed => return Err(io::Error::other("resumable output already closed")),
+        };
+        out.flush()?;
+        out.get_ref().sync_data()?;
+        Ok(out)
+    }
+}
+
+impl Write for ResumableSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match &mut self.inner {
+            SinkInner::Plain(out) => out.write(buf)?,
+            SinkInner::Zstd(encoder) => encoder.write(buf)?,
+            SinkInner::Closed => return Err(io::Error::other("resumable output already closed")),
+        };
+        self.pending += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            SinkInner::Plain(out) => out.flush(),
+            SinkInner::Zstd(encoder) => encoder.flush(),
+            SinkInner::Closed => Ok(()),
+        }
+    }
+}
+
+/// `--resume` : reprend après le dernier point de contrôle s'il en existe un (sortie
+/// tronquée à l'offset enregistré, entrées déjà archivées sautées), sinon part de zéro.
+/// Le parcours est trié par nom pour que « les entrées restantes » soient bien définies.
+fn compress_tar_resumable(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    let checkpoint_path = Checkpoint::path(opts.output());
+    let resume_from = Checkpoint::load(&checkpoint_path)?;
+    let file = match &resume_from {
+        Some(checkpoint) => {
+            if checkpoint.format != format {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Checkpoint {:?} was written for a {} output, not {}", checkpoint_path, checkpoint.format.name(), format.name()),
+                ));
+            }
+            let mut file = fs::OpenOptions::new().write(true).open(opts.output())?;
+            if file.metadata()?.len() < checkpoint.offset {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} is shorter than its checkpoint ({} bytes)", opts.output(), checkpoint.offset),
+                ));
+            }
+            file.set_len(checkpoint.offset)?;
+            file.seek(SeekFrom::End(0))?;
+            status!(opts, "Resuming after {} entries ({} bytes kept)", checkpoint.entries, checkpoint.offset);
+            file
+        }
+        None => File::create(opts.output())?,
+    };
+
+    let zstd = match format {
+        Format::TarZst => Some(ZstdSettings::from_args(opts)?),
+        _ => None,
+    };
+    let mut builder = Builder::new(ResumableSink::new(BufWriter::with_capacity(opts.buffer_size, file), zstd)?);
+    let (pb, mtime) = build_progress(opts, filter)?;
+    let skip = resume_from.as_ref().map_or(0, |c| c.entries);
+    let mut after_entry = |builder: &mut Builder<ResumableSink>, entries: usize| -> io::Result<()> {
+        if builder.get_ref().pending < CHECKPOINT_INTERVAL {
+            return Ok(());
+        }
+        let offset = builder.get_mut().checkpoint()?;
+        Checkpoint { format, entries, offset }.save(&checkpoint_path)
+    };
+    let total = traverse_and_append(&mut builder, &pb, filter, opts, mtime, log, Some((skip, &mut after_entry)))?;
+    pb.finish_and_clear();
+    builder.into_inner()?.close()?;
+
+    // Archive reprise : on relit la sortie pour s'assurer qu'elle contient bien toutes les entrées
+    if resume_from.is_some() {
+        let reader = BufReader::with_capacity(opts.buffer_size, File::open(opts.output())?);
+        let found = match format {
+            Format::TarZst => tar_entries(zstd_decoder(reader)?)?.len(),
+            _ => tar_entries(reader)?.len(),
+        };
+        if found != total {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Resumed archive has {} entries, expected {}; checkpoint kept", found, total),
+            ));
+        }
+    }
+    fs::remove_file(&checkpoint_path).or_else(|e| if e.kind() == io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+}
+
+/// Chaîne de méthodes 7z correspondant au nom passé à `--7z-method`.
+/// Le writer de sevenz-rust ne sait encoder que LZMA et LZMA2.
+fn sevenz_methods(name: &str, preset: u32) -> io::Result<Vec<SevenZMethodConfiguration>> {
+    match name.to_lowercase().as_str() {
+        "lzma2" => Ok(vec![LZMA2Options::with_preset(preset).into()]),
+        "lzma" => Ok(vec![
+            SevenZMethodConfiguration::new(SevenZMethod::LZMA)
+                .with_options(LZMA2Options::with_preset(preset).into()),
+        ]),
+        "ppmd" | "bcj+lzma2" => Err(unsupported(format!("7z method '{}' is not supported by the 7z writer backend (available: lzma2, lzma)", name))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown 7z method '{}' (expected lzma2, lzma, ppmd or bcj+lzma2)", name),
+        )),
+    }
+}
+
+fn compress_7z(opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+    let mut methods = sevenz_methods(&opts.sevenz_method, opts.xz_preset)?;
+    if let Some(password) = &opts.password {
+        // Le chiffrement s'applique en dernier : il vient en tête de la chaîne des méthodes
+        let aes = sevenz_rust::AesEncoderOptions::new(sevenz_password(Some(password)));
+        methods.insert(0, aes.into());
+    }
+    status!(
+        opts,
+        "Compression: {:?} → {:?} (7z {}, preset {})",
+        opts.input(), opts.output(), opts.sevenz_method.to_lowercase(), opts.xz_preset
+    );
+
+    let mut writer = SevenZWriter::create(opts.output())
+        .map_err(|e| io::Error::other(format!("Failed to create 7Z archive: {}", e)))?;
+    writer.set_content_methods(methods);
+
+    // Ni 7z ni zip n'archivent de liens symboliques : ils sont toujours suivis
+    let (pb, mtime) = build_progress(opts, filter)?;
+    let mtime = mtime
+        .map(|t| sevenz_rust::nt_time::FileTime::from_unix_time(t as i64))
+        .transpose()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let walker = WalkDir::new(opts.input())
+        .min_depth(walk_min_depth(opts))
+        .max_depth(opts.max_depth.unwrap_or(usize::MAX))
+        .follow_links(true);
+    for entry in walker.into_iter().filter_map(|e| walk_entry(&pb, e)) {
+        let path = entry.path();
+        if filter.is_excluded(path) || skip_by_size(opts, log, path, entry.file_type()) { continue }
+        if let Some(e) = unreadable(opts, path, entry.file_type().is_dir()) {
+            skip_unreadable(&pb, log, path, e)?;
+            continue;
+        }
+        let name = walked_entry_name(opts, path).to_string_lossy().into_owned();
+        compressing(opts, &pb, &name);
+
+        let mut archive_entry = SevenZArchiveEntry::from_path(path, name);
+        // sevenz-rust 0.6.1 (`write_file_anti_items`) pose le bit kAnti des entrées sans flux
+        // qui ne sont *pas* marquées : un fichier vide ou un répertoire laissé à `false`
+        // deviendrait une suppression pour 7-Zip. Toutes marquées, aucun bit n'est écrit ;
+        // à la lecture, `sevenz_anti_items` ignore les archives où toutes le sont.
+        archive_entry.is_anti_item = true;
+        if let Some(mtime) = mtime {
+            archive_entry.last_modified_date = mtime;
+            archive_entry.has_last_modified_date = true;
+        }
+        let reader = if entry.file_type().is_dir() { None } else { Some(pb.wrap_read(RetryReader::open(path, opts.io_retries)?)) };
+        let result = writer.push_archive_entry(archive_entry, reader)
+            .map(|_| ())
+            .map_err(|e| io::Error::other(format!("7Z compression error: {}", e)));
+        let size = if entry.file_type().is_dir() { 0 } else { entry.metadata().map(|m| m.len()).unwrap_or(0) };
+        log.record("add", path, size, size, &result)?;
+        result?;
+    }
+    writer.finish()?;
+    pb.finish_and_clear();
+
+    report_output_size(opts, filter)
+}
+
+/// Niveau deflate des zip (0–9) : `--level`, à défaut le niveau Zstd (0–22) ramené sur cette échelle
+fn zip_level(opts: &CompressOptions) -> i32 {
+    match opts.level {
+        Some(level) if !opts.codec_flags_given.contains(&"--zstd-level") => level as i32,
+        _ => (opts.zstd_level * 9 + 11) / 22,
+    }
+}
+
+/// Extensions de formats déjà compressés (images, audio, vidéo, archives), stockées
+/// telles quelles par `--store-extensions` donné sans liste
+pub const STORE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "avif", "mp3", "m4a", "aac", "ogg", "opus", "flac", "mp4", "m4v",
+    "mkv", "mov", "avi", "webm", "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "br", "lz4", "jar", "apk",
+    "docx", "xlsx", "pptx", "odt", "woff2",
+];
+
+/// Fichier dont l'extension figure dans `--store-extensions`
+fn stores_extension(opts: &CompressOptions, path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else { return false };
+    opts.store_extensions.iter().any(|stored| stored.eq_ignore_ascii_case(ext))
+}
+
+/// `--compress-if-smaller` pour une entrée zip : le deflate de `path` au niveau `level`
+/// est-il plus petit que le fichier ? Le fichier est lu une première fois pour rien.
+fn deflate_shrinks(path: &Path, size: u64, level: i64, retries: u32) -> io::Result<bool> {
+    let level = flate2::Compression::new(level.clamp(0, 9) as u32);
+    let mut encoder = flate2::write::DeflateEncoder::new(io::sink(), level);
+    io::copy(&mut RetryReader::open(path, retries)?, &mut encoder)?;
+    encoder.try_finish()?;
+    Ok(encoder.total_out() < size)
+}
+
+/// Archive ZIP (deflate), au niveau de `zip_level`. Le niveau 0 stocke les fichiers sans
+/// compression, comme ceux dont l'extension est dans `--store-extensions` et, avec
+/// `--compress-if-smaller`, ceux que le deflate ne réduit pas.
+fn compress_zip(opts: &CompressOptions, filter: &ExcludeFilter, log: &mut EventLog) -> io::Result<()> {
+    let (method, level) = match zip_level(opts) {
+        0 => (zip::CompressionMethod::Stored, None),
+        level => (zip::CompressionMethod::Deflated, Some(level as i64)),
+    };
+    status!(
+        opts,
+        "Compression: {:?} → {:?} (zip {})",
+        opts.input(), opts.output(), level.map_or("stored".to_string(), |l| format!("deflate, level {}", l))
+    );
+
+    let mut writer = ZipWriter::new(BufWriter::with_capacity(opts.buffer_size, File::create(opts.output())?));
+    let (pb, mtime) = build_progress(opts, filter)?;
+    let mut fallbacks = 0;
+    let walker = WalkDir::new(opts.input())
+        .min_depth(walk_min_depth(opts))
+        .max_depth(opts.max_depth.unwrap_or(usize::MAX))
+        .follow_links(true);
+    for entry in walker.into_iter().filter_map(|e| walk_entry(&pb, e)) {
+        let path = entry.path();
+        if filter.is_excluded(path) || skip_by_size(opts, log, path, entry.file_type()) { continue }
+        if let Some(e) = unreadable(opts, path, entry.file_type().is_dir()) {
+            skip_unreadable(&pb, log, path, e)?;
+            continue;
+        }
+        let name = walked_entry_name(opts, path);
+        // Les noms ZIP utilisent toujours '/' comme séparateur
+        let name: Vec<_> = name.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        let name = name.join("/");
+        compressing(opts, &pb, &name);
+
+        let meta = entry.metadata().map_err(io::Error::other)?;
+        let modified = mtime.or_else(|| {
+            meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
+        });
+        // Fichier déjà compressé : le deflate n'y gagnerait presque rien
+        let (method, level) = if entry.file_type().is_file() && stores_extension(opts, path) {
+            (zip::CompressionMethod::Stored, None)
+        } else if let Some(deflate) = level
+            && opts.compress_if_smaller
+            && entry.file_type().is_file()
+            && !deflate_shrinks(path, meta.len(), deflate, opts.io_retries)?
+        {
+            fallbacks += 1;
+            if opts.verbose {
+                pb.suspend(|| status!(opts, "Stored {}: deflate does not shrink it", name));
+            }
+            (zip::CompressionMethod::Stored, None)
+        } else {
+            (method, level)
+        };
+        let mut options = SimpleFileOptions::default()
+            .compression_method(method)
+            .compression_level(level)
+            .large_file(meta.len() >= u32::MAX as u64);
+        if let Some(datetime) = modified.and_then(zip_datetime) {
+            options = options.last_modified_time(datetime);
+        }
+
+        let (size, result) = if entry.file_type().is_dir() {
+            (0, writer.add_directory(format!("{}/", name), options).map_err(io::Error::from))
+        } else {
+            let result = writer.start_file(name, options)
+                .map_err(io::Error::from)
+                .and_then(|()| io::copy(&mut pb.wrap_read(RetryReader::open(path, opts.io_retries)?), &mut writer).map(|_| ()));
+            (meta.len(), result)
+        };
+        log.record("add", path, size, size, &result)?;
+        result?;
+    }
+    writer.finish()?.flush()?;
+    pb.finish_and_clear();
+    if opts.zip_name_encoding == ZipNameEncoding::Cp437 {
+        recode_zip_names_cp437(opts.output())?;
+    }
+    if fallbacks > 0 {
+        summary!(opts, "Stored {} entry(ies) uncompressed: deflate did not make them smaller", fallbacks);
+    }
+
+    report_output_size(opts, filter)
+}
+
+/// Caractères CP437 des octets 0x80 à 0xFF (0x00–0x7F : ASCII)
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// Encode `name` en CP437 ; les caractères absents de la page de code deviennent '_'.
+/// Renvoie aussi `false` si au moins un caractère a été remplacé.
+fn encode_cp437(name: &str) -> (Vec<u8>, bool) {
+    let mut exact = true;
+    let bytes = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c as u8,
+            c => match CP437_HIGH.chars().position(|h| h == c) {
+                Some(i) => 0x80 + i as u8,
+                None => {
+                    exact = false;
+                    b'_'
+                }
+            },
+        })
+        .collect();
+    (bytes, exact)
+}
+
+/// `--zip-name-encoding cp437` : le writer zip ne sait écrire que des noms UTF-8, on
+/// réécrit donc l'archive terminée en recodant chaque nom (en-tête local et répertoire
+/// central) et en retirant le drapeau UTF-8 (bit 11), puis on recalcule les offsets.
+fn recode_zip_names_cp437(path: &Path) -> io::Result<()> {
+    let data = fs::read(path)?;
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Cannot recode zip names: {}", what));
+
+    let eocd = (0..=data.len().saturating_sub(22))
+        .rev()
+        .find(|&pos| data[pos..pos + 4] == *b"PK\x05\x06")
+        .ok_or_else(|| invalid("end of central directory not found"))?;
+    if eocd >= 20 && data[eocd - 20..eocd - 16] == *b"PK\x06\x07" {
+        return Err(unsupported("cp437 entry names are not supported for zip64 archives"));
+    }
+    let count = u16_at(eocd + 10);
+    let cd_start = u32_at(eocd + 16);
+
+    // (début de l'en-tête central, nom recodé, offset de l'en-tête local)
+    let mut entries = Vec::with_capacity(count);
+    let mut pos = cd_start;
+    for _ in 0..count {
+        if data.get(pos..pos + 4) != Some(b"PK\x01\x02") {
+            return Err(invalid("corrupt central directory"));
+        }
+        let name_len = u16_at(pos + 28);
+        let name = String::from_utf8_lossy(&data[pos + 46..pos + 46 + name_len]);
+        let (encoded, exact) = encode_cp437(&name);
+        if !exact {
+            eprintln!("Warning: '{}' cannot be represented in CP437, stored as '{}'", name, String::from_utf8_lossy(&encoded));
+        }
+        entries.push((pos, encoded, u32_at(pos + 42)));
+        pos += 46 + name_len + u16_at(pos + 30) + u16_at(pos + 32);
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut local_offsets = vec![0u32; count];
+    let mut by_offset: Vec<usize> = (0..count).collect();
+    by_offset.sort_by_key(|&i| entries[i].2);
+    for (n, &i) in by_offset.iter().enumerate() {
+        let local = entries[i].2;
+        let end = by_offset.get(n + 1).map_or(cd_start, |&next| entries[next].2);
+        let name_len = u16_at(local + 26);
+        local_offsets[i] = out.len() as u32;
+        out.extend_from_slice(&data[local..local + 30]);
+        let header = out.len() - 30;
+        let flags = u16_at(local + 6) as u16 & !0x0800;
+        out[header + 6..header + 8].copy_from_slice(&flags.to_le_bytes());
+        out[header + 26..header + 28].copy_from_slice(&(entries[i].1.len() as u16).to_le_bytes());
+        out.extend_from_slice(&entries[i].1);
+        out.extend_from_slice(&data[local + 30 + name_len..end]);
+    }
+
+    let new_cd_start = out.len();
+    for (i, (central, name, _)) in entries.iter().enumerate() {
+        let name_len = u16_at(central + 28);
+        let tail = u16_at(central + 30) + u16_at(central + 32);
+        let header = out.len();
+        out.extend_from_slice(&data[*central..central + 46]);
+        let flags = u16_at(central + 8) as u16 & !0x0800;
+        out[header + 8..header + 10].copy_from_slice(&flags.to_le_bytes());
+        out[header + 28..header + 30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        out[header + 42..header + 46].copy_from_slice(&local_offsets[i].to_le_bytes());
+        out.extend_from_slice(name);
+        out.extend_from_slice(&data[central + 46 + name_len..central + 46 + name_len + tail]);
+    }
+    let cd_size = (out.len() - new_cd_start) as u32;
+    let footer = out.len();
+    out.extend_from_slice(&data[eocd..]);
+    out[footer + 12..footer + 16].copy_from_slice(&cd_size.to_le_bytes());
+    out[footer + 16..footer + 20].copy_from_slice(&(new_cd_start as u32).to_le_bytes());
+    fs::write(path, out)
+}
+
+/// Date ZIP (MS-DOS, 1980–2107) d'un horodatage Unix ; `None` hors de cette plage
+fn zip_datetime(secs: u64) -> Option<zip::DateTime> {
+    let datetime = time::OffsetDateTime::from_unix_timestamp(secs as i64).ok()?;
+    zip::DateTime::try_from(datetime).ok()
+}
+
+/// Sortie `.lzfse` : un fichier seul est compressé tel quel, un répertoire
+/// (ou une sortie `.tar.lzfse`) passe d'abord par tar.
+fn compress_lzfse(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    let wrap_tar = opts.input().is_dir() || format == Format::TarLzfse;
+    status!(
+        opts,
+        "Compression: {:?} → {:?} ({})",
+        opts.input(), opts.output(), if wrap_tar { "tar → lzfse" } else { "lzfse" }
+    );
+
+    let outfile = create_output(opts)?;
+    let mut encoder = LzfseRingEncoder::default();
+    let mut writer = encoder.writer(outfile);
+    if wrap_tar {
+        write_tar(opts, filter, &mut writer, log)?;
+    } else {
+        let mut hashing = HashingReader { inner: RetryReader::open(opts.input(), opts.io_retries)?, hasher: Sha256::new() };
+        let size = io::copy(&mut hashing, &mut writer)?;
+        log.record("add", opts.input(), size, size, &Ok(()))?;
+        let name = archive_root(opts);
+        log.manifest(name, size, Some(&hex_digest(hashing.hasher)))?;
+    }
+    writer.finalize()?.flush()?;
+
+    report_output_size(opts, filter)
+}
+
+/// Sortie `.gz`, `.bz2`, `.xz` ou `.zst` d'un seul fichier : le flux compressé, sans tar
+fn compress_single_file(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    if opts.input().is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} output compresses a single file; use tar.{} for directories", format.name(), format.name()),
+        ));
+    }
+    status!(opts, "Compression: {:?} → {:?} ({})", opts.input(), opts.output(), format.name());
+
+    let outfile = create_output(opts)?;
+    let mut input = HashingReader { inner: RetryReader::open(opts.input(), opts.io_retries)?, hasher: Sha256::new() };
+    let (size, mut outfile) = match format {
+        Format::Gz => copy_into(&mut input, gz_single_encoder(opts.input(), archive_root(opts), outfile, deflate_level(opts))?)
+            .and_then(|(size, encoder)| Ok((size, encoder.finish()?)))?,
+        Format::Bz2 => copy_into(&mut input, BzEncoder::new(outfile, bzip2_level(opts)?))
+            .and_then(|(size, encoder)| Ok((size, encoder.finish()?)))?,
+        Format::Xz => copy_into(&mut input, xz_encoder(outfile, opts)?)
+            .and_then(|(size, encoder)| Ok((size, encoder.finish()?)))?,
+        _ => copy_into(&mut input, ZstdSettings::from_args(opts)?.encoder(outfile)?)
+            .and_then(|(size, encoder)| Ok((size, encoder.finish()?)))?,
+    };
+    outfile.flush()?;
+    log.record("add", opts.input(), size, size, &Ok(()))?;
+    let name = archive_root(opts);
+    log.manifest(name, size, Some(&hex_digest(input.hasher)))?;
+
+    report_output_size(opts, filter)
+}
+
+/// Encodeur gzip d'un fichier seul, qui consigne son nom `name` (FNAME) et sa date (MTIME)
+/// dans l'en-tête comme `gzip`
+fn gz_single_encoder<W: Write>(input: &Path, name: &Path, writer: W, level: flate2::Compression) -> io::Result<GzEncoder<W>> {
+    let mut builder = flate2::GzBuilder::new();
+    if !name.as_os_str().is_empty() {
+        builder = builder.filename(name.as_os_str().as_encoded_bytes());
+    }
+    // MTIME tient sur 32 bits ; 0 signifie « pas de date »
+    let mtime = fs::metadata(input)?.modified().ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|d| u32::try_from(d.as_secs()).ok())
+        .unwrap_or(0);
+    Ok(builder.mtime(mtime).write(writer, level))
+}
+
+/// Copie `reader` dans l'encodeur et le rend pour que l'appelant le termine
+fn copy_into<R: Read, W: Write>(reader: &mut R, mut writer: W) -> io::Result<(u64, W)> {
+    let size = io::copy(reader, &mut writer)?;
+    Ok((size, writer))
+}
+
+/// Compresse un tampon en mémoire en flux mono-fichier, sans tar : gz, bz2, xz, zst, br ou lz4
+/// (trame LZ4, reconnaissable à sa signature). `level` suit l'échelle du codec : gz 0–9, bz2 1–9,
+/// xz 0–9, zst 1–22, br 0–11 ; lz4 l'ignore.
+pub fn compress_bytes(data: &[u8], format: Format, level: i32) -> io::Result<Vec<u8>> {
+    let levels = match format {
+        Format::Gz | Format::Xz => 0..=9,
+        Format::Bz2 => 1..=9,
+        Format::Zst => 1..=22,
+        Format::Brotli => 0..=11,
+        Format::Lz4 => level..=level,
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("compress_bytes writes gz, bz2, xz, zst, br or lz4 streams, not {}", other.name()),
+            ));
+        }
+    };
+    if !levels.contains(&level) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Level {} is out of range for {} ({}–{})", level, format.name(), levels.start(), levels.end()),
+        ));
+    }
+
+    let mut reader = data;
+    let sink = Cursor::new(Vec::new());
+    let sink = match format {
+        Format::Gz => copy_into(&mut reader, GzEncoder::new(sink, flate2::Compression::new(level as u32)))
+            .and_then(|(_, encoder)| encoder.finish())?,
+        Format::Bz2 => copy_into(&mut reader, BzEncoder::new(sink, bzip2::Compression::new(level as u32)))
+            .and_then(|(_, encoder)| encoder.finish())?,
+        Format::Xz => copy_into(&mut reader, XzEncoder::new(sink, level as u32))
+            .and_then(|(_, encoder)| encoder.finish())?,
+        Format::Zst => copy_into(&mut reader, ZstdEncoder::new(sink, level)?)
+            .and_then(|(_, encoder)| encoder.finish())?,
+        Format::Brotli => {
+            let (_, mut writer) = copy_into(&mut reader, brotli::CompressorWriter::new(sink, 4096, level as u32, 22))?;
+            writer.flush()?;
+            writer.into_inner()
+        }
+        _ => copy_into(&mut reader, lz4_flex::frame::FrameEncoder::new(sink))
+            .and_then(|(_, encoder)| encoder.finish().map_err(io::Error::other))?,
+    };
+    Ok(sink.into_inner())
+}
+
+/// Décompresse un flux mono-fichier en mémoire, reconnu à sa signature (gz, bz2, xz, zst, trame lz4).
+/// Brotli n'ayant pas de signature, un tampon non reconnu est décodé comme tel.
+pub fn decompress_bytes(data: &[u8]) -> io::Result<Vec<u8>> {
+    let source = Cursor::new(data);
+    let mut out = Vec::new();
+    match Format::from_magic(data) {
+        Some(Format::Gz) => GzDecoder::new(source).read_to_end(&mut out)?,
+        Some(Format::Bz2) => BzDecoder::new(source).read_to_end(&mut out)?,
+        Some(Format::Xz) => XzDecoder::new(source).read_to_end(&mut out)?,
+        Some(Format::Zst) => zstd_decoder(source)?.read_to_end(&mut out)?,
+        Some(Format::TarLz4) => lz4_flex::frame::FrameDecoder::new(source).read_to_end(&mut out)?,
+        None => BrotliDecoder::new(source, 4096).read_to_end(&mut out).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Unrecognized compressed data: {}", e))
+        })?,
+        Some(other) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("decompress_bytes reads single-stream data, not {} archives", other.name()),
+            ));
+        }
+    };
+    Ok(out)
+}
+
+/// Sortie `.br` : un fichier seul est compressé tel quel, un répertoire (ou une sortie
+/// `.tar.br`) passe d'abord par tar. Brotli n'ayant pas de signature, la lecture reconnaît
+/// le tar interne après décodage.
+fn compress_brotli(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    if opts.brotli_quality > 11 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Brotli quality must be between 0 and 11 (got {})", opts.brotli_quality),
+        ));
+    }
+    if !(10..=24).contains(&opts.brotli_window) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Brotli window must be between 10 and 24 (got {})", opts.brotli_window),
+        ));
+    }
+    let wrap_tar = opts.input().is_dir() || format == Format::TarBrotli;
+    status!(
+        opts,
+        "Compression: {:?} → {:?} ({}, quality {}, window {})",
+        opts.input(), opts.output(), if wrap_tar { "tar → brotli" } else { "brotli" },
+        opts.brotli_quality, opts.brotli_window
+    );
+
+    let mut outfile = create_output(opts)?;
+    let mut writer = brotli::CompressorWriter::new(&mut outfile, 4096, opts.brotli_quality, opts.brotli_window);
+    if wrap_tar {
+        writer = write_tar(opts, filter, writer, log)?;
+    } else {
+        let mut hashing = HashingReader { inner: RetryReader::open(opts.input(), opts.io_retries)?, hasher: Sha256::new() };
+        let size = io::copy(&mut hashing, &mut writer)?;
+        log.record("add", opts.input(), size, size, &Ok(()))?;
+        let name = archive_root(opts);
+        log.manifest(name, size, Some(&hex_digest(hashing.hasher)))?;
+    }
+    // `into_inner` termine le flux sans rendre d'erreur : le gros des données part avec `flush`
+    writer.flush()?;
+    writer.into_inner();
+    outfile.flush()?;
+
+    report_output_size(opts, filter)
+}
+
+/// Sortie `.lz4` : un fichier seul devient un bloc à taille préfixée (`compress_prepend_size`),
+/// un répertoire (ou une sortie `.tar.lz4`) un tar dans une trame LZ4.
+fn compress_lz4(opts: &CompressOptions, filter: &ExcludeFilter, format: Format, log: &mut EventLog) -> io::Result<()> {
+    let wrap_tar = opts.input().is_dir() || format == Format::TarLz4;
+    status!(
+        opts,
+        "Compression: {:?} → {:?} ({})",
+        opts.input(), opts.output(), if wrap_tar { "tar → lz4 frame" } else { "lz4" }
+    );
+
+    let mut outfile = create_output(opts)?;
+    if wrap_tar {
+        let encoder = write_tar(opts, filter, lz4_flex::frame::FrameEncoder::new(&mut outfile), log)?;
+        encoder.finish().map_err(io::Error::other)?;
+    } else {
+        // Un bloc LZ4 à taille préfixée (32 bits) se compresse d'un seul tenant : le fichier
+        // est lu en entier, et au-delà de 4 Gio seule la trame (`tar.lz4`) convient
+        let len = fs::metadata(opts.input())?.len();
+        if len > u32::MAX as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("lz4 output holds at most 4 GiB ({} bytes given); use tar.lz4", len),
+            ));
+        }
+        let mut input = HashingReader { inner: RetryReader::open(opts.input(), opts.io_retries)?, hasher: Sha256::new() };
+        let mut data = Vec::with_capacity(len as usize);
+        input.read_to_end(&mut data)?;
+        outfile.write_all(&lz4_flex::compress_prepend_size(&data))?;
+        let size = data.len() as u64;
+        log.record("add", opts.input(), size, size, &Ok(()))?;
+        log.manifest(archive_root(opts), size, Some(&hex_digest(input.hasher)))?;
+    }
+    outfile.flush()?;
+
+    report_output_size(opts, filter)
+}
+
+/// `--list-formats` : formats reconnus, avec la chaîne de décodage et la prise en charge en écriture
+pub fn list_formats() {
+    println!("{:<10} {:<6} {:<6} CHAIN", "FORMAT", "READ", "WRITE");
+    for codec in codecs() {
+        let read = if codec.can_read() { "yes" } else { "no" };
+        let write = if codec.can_write() { "yes" } else { "no" };
+        println!("{:<10} {:<6} {:<6} {}", codec.format().name(), read, write, codec.decoder_chain());
+    }
+}
+
+/// Affiche la taille produite ; avec `--compress-if-smaller`, supprime la sortie
+/// si elle n'est pas plus petite que les données d'entrée. Les zip font ce choix entrée
+/// par entrée (`compress_zip`) et sont toujours gardés.
+fn report_output_size(opts: &CompressOptions, filter: &ExcludeFilter) -> io::Result<()> {
+    if opts.writes_stdout() {
+        return Ok(());
+    }
+    let size = fs::metadata(opts.output())?.len();
+    summary!(opts, "Output size: {} bytes", size);
+    if !opts.compress_if_smaller || output_format(opts)? == Format::Zip {
+        return Ok(());
+    }
+    let input_size: u64 = WalkDir::new(opts.input())
+        .max_depth(opts.max_depth.unwrap_or(usize::MAX))
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && !filter.is_excluded(e.path()))
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .filter(|&size| !size_excluded(opts, size))
+        .sum();
+    if size >= input_size {
+        fs::remove_file(opts.output())?;
+        summary!(
+            opts,
+            "Compression skipped: output ({} bytes) is not smaller than input ({} bytes); {:?} removed, original kept",
+            size, input_size, opts.output()
+        );
+    } else {
+        summary!(opts, "Kept compressed output: {} bytes saved", input_size - size);
+    }
+    Ok(())
+}
+
+/// Extrait l'archive `opts.input` (ou l'entrée standard pour `-`) dans `opts.output`
+pub fn decompress(opts: &DecompressOptions) -> Result<(), SharkyError> {
+    let start = Instant::now();
+    if !opts.verbose {
+        status!(opts, "© 2025, Matheo Simard");
+    }
+    if opts.test {
+        status!(opts, "Testing {:?}", opts.input());
+    } else {
+        status!(opts, "Decompressing {:?} → {:?}{}", opts.input(), opts.output(), if opts.dry_run { " (dry run)" } else { "" });
+    }
+    check_output_contains_input(opts)?;
+    let mut ex = Extractor::new(opts)?;
+    if !opts.writes_stdout() {
+        ex.create_parent(&opts.output().join("-"))?;
+    }
+    let from_stdin = opts.input().as_os_str() == "-";
+    let format = if from_stdin {
+        decompress_stdin(opts, &mut ex)?
+    } else {
+        decompress_file(opts, &mut ex)?
+    };
+    ex.finish()?;
+    if opts.test {
+        status!(opts, "{:?}: OK", opts.input());
+    }
+    if let Some(path) = &opts.stats_json {
+        let stats = Stats {
+            operation: "decompress",
+            format,
+            level: None,
+            input_bytes: if from_stdin { None } else { Some(fs::metadata(opts.input())?.len()) },
+            output_bytes: Some(ex.log.bytes + ex.stream_bytes.load(Ordering::Relaxed)),
+            elapsed: start.elapsed(),
+            entries: ex.log.entries + ex.streams.load(Ordering::Relaxed),
+        };
+        stats.write(path)?;
+    }
+    Ok(())
+}
+
+fn decompress_file(opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<Format> {
+    let (format, detection) = input_format(opts)?;
+    report_format(opts, format, detection, false);
+    decompress_as(opts, format, ex)?;
+    Ok(format)
+}
+
+/// Format de `--input` et la façon dont il a été déterminé. Extension absente ou
+/// inconnue : signature du contenu, puis format sharky par défaut.
+fn input_format(opts: &DecompressOptions) -> io::Result<(Format, &'static str)> {
+    Ok(match &opts.input_format_hint {
+        Some(hint) => (parse_format_hint(hint)?, "--input-format-hint"),
+        None => match Format::from_extension(opts.input()) {
+            Some(format) => (format, "extension"),
+            None => match sniff_format(opts.input())? {
+                Some(format) => (format, "magic bytes"),
+                None => (Format::Sharky, "default"),
+            },
+        },
+    })
+}
+
+/// Entrée d'archive lue sans extraction
+pub struct EntryInfo {
+    pub path: String,
+    pub size: u64,
+    /// Date de modification (secondes Unix), si le format la fournit
+    pub mtime: Option<i64>,
+    pub is_dir: bool,
+}
+
+/// Passe de métadonnées : parcourt l'archive `--input` sans rien écrire sur le disque
+pub fn read_entries(opts: &DecompressOptions) -> Result<Vec<EntryInfo>, SharkyError> {
+    let (format, _) = input_format(opts)?;
+    Ok(format.codec().entries(opts)?)
+}
+
+fn tar_entries<R: Read>(reader: R) -> io::Result<Vec<EntryInfo>> {
+    let mut archive = Archive::new(reader);
+    archive.entries()?
+        .map(|entry| {
+            let entry = entry?;
+            Ok(EntryInfo {
+                path: entry.path()?.display().to_string(),
+                size: entry.header().size()?,
+                mtime: entry.header().mtime().ok().map(|t| t as i64),
+                is_dir: entry.header().entry_type().is_dir(),
+            })
+        })
+        .collect()
+}
+
+/// `--list` : une ligne par entrée puis le total
+pub fn print_listing(entries: &[EntryInfo]) {
+    println!("{:>12}  {:<16}  Name", "Size", "Modified");
+    for entry in entries {
+        let modified = entry.mtime.and_then(format_mtime).unwrap_or_else(|| "-".to_string());
+        println!("{:>12}  {:<16}  {}", entry.size, modified, entry.path);
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    println!("{} entries, {} bytes", entries.len(), total);
+}
+
+/// Date Unix affichée `AAAA-MM-JJ HH:MM` (UTC)
+fn format_mtime(secs: i64) -> Option<String> {
+    let dt = time::OffsetDateTime::from_unix_timestamp(secs).ok()?;
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        dt.year(), dt.month() as u8, dt.day(), dt.hour(), dt.minute()
+    ))
+}
+
+/// `--largest N` : les N plus gros fichiers, avec leur part de la taille totale
+pub fn print_largest(entries: &[EntryInfo], n: usize) {
+    let mut files: Vec<&EntryInfo> = entries.iter().filter(|e| !e.is_dir).collect();
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    let total: u64 = files.iter().map(|e| e.size).sum();
+    println!("Largest {} of {} files:", n.min(files.len()), files.len());
+    for entry in files.iter().take(n) {
+        let percent = if total == 0 { 0.0 } else { entry.size as f64 * 100.0 / total as f64 };
+        println!("{:>12}  {:>5.1}%  {}", entry.size, percent, entry.path);
+    }
+}
+
+/// Histogramme des tailles de fichiers par tranches décimales (<1 KB … >100 MB)
+pub fn print_histogram(entries: &[EntryInfo]) {
+    const BUCKETS: [(&str, u64); 7] = [
+        ("< 1 KB", 1_000),
+        ("1-10 KB", 10_000),
+        ("10-100 KB", 100_000),
+        ("100 KB-1 MB", 1_000_000),
+        ("1-10 MB", 10_000_000),
+        ("10-100 MB", 100_000_000),
+        ("> 100 MB", u64::MAX),
+    ];
+    let mut counts = [0usize; BUCKETS.len()];
+    let files: Vec<u64> = entries.iter().filter(|e| !e.is_dir).map(|e| e.size).collect();
+    for &size in &files {
+        let bucket = BUCKETS.iter().position(|&(_, limit)| size < limit).unwrap_or(BUCKETS.len() - 1);
+        counts[bucket] += 1;
+    }
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    println!("Entry size histogram ({} files):", files.len());
+    for ((label, _), count) in BUCKETS.iter().zip(counts) {
+        // une barre non vide dès qu'une tranche contient au moins un fichier
+        let bar = "#".repeat((count * 40).div_ceil(max));
+        println!("  {:<12} {:>8}  {}", label, count, bar);
+    }
+}
+
+/// Extrait `opts.input()` dans `opts.output()` selon le format donné
+fn decompress_as(opts: &DecompressOptions, format: Format, ex: &mut Extractor) -> io::Result<()> {
+    if opts.writes_stdout() && !format.is_single_stream() && opts.extract_entry.is_none() {
+        return Err(stdout_unsupported(format));
+    }
+    let codec = format.codec();
+    // Brotli, LZ4 et LZMA emballent souvent un tar : à trancher avant de refuser --extract-entry
+    if let Some(tar) = codec.wrapped_tar(opts.input())? {
+        return decompress_as(opts, tar, ex);
+    }
+    if opts.extract_entry.is_some() && !codec.extracts_single_entry() {
+        return Err(extract_entry_unsupported(format));
+    }
+    codec.decompress(opts, ex)
+}
+
+/// `-o -` demandé pour une archive à plusieurs entrées
+fn stdout_unsupported(format: Format) -> io::Error {
+    unsupported(format!(
+        "Cannot extract {} archives to stdout; -o - only works for single-file streams (gz, bz2, xz, zst, lzma, br, lz4, lzfse)",
+        format.name()
+    ))
+}
+
+fn extract_entry_unsupported(format: Format) -> io::Error {
+    unsupported(format!("--extract-entry does not support {} input; it works with zip, 7z and tar archives", format.name()))
+}
+
+/// Flux tar d'une archive sharky : zstd → xz → tar, ou zstd → tar quand
+/// `--smart-pipeline` a abandonné la couche xz
+fn sharky_tar_reader<'r, R: BufRead + 'r>(reader: R) -> io::Result<Box<dyn Read + 'r>> {
+    let mut decoded = BufReader::new(zstd_decoder(reader)?);
+    if decoded.fill_buf()?.starts_with(&XZ_MAGIC) {
+        Ok(Box::new(XzDecoder::new(decoded)))
+    } else {
+        Ok(Box::new(decoded))
+    }
+}
+
+/// `--batch-extract` : chaque archive reconnue de `--input` est extraite dans
+/// `--output/<nom>/`. Les fichiers non reconnus sont ignorés (erreur sous `--strict`),
+/// les formats non pris en charge aussi avec `--continue-on-unsupported`.
+pub fn batch_extract(opts: &DecompressOptions, skipped: &mut Vec<(PathBuf, String)>) -> Result<(), SharkyError> {
+    if !opts.verbose {
+        status!(opts, "© 2025, Matheo Simard");
+    }
+    status!(opts, "Batch extracting {:?} → {:?}", opts.input(), opts.output());
+    let mut files: Vec<PathBuf> = fs::read_dir(opts.input())?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+
+    let multi = if opts.hides_progress() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let overall = multi.add(ProgressBar::new(files.len() as u64));
+    overall.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.green/white} {pos}/{len} archives {wide_msg}")
+            .map_err(|e| io::Error::other(e.to_string()))?
+            .progress_chars("#>-"),
+    );
+    let mut ex = Extractor::new(opts)?;
+    ex.multi = Some(multi.clone());
+
+    let mut extracted = 0;
+    for path in files {
+        overall.set_message(path.display().to_string());
+        let Some(format) = sniff_format(&path)? else {
+            if opts.strict {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: not a recognized archive", path.display()),
+                ).into());
+            }
+            multi.suspend(|| eprintln!("Skipping {}: not a recognized archive", path.display()));
+            overall.inc(1);
+            continue;
+        };
+
+        let target = opts.output().join(archive_stem(&path));
+        ex.create_parent(&target.join("-"))?;
+        let mut sub = opts.clone();
+        sub.input = path.clone();
+        sub.output = target;
+        if opts.verbose {
+            multi.suspend(|| report_format(opts, format, "magic bytes", true));
+        }
+        match decompress_as(&sub, format, &mut ex) {
+            Ok(()) => extracted += 1,
+            Err(e) => match SharkyError::from(e) {
+                e if opts.continue_on_unsupported && e.is_unsupported() => skipped.push((path, e.to_string())),
+                e => return Err(SharkyError::InArchive { path, source: Box::new(e) }),
+            },
+        }
+        overall.inc(1);
+    }
+    overall.finish_and_clear();
+    ex.finish()?;
+    summary!(opts, "Extracted {} archive(s)", extracted);
+    Ok(())
+}
+
+/// Nom du sous-répertoire d'une archive : son nom sans extension (ni `.tar` intermédiaire)
+fn archive_stem(path: &Path) -> PathBuf {
+    let stem = Path::new(path.file_stem().unwrap_or(path.as_os_str()));
+    match stem.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("tar") => PathBuf::from(stem.file_stem().unwrap()),
+        _ => stem.to_path_buf(),
+    }
+}
+
+/// Lit jusqu'à `buf.len()` octets (moins si la fin du flux est atteinte)
+fn read_head<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Premiers octets décompressés d'un flux, pour reconnaître un tar ou une couche xz interne
+fn decoded_head<R: Read>(mut decoder: R) -> Vec<u8> {
+    let mut head = vec![0u8; 512];
+    let n = read_head(&mut decoder, &mut head).unwrap_or(0);
+    head.truncate(n);
+    head
+}
+
+/// Vérifie la signature ISO 9660 du premier descripteur de volume (secteur 16)
+fn has_iso_signature(file: &mut File) -> io::Result<bool> {
+    let mut buffer = [0u8; 6];
+    file.seek(SeekFrom::Start(32768))?;
+    let n = read_head(file, &mut buffer)?;
+    Ok(n == 6 && &buffer[1..6] == b"CD001")
+}
+
+/// Format reconnu d'après le contenu : signature, descripteur ISO, puis contenu
+/// des flux compressés (tar interne, ou couche xz du format sharky).
+fn sniff_format(path: &Path) -> io::Result<Option<Format>> {
+    let mut file = File::open(path)?;
+    let mut head = vec![0u8; 512];
+    let n = read_head(&mut file, &mut head)?;
+    head.truncate(n);
+
+    let mut format = match Format::from_magic(&head) {
+        Some(format) => format,
+        None if has_iso_signature(&mut file)? => return Ok(Some(Format::Iso)),
+        None => return Ok(None),
+    };
+
+    // Contenu des flux compressés : tar interne ou format sharky (zstd → xz)
+    let codec = format.codec();
+    let Some(tar) = codec.tar_format() else { return Ok(Some(format)) };
+    let mut inner = Vec::new();
+    codec.decode(Box::new(BufReader::new(File::open(path)?)), &mut |decoded| {
+        inner = decoded_head(decoded);
+        Ok(())
+    })?;
+    if is_tar_header(&inner) {
+        format = tar;
+    } else if format == Format::Zst && inner.starts_with(&XZ_MAGIC) {
+        format = Format::Sharky;
+    }
+    Ok(Some(format))
+}
+
+/// `--probe` : identifie le format (signature, puis extension) et affiche les
+/// métadonnées lisibles sans extraction. Renvoie `false` si le format est inconnu.
+pub fn probe_file(path: &Path) -> Result<bool, SharkyError> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut head = vec![0u8; 512];
+    let n = read_head(&mut file, &mut head)?;
+    head.truncate(n);
+
+    println!("File: {}", path.display());
+    println!("Size: {} bytes", size);
+
+    let (format, detection) = match sniff_format(path)? {
+        Some(format) => (format, "magic bytes"),
+        None => match Format::from_path(path) {
+            Format::Sharky => {
+                println!("Format: unknown");
+                return Ok(false);
+            }
+            format => (format, "extension"),
+        },
+    };
+
+    println!("Format: {} (detected by {})", format.name(), detection);
+    println!("Codec chain: {}", format.decoder_chain());
+
+    match format {
+        Format::Gz | Format::TarGz => {
+            let mut decoder = GzDecoder::new(File::open(path)?);
+            decoded_head(&mut decoder);
+            if let Some(header) = decoder.header() {
+                if let Some(name) = header.filename() {
+                    println!("Original name: {}", String::from_utf8_lossy(name));
+                }
+                if header.mtime() != 0 {
+                    println!("Original mtime: {} (unix time)", header.mtime());
+                }
+            }
+        }
+        Format::Zst | Format::TarZst | Format::Sharky => {
+            match zstd::zstd_safe::get_frame_content_size(&head) {
+                Ok(Some(content_size)) => println!("Frame content size: {} bytes", content_size),
+                _ => println!("Frame content size: unknown"),
+            }
+        }
+        Format::Tar => {
+            let mut archive = Archive::new(File::open(path)?);
+            let count = archive.entries()?.filter(|e| e.is_ok()).count();
+            println!("Entries: {}", count);
+        }
+        Format::Zip => {
+            let archive = ZipArchive::new(File::open(path)?)?;
+            println!("Entries: {}", archive.len());
+        }
+        Format::SevenZ => {
+            file.seek(SeekFrom::Start(0))?;
+            let reader = SevenZReader::new(file, size, sevenz_rust::Password::empty())
+                .map_err(|e| sevenz_error(path, "Failed to open 7Z archive", e))?;
+            println!("Entries: {}", reader.archive().files.len());
+        }
+        Format::Iso => {
+            let mut pvd = [0u8; 2048];
+            file.seek(SeekFrom::Start(32768))?;
+            file.read_exact(&mut pvd)?;
+            println!("{}", IsoVolumeInfo::from_pvd(&pvd));
+        }
+        _ => {}
+    }
+    Ok(true)
+}
+
+/// Sous `--verbose`, indique le format retenu, comment il a été détecté et les décodeurs utilisés
+fn report_format(opts: &DecompressOptions, format: Format, detection: &str, probes_contents: bool) {
+    if !opts.verbose {
+        return;
+    }
+    status!(opts, "Format: {} (detected by {})", format.name(), detection);
+    if probes_contents && matches!(format, Format::Gz | Format::Bz2 | Format::Xz | Format::Zst | Format::Brotli) {
+        status!(opts, "Decoder chain: {} → (tar/xz layers probed after decoding)", format.decoder_chain());
+    } else {
+        status!(opts, "Decoder chain: {}", format.decoder_chain());
+    }
+}
+
+fn parse_format_hint(hint: &str) -> io::Result<Format> {
+    Format::from_name(hint).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unknown input format '{}'", hint),
+    ))
+}
+
+/// Décompression depuis stdin : le format vient de `--input-format-hint`
+/// ou, à défaut, de la signature lue dans le tampon sans la consommer.
+fn decompress_stdin(opts: &DecompressOptions, ex: &mut Extractor) -> io::Result<Format> {
+    let mut reader = BufReader::with_capacity(opts.buffer_size, io::stdin().lock());
+    let (format, sniffed) = match &opts.input_format_hint {
+        Some(hint) => (parse_format_hint(hint)?, false),
+        None => {
+            let format = Format::from_magic(reader.fill_buf()?).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Could not detect the format of stdin; pass --input-format-hint",
+            ))?;
+            (format, true)
+        }
+    };
+    let detection = if sniffed { "magic bytes" } else { "--input-format-hint" };
+    report_format(opts, format, detection, sniffed);
+    if format.needs_seek() {
+        return Err(unsupported(format!("{} archives cannot be read from stdin (they need a seekable file); save the stream to a file first", format.name())));
+    }
+
+    if opts.extract_entry.is_some() {
+        if !format.extracts_single_entry() {
+            return Err(extract_entry_unsupported(format));
+        }
+    } else if opts.writes_stdout() && (!format.is_single_stream() || reader.fill_buf()?.starts_with(&LZ4_FRAME_MAGIC)) {
+        return Err(stdout_unsupported(format));
+    }
+    format.codec().decompress_reader(Box::new(reader), sniffed, opts, ex)?;
+    Ok(format)
+}
+
+/// Écrit un flux décompressé sans nom de fichier d'origine. Si le format a été
+/// détecté par signature et que le contenu est un tar, il est extrait comme tel (sauf vers stdout).
+fn decompress_stream<R: Read>(
+    decoder: R,
+    output: &Path,
+    probe_tar: bool,
+    label: &str,
+    ex: &mut Extractor,
+) -> io::Result<()> {
+    let mut decoded = BufReader::new(decoder);
+    // Avec `-o -`, le contenu part tel quel sur stdout, même s'il s'agit d'un tar
+    if probe_tar && !ex.opts.writes_stdout() && is_tar_header(decoded.fill_buf()?) {
+        return decompress_tar_plain(decoded, output, ex);
+    }
+    let output_file_path = output.join("decompressed");
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    io::copy(&mut decoded, &mut output_file)?;
+    ex.stream_done(label, &output_file_path);
+    Ok(())
+}
+
+/// Progression d'extraction préfixée du format. Avec un total d'entrées connu (7z, tar non
+/// compressé), barre avec temps restant estimé ; sinon un spinner qui compte les entrées.
+fn extract_progress(ex: &Extractor, label: &'static str, total: Option<u64>) -> io::Result<ProgressBar> {
+    let (pb, template) = match total {
+        Some(total) => (
+            ProgressBar::new(total),
+            "{spinner:.green} {prefix} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} (ETA {eta}) {msg}",
+        ),
+        None => (ProgressBar::new_spinner(), "{spinner:.green} {prefix} [{elapsed_precise}] {pos} entries {msg}"),
+    };
+    let pb = ex.progress(pb.with_prefix(label));
+    pb.set_style(
+        ProgressStyle::with_template(template)
+            .map_err(|e| io::Error::other(e.to_string()))?
+            .progress_chars("#>-"),
+    );
+    pb.enable_steady_tick(Duration::from_millis(100));
+    Ok(pb)
+}
+
+/// Message commun aux extracteurs lorsqu'une archive ne contient aucune entrée
+fn report_empty_archive(ex: &Extractor, pb: &ProgressBar) {
+    pb.finish_and_clear();
+    status!(ex.opts, "Archive is empty, nothing to extract.");
+}
+
+/// Nom à plat d'un chemin relatif : composants joints par `delim`
+fn flatten_name(rel: &Path, delim: &str) -> String {
+    let parts: Vec<_> = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    parts.join(delim)
+}
+
+/// Chemin de sortie d'une entrée, refusé s'il est absolu ou sort de `base` (Zip Slip)
+fn sanitize_entry_path(base: &Path, entry: &Path) -> io::Result<PathBuf> {
+    let rejected = || io::Error::from(SharkyError::PathTraversal { path: entry.to_path_buf() });
+    let mut relative = PathBuf::new();
+    for component in entry.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !relative.pop() {
+                    return Err(rejected());
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(rejected()),
+        }
+    }
+    Ok(base.join(relative))
+}
+
+/// Refuse un chemin qui passe par un lien symbolique déjà présent sous `base` : un lien extrait
+/// plus tôt (`d -> ..`) ferait sortir de `base` un chemin pourtant accepté par
+/// `sanitize_entry_path`. Le dernier composant n'est examiné que si `with_last` (répertoire,
+/// source d'un lien dur) ; un fichier remplace le lien au lieu de le suivre (`make_room`).
+fn check_symlink_parents(base: &Path, path: &Path, entry: &Path, with_last: bool) -> io::Result<()> {
+    let Ok(relative) = path.strip_prefix(base) else { return Ok(()) };
+    let mut current = base.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() && !with_last {
+            break;
+        }
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(SharkyError::PathTraversal { path: entry.to_path_buf() }.into());
+            }
+            Ok(_) => {}
+            // Rien n'existe encore plus bas
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Nom d'entrée zip en chemin : les `\` des archives Windows deviennent des `/` et un
+/// préfixe de lecteur (`C:\`) est retiré. `sanitize_entry_path` vérifie ensuite le résultat.
+fn zip_entry_path(name: &str) -> PathBuf {
+    let name = name.replace('\\', "/");
+    let bytes = name.as_bytes();
+    let relative = if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        name[2..].trim_start_matches('/')
+    } else {
+        &name
+    };
+    PathBuf::from(relative)
+}
+
+fn decompress_zip(input: &Path, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    let f = File::open(input)?;
+    let mut archive = ZipArchive::new(f)?;
+    if let Some(name) = ex.opts.extract_entry.as_deref() {
+        let wanted = entry_key(&zip_entry_path(name).to_string_lossy());
+        let i = (0..archive.len())
+            .find(|&i| archive.name_for_index(i).is_some_and(|n| entry_key(&zip_entry_path(n).to_string_lossy()) == wanted))
+            .ok_or_else(|| entry_not_found(name))?;
+        let mut file = open_zip_entry(&mut archive, i, ex.opts.password.as_deref())?;
+        if file.is_dir() {
+            return Err(entry_not_a_file(name));
+        }
+        let crc = zip_entry_crc(&file);
+        let entry_name = zip_entry_path(file.name());
+        return ex.extract_named(&mut file, output, &entry_name.to_string_lossy(), crc);
+    }
+    let progress = ZipProgress::new(&mut archive, ex)?;
+    let pb = &progress.pb;
+    if archive.is_empty() {
+        report_empty_archive(ex, pb);
+        return Ok(());
+    }
+    let threads = match ex.opts.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n as usize,
+    };
+    let extracted_count = if threads > 1 {
+        extract_zip_parallel(input, output, &mut archive, ex, &progress, threads)?
+    } else {
+        let mut extracted_count = 0;
+        for i in 0..archive.len() {
+            let mut file = open_zip_entry(&mut archive, i, ex.opts.password.as_deref())?;
+            let size = file.size();
+            let Some(outpath) = ex.entry_path(output, &zip_entry_path(file.name()), file.is_dir())? else {
+                extracted_count += 1;
+                progress.entry_done(size, 0);
+                continue;
+            };
+            let mut read = 0;
+            let result = if file.is_dir() {
+                ex.create_dir(&outpath)
+            } else {
+                let crc = zip_entry_crc(&file);
+                let mut wrapped = pb.wrap_read(&mut file);
+                let mut counted = CountingReader { inner: &mut wrapped, count: 0 };
+                let result = ex.create_parent(&outpath).and_then(|()| ex.write_file_crc(&mut counted, &outpath, crc));
+                read = counted.count;
+                result
+            };
+            ex.entry_result(&outpath, result)?;
+            extracted_count += 1;
+            progress.entry_done(size, read);
+        }
+        extracted_count
+    };
+    verify_entry_count(Format::Zip, archive.len(), extracted_count)?;
+    pb.finish_with_message("Zip decompression done.");
+    Ok(())
+}
+
+/// Avancement de l'extraction zip : la barre suit les octets décompressés, pour qu'une seule
+/// grosse entrée la fasse avancer, et le message compte les entrées traitées
+struct ZipProgress {
+    pb: ProgressBar,
+    entries: usize,
+    done: AtomicUsize,
+}
+
+impl ZipProgress {
+    /// Barre dimensionnée sur les tailles décompressées du répertoire central
+    fn new(archive: &mut ZipArchive<File>, ex: &Extractor) -> io::Result<Self> {
+        let mut total = 0u64;
+        for i in 0..archive.len() {
+            total += archive.by_index_raw(i)?.size();
+        }
+        let pb = ex.progress(ProgressBar::new(total.max(1)));
+        pb.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} zip [{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta}) {msg}"
+            )
+            .map_err(|e| io::Error::other(e.to_string()))?
+            .progress_chars("#>-"),
+        );
+        let progress = ZipProgress { pb, entries: archive.len(), done: AtomicUsize::new(0) };
+        progress.pb.set_message(format!("0/{} entries", progress.entries));
+        Ok(progress)
+    }
+
+    /// Entrée terminée : rattrape les octets non lus (entrée ignorée, en échec ou vide de
+    /// données comme en `--dry-run`) et met à jour le compteur d'entrées
+    fn entry_done(&self, size: u64, read: u64) {
+        self.pb.inc(size.saturating_sub(read));
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.pb.set_message(format!("{}/{} entries", done, self.entries));
+    }
+}
+
+/// Ouvre l'entrée `i`, déchiffrée si un mot de passe est fourni
+fn open_zip_entry<'z>(archive: &'z mut ZipArchive<File>, i: usize, password: Option<&str>) -> io::Result<zip::read::ZipFile<'z, File>> {
+    let name = archive.name_for_index(i).unwrap_or_default().to_string();
+    let opened = match password {
+        Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+        None => archive.by_index(i),
+    };
+    opened.map_err(|e| zip_password_error(e, &name))
+}
+
+/// CRC-32 du répertoire central ; absent (0) pour les entrées AES AE-2
+fn zip_entry_crc(file: &zip::read::ZipFile<'_, File>) -> Option<u32> {
+    (file.crc32() != 0 || file.size() == 0).then(|| file.crc32())
+}
+
+/// Extraction zip répartie sur `threads` threads. `ZipArchive` n'étant pas partageable, chaque
+/// thread rouvre l'archive ; les chemins sont vérifiés et les répertoires créés avant de lancer
+/// les écritures, le journal est tenu une fois les threads terminés.
+fn extract_zip_parallel(
+    input: &Path,
+    output: &Path,
+    archive: &mut ZipArchive<File>,
+    ex: &mut Extractor,
+    progress: &ZipProgress,
+    threads: usize,
+) -> io::Result<usize> {
+    let mut files = Vec::new();
+    let mut extracted_count = 0;
+    for i in 0..archive.len() {
+        let (name, is_dir, size) = {
+            let file = archive.by_index_raw(i)?;
+            (file.name().to_string(), file.is_dir(), file.size())
+        };
+        match ex.entry_path(output, &zip_entry_path(&name), is_dir)? {
+            Some(outpath) if !is_dir => {
+                ex.create_parent(&outpath)?;
+                files.push((i, outpath, size));
+            }
+            outpath => {
+                if let Some(outpath) = outpath {
+                    ex.create_dir(&outpath)?;
+                }
+                extracted_count += 1;
+                progress.entry_done(size, 0);
+            }
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let shared: &Extractor = ex;
+    let workers = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.min(files.len())).map(|_| {
+            let tx = tx.clone();
+            let (files, next, failed) = (&files, &next, &failed);
+            scope.spawn(move || -> io::Result<()> {
+                let mut archive = ZipArchive::new(File::open(input)?)?;
+                while !failed.load(Ordering::Relaxed) {
+                    let Some((i, outpath, size)) = files.get(next.fetch_add(1, Ordering::Relaxed)) else { break };
+                    let mut read = 0;
+                    let result = open_zip_entry(&mut archive, *i, shared.opts.password.as_deref()).and_then(|mut file| {
+                        let crc = zip_entry_crc(&file);
+                        let (copied, result) = shared.copy_entry(&mut progress.pb.wrap_read(&mut file), outpath, crc);
+                        read = copied;
+                        Ok((copied, result?))
+                    });
+                    if result.is_err() && !shared.opts.keep_going {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    progress.entry_done(*size, read);
+                    // Le récepteur ne disparaît qu'avec le scope
+                    let _ = tx.send((outpath, result));
+                }
+                Ok(())
+            })
+        }).collect();
+        drop(tx);
+        let done: Vec<_> = rx.iter().collect();
+        handles.into_iter()
+            .try_for_each(|h| h.join().unwrap_or_else(|_| Err(io::Error::other("zip extraction thread panicked"))))
+            .map(|_| done)
+    })?;
+
+    let mut first_error = None;
+    for (outpath, result) in workers {
+        let written = fs::metadata(outpath).map(|m| m.len()).unwrap_or(0);
+        let (read, converted) = result.as_ref().map_or((0, false), |r| *r);
+        let result = result.map(|_| ());
+        ex.log.record("extract", outpath, read, written, &result)?;
+        match result {
+            Ok(()) => {
+                ex.converted += usize::from(converted);
+                extracted_count += 1;
+            }
+            Err(e) => match ex.entry_result(outpath, Err(e)) {
+                Ok(()) => extracted_count += 1,
+                Err(e) => { first_error.get_or_insert(e); }
+            },
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(extracted_count),
+    }
+}
+
+/// Erreurs de chiffrement zip rendues explicites (mot de passe absent ou faux)
+fn zip_password_error(e: zip::result::ZipError, name: &str) -> io::Error {
+    match e {
+        zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED) => {
+            SharkyError::PasswordRequired(name.to_string()).into()
+        }
+        zip::result::ZipError::InvalidPassword => SharkyError::WrongPassword(name.to_string()).into(),
+        other => other.into(),
+    }
+}
+
+/// Nommage des volumes d'un jeu RAR, déduit du nom de l'un d'eux : `nom.partN.rar`
+/// (RAR 3 et suivants) ou `nom.rar`, `nom.r00`, `nom.r01`… (ancienne numérotation)
+struct RarVolumes {
+    dir: PathBuf,
+    base: String,
+    /// Largeur du numéro `partN`, `None` pour l'ancienne numérotation
+    part_width: Option<usize>,
+    /// Rang du volume nommé (0 = premier)
+    index: usize,
+}
+
+impl RarVolumes {
+    fn parse(path: &Path) -> Option<Self> {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let (stem, ext) = path.file_name()?.to_str()?.rsplit_once('.')?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if ext.eq_ignore_ascii_case("rar") {
+            if let Some((base, part)) = stem.rsplit_once('.')
+                && part.len() > 4
+                && part[..4].eq_ignore_ascii_case("part")
+                && digits(&part[4..])
+            {
+                let index = part[4..].parse::<usize>().ok()?.checked_sub(1)?;
+                return Some(RarVolumes { dir, base: base.to_string(), part_width: Some(part.len() - 4), index });
+            }
+            return Some(RarVolumes { dir, base: stem.to_string(), part_width: None, index: 0 });
+        }
+        if ext.len() >= 3 && ext[..1].eq_ignore_ascii_case("r") && digits(&ext[1..]) {
+            let index = ext[1..].parse::<usize>().ok()? + 1;
+            return Some(RarVolumes { dir, base: stem.to_string(), part_width: None, index });
+        }
+        None
+    }
+
+    /// Chemin du volume de rang `n`
+    fn path(&self, n: usize) -> PathBuf {
+        let name = match self.part_width {
+            Some(width) => format!("{}.part{:0width$}.rar", self.base, n + 1),
+            None if n == 0 => format!("{}.rar", self.base),
+            None => format!("{}.r{:02}", self.base, n - 1),
+        };
+        self.dir.join(name)
+    }
+
+    /// Volumes présents à la suite du premier
+    fn present(&self) -> usize {
+        (0..).take_while(|&n| self.path(n).exists()).count()
+    }
+
+    /// Premier volume absent : celui que la suite du jeu réclame
+    fn first_missing(&self) -> PathBuf {
+        self.path(self.present())
+    }
+}
+
+fn decompress_rar(input: &Path, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    status!(ex.opts, "Attempting RAR decompression (requires external unrar library)...");
+
+    // Un volume suivant commence au milieu d'un fichier : l'extraction repart du premier
+    let volumes = RarVolumes::parse(input);
+    let first = match &volumes {
+        Some(volumes) if volumes.index > 0 => {
+            let first = volumes.path(0);
+            if !first.exists() {
+                return Err(SharkyError::MissingVolume { path: first }.into());
+            }
+            status!(ex.opts, "{:?} is volume {} of a multi-volume set; extracting from {:?}", input, volumes.index + 1, first);
+            first
+        }
+        _ => input.to_path_buf(),
+    };
+    let mut archive = UnrarArchive::new(&first)
+        .open_for_processing()
+        .map_err(|e| rar_error("Failed to open RAR archive", e))?;
+    let multi_volume = archive.volume_info() != unrar::VolumeInfo::None;
+    if multi_volume && let Some(volumes) = &volumes {
+        status!(ex.opts, "Multi-volume RAR set: {} volume(s) present", volumes.present());
+    }
+    // unrar signale un volume suivant introuvable par EOPEN, sans le nommer
+    let volume_error = |context: String, e: unrar::error::UnrarError| match &volumes {
+        Some(volumes) if multi_volume && e.code == unrar::error::Code::EOpen => {
+            SharkyError::MissingVolume { path: volumes.first_missing() }.into()
+        }
+        _ => rar_error(context, e),
+    };
+
+    let pb = extract_progress(ex, "rar", None)?;
+
+    let mut extracted_count = 0;
+
+    loop {
+        let current_filename_display;
+
+        let next_archive_state = {
+            match archive.read_header() {
+                Ok(Some(open_archive_with_entry)) => {
+                    let entry = open_archive_with_entry.entry();
+                    let entry_path = ex.entry_path(output, &entry.filename, entry.is_directory())?;
+                    current_filename_display = entry.filename.display().to_string();
+
+                    let Some(entry_path) = entry_path else {
+                        archive = open_archive_with_entry.skip()
+                            .map_err(|e| volume_error("Failed to skip RAR entry".to_string(), e))?;
+                        extracted_count += 1;
+                        pb.inc(1);
+                        continue;
+                    };
+                    if entry.is_directory() {
+                        let result = ex.create_dir(&entry_path);
+                        ex.entry_result(&entry_path, result)?;
+                        open_archive_with_entry.skip()
+                            .map_err(|e| volume_error("Failed to skip RAR directory entry".to_string(), e))?
+                    } else if ex.opts.test {
+                        open_archive_with_entry.test()
+                            .map_err(|e| volume_error(format!("RAR test failed for '{}'", current_filename_display), e))?
+                    } else if ex.opts.dry_run {
+                        ex.announce(format!("Would write {}", entry_path.display()));
+                        open_archive_with_entry.skip()
+                            .map_err(|e| volume_error("Failed to skip RAR entry".to_string(), e))?
+                    } else if let Err(e) = ex.create_parent(&entry_path) {
+                        // unrar consomme l'archive en cas d'échec de `extract_to` : seule la
+                        // création du répertoire parent peut être rattrapée par --keep-going
+                        ex.entry_result(&entry_path, Err(e))?;
+                        open_archive_with_entry.skip()
+                            .map_err(|e| volume_error("Failed to skip RAR entry".to_string(), e))?
+                    } else {
+                        open_archive_with_entry.extract_to(&entry_path)
+                            .map_err(|e| volume_error(format!("Failed to extract RAR file '{}'", current_filename_display), e))?
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => return Err(volume_error("Error reading RAR header".to_string(), e)),
+            }
+        };
+
+        archive = next_archive_state;
+        extracted_count += 1;
+        pb.set_message(format!("Extracting: {}", current_filename_display));
+        pb.inc(1);
+    }
+
+    if extracted_count == 0 {
+        report_empty_archive(ex, &pb);
+        return Ok(());
+    }
+    pb.finish_with_message(format!("RAR decompression done. Extracted {} files/directories.", extracted_count));
+    Ok(())
+}
+
+/// Champs descriptifs du Primary Volume Descriptor (chaînes complétées par des espaces)
+struct IsoVolumeInfo {
+    volume_id: String,
+    publisher: String,
+    application: String,
+    created: Option<String>,
+}
+
+impl IsoVolumeInfo {
+    fn from_pvd(pvd: &[u8; 2048]) -> Self {
+        let text = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&pvd[range]).trim_end_matches([' ', '\0']).to_string()
+        };
+        // Date au format "YYYYMMDDHHMMSScc" + décalage horaire ; tout à '0' si absente
+        let date = &pvd[813..829];
+        let created = (date.iter().all(u8::is_ascii_digit) && date.iter().any(|&b| b != b'0')).then(|| {
+            let d = String::from_utf8_lossy(date);
+            format!("{}-{}-{} {}:{}:{}", &d[0..4], &d[4..6], &d[6..8], &d[8..10], &d[10..12], &d[12..14])
+        });
+        IsoVolumeInfo {
+            volume_id: text(40..72),
+            publisher: text(318..446),
+            application: text(574..702),
+            created,
+        }
+    }
+
+}
+
+impl std::fmt::Display for IsoVolumeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Volume label: {}", self.volume_id)?;
+        if !self.publisher.is_empty() {
+            write!(f, "\nPublisher: {}", self.publisher)?;
+        }
+        if !self.application.is_empty() {
+            write!(f, "\nApplication: {}", self.application)?;
+        }
+        if let Some(created) = &self.created {
+            write!(f, "\nCreated: {}", created)?;
+        }
+        Ok(())
+    }
+}
+
+fn decompress_iso(input: &Path, output: &Path, buffer_size: usize, ex: &mut Extractor) -> io::Result<()> {
+    status!(ex.opts, "Attempting ISO decompression...");
+    
+    let mut file = File::open(input)?;
+    
+    // Vérifier la signature ISO 9660
+    let mut buffer = [0u8; 8];
+    file.seek(SeekFrom::Start(32768))?; // Volume descriptor commence à 32KB
+    file.read_exact(&mut buffer)?;
+    
+    if &buffer[1..6] != b"CD001" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid ISO 9660 signature"
+        ));
+    }
+    
+    let pb = extract_progress(ex, "iso", None)?;
+    pb.set_message("Reading ISO structure...");
+    
+    // Lire le Primary Volume Descriptor
+    let mut pvd = [0u8; 2048];
+    file.seek(SeekFrom::Start(32768))?;
+    file.read_exact(&mut pvd)?;
+    
+    // Extraire les informations du répertoire racine
+    let root = iso_root(&mut file, &pvd)?;
+    if ex.opts.verbose {
+        pb.suspend(|| status!(ex.opts, "{}", IsoVolumeInfo::from_pvd(&pvd)));
+        if root.joliet {
+            pb.suspend(|| status!(ex.opts, "Using Joliet names"));
+        }
+    }
+    
+    pb.set_message("Extracting files...");
+    
+    // Répertoires créés pendant le parcours de l'arborescence, fichiers extraits
+    // ensuite dans l'ordre de leurs secteurs pour lire l'image d'un bout à l'autre
+    let mut directories = read_iso_directories(&mut file, &root, buffer_size)?;
+    let mut extracted_count = 0;
+    let mut files = Vec::new();
+    collect_iso_directory(
+        &mut file,
+        &mut directories,
+        &mut HashSet::new(),
+        root.location,
+        root.size,
+        root.joliet,
+        output,
+        "",
+        &pb,
+        &mut extracted_count,
+        &mut files,
+        ex,
+    )?;
+    files.sort_by_key(|f| f.location);
+    
+    let image_len = file.metadata()?.len();
+    let mut reader = IsoReader::new(&mut file, buffer_size)?;
+    for IsoFile { location, size, output_path, mode } in files {
+        ex.create_parent(&output_path)?;
+        pb.set_message(format!("Extracting: {}", output_path.display()));
+        let result = check_iso_extent(location, size, image_len)
+            .and_then(|()| reader.seek_to(location as u64 * 2048))
+            .and_then(|()| ex.write_file(&mut (&mut reader).take(size as u64), &output_path))
+            .and_then(|()| ex.set_metadata(&output_path, mode, None));
+        if let Err(e) = result {
+            ex.record_failure(&output_path, e);
+            continue;
+        }
+        extracted_count += 1;
+        pb.inc(1);
+    }
+    
+    if extracted_count == 0 {
+        report_empty_archive(ex, &pb);
+        return Ok(());
+    }
+    pb.finish_with_message(format!("ISO decompression done. Extracted {} files/directories.", extracted_count));
+    Ok(())
+}
+
+/// Répertoire racine retenu pour parcourir l'image
+struct IsoRoot {
+    location: u32,
+    size: u32,
+    /// Arborescence du Supplementary Volume Descriptor Joliet (noms UTF-16)
+    joliet: bool,
+    /// Table des chemins (type L, petit-boutiste) du même descripteur : secteur et taille
+    path_table_location: u32,
+    path_table_size: u32,
+}
+
+/// Racine à parcourir : celle du descripteur Joliet s'il y en a un, sauf si la racine
+/// primaire porte déjà Rock Ridge (noms longs et permissions POSIX)
+fn iso_root(file: &mut File, pvd: &[u8; 2048]) -> io::Result<IsoRoot> {
+    let root_of = |vd: &[u8; 2048], joliet| IsoRoot {
+        location: u32::from_le_bytes(vd[158..162].try_into().unwrap()),
+        size: u32::from_le_bytes(vd[166..170].try_into().unwrap()),
+        joliet,
+        path_table_location: u32::from_le_bytes(vd[140..144].try_into().unwrap()),
+        path_table_size: u32::from_le_bytes(vd[132..136].try_into().unwrap()),
+    };
+    let primary = root_of(pvd, false);
+
+    let mut joliet = None;
+    let mut vd = [0u8; 2048];
+    for sector in 17..64u64 {
+        file.seek(SeekFrom::Start(sector * 2048))?;
+        if file.read_exact(&mut vd).is_err() || &vd[1..6] != b"CD001" || vd[0] == 255 {
+            break;
+        }
+        // Supplementary Volume Descriptor avec une séquence d'échappement UCS-2 (niveaux 1 à 3)
+        let escapes = &vd[88..91];
+        if vd[0] == 2 && matches!(escapes, b"%/@" | b"%/C" | b"%/E") {
+            joliet = Some(root_of(&vd, true));
+            break;
+        }
+    }
+    match joliet {
+        Some(root) if !iso_has_rock_ridge(file, &primary)? => Ok(root),
+        _ => Ok(primary),
+    }
+}
+
+/// Rock Ridge s'annonce par une entrée SUSP `SP` dans l'enregistrement `.` de la racine
+fn iso_has_rock_ridge(file: &mut File, root: &IsoRoot) -> io::Result<bool> {
+    let mut record = [0u8; 64];
+    file.seek(SeekFrom::Start(root.location as u64 * 2048))?;
+    file.read_exact(&mut record)?;
+    Ok(record[32] == 1 && &record[34..36] == b"SP" && record[38..40] == [0xBE, 0xEF])
+}
+
+/// Enregistrement d'un répertoire ISO 9660, hors `.` et `..`
+struct IsoRecord {
+    name: String,
+    version: Option<u32>,
+    location: u32,
+    size: u32,
+    is_dir: bool,
+    /// Date d'enregistrement (secondes Unix)
+    mtime: Option<i64>,
+    /// Mode POSIX de l'entrée `PX` Rock Ridge
+    mode: Option<u32>,
+}
+
+/// Taille maximale d'un répertoire ISO. Les répertoires réels font quelques Kio : au-delà,
+/// l'enregistrement est corrompu ou forgé.
+const MAX_ISO_DIR_SIZE: u32 = 64 * 1024 * 1024;
+
+/// Lit les enregistrements du répertoire situé au secteur `location`, secteur par secteur :
+/// un enregistrement ne chevauche jamais deux secteurs, la fin de chacun est bourrée de zéros.
+/// La taille annoncée est vérifiée contre celle de l'image avant toute lecture.
+fn read_iso_records(file: &mut File, location: u32, size: u32, joliet: bool) -> io::Result<Vec<IsoRecord>> {
+    let start = location as u64 * 2048;
+    let image_len = file.metadata()?.len();
+    if size > MAX_ISO_DIR_SIZE || start + size as u64 > image_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Corrupt ISO image: directory at sector {} claims {} bytes (image is {} bytes)",
+                location, size, image_len
+            ),
+        ));
+    }
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut records = Vec::new();
+    let mut sector = [0u8; 2048];
+    let mut remaining = size as usize;
+    while remaining > 0 {
+        let len = remaining.min(sector.len());
+        file.read_exact(&mut sector[..len])?;
+        remaining -= len;
+        parse_iso_sector(&sector[..len], joliet, &mut records)?;
+    }
+    Ok(records)
+}
+
+/// Un fichier dont l'extent dépasse la fin de l'image (image tronquée) serait extrait
+/// raccourci sans erreur
+fn check_iso_extent(location: u32, size: u32, image_len: u64) -> io::Result<()> {
+    if location as u64 * 2048 + size as u64 > image_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Truncated ISO image: file of {} bytes at sector {} ends past the image ({} bytes)",
+                size, location, image_len
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Lecteur de l'image qui avance par sauts relatifs : des extents lus dans l'ordre des
+/// secteurs restent dans le tampon au lieu de coûter chacun un `lseek` et une lecture
+struct IsoReader<'f> {
+    inner: BufReader<&'f mut File>,
+    pos: u64,
+}
+
+impl<'f> IsoReader<'f> {
+    fn new(file: &'f mut File, buffer_size: usize) -> io::Result<Self> {
+        file.seek(SeekFrom::Start(0))?;
+        Ok(IsoReader { inner: BufReader::with_capacity(buffer_size, file), pos: 0 })
+    }
+
+    fn seek_to(&mut self, offset: u64) -> io::Result<()> {
+        if offset != self.pos {
+            self.inner.seek_relative(offset as i64 - self.pos as i64)?;
+            self.pos = offset;
+        }
+        Ok(())
+    }
+}
+
+impl Read for IsoReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// Secteurs des répertoires listés par la table des chemins : entrées de 8 octets
+/// (longueur du nom, attributs étendus, secteur, parent) suivies du nom, bourré à une
+/// longueur paire. Vide si la table est absente ou déborde de l'image.
+fn iso_path_table(reader: &mut IsoReader, root: &IsoRoot, image_len: u64) -> io::Result<Vec<u32>> {
+    let start = root.path_table_location as u64 * 2048;
+    let size = root.path_table_size;
+    if root.path_table_location == 0 || size == 0 || size > MAX_ISO_DIR_SIZE || start + size as u64 > image_len {
+        return Ok(Vec::new());
+    }
+    let mut table = vec![0u8; size as usize];
+    reader.seek_to(start)?;
+    reader.read_exact(&mut table)?;
+
+    let mut locations = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= table.len() && table[offset] != 0 {
+        let name_length = table[offset] as usize;
+        locations.push(u32::from_le_bytes(table[offset + 2..offset + 6].try_into().unwrap()));
+        offset += 8 + name_length + name_length % 2;
+    }
+    Ok(locations)
+}
+
+/// Lit d'avance, dans l'ordre des secteurs, les répertoires de la table des chemins, au
+/// lieu d'un aller-retour dans l'image par répertoire. Ceux que la table omet, ou dont
+/// l'enregistrement `.` est illisible, restent à lire à la demande par `iso_directory`.
+fn read_iso_directories(file: &mut File, root: &IsoRoot, buffer_size: usize) -> io::Result<HashMap<u32, Vec<IsoRecord>>> {
+    let image_len = file.metadata()?.len();
+    let mut reader = IsoReader::new(file, buffer_size)?;
+    let mut locations = iso_path_table(&mut reader, root, image_len)?;
+    locations.sort_unstable();
+    locations.dedup();
+
+    let mut directories = HashMap::new();
+    let mut first = [0u8; 34];
+    for location in locations {
+        let start = location as u64 * 2048;
+        if start + 2048 > image_len {
+            continue;
+        }
+        // Enregistrement `.` en tête du répertoire : il en donne la taille
+        reader.seek_to(start)?;
+        reader.read_exact(&mut first)?;
+        let size = u32::from_le_bytes(first[10..14].try_into().unwrap());
+        if first[0] < 34 || first[32] != 1 || first[33] != 0 || size == 0 || size > MAX_ISO_DIR_SIZE
+            || start + size as u64 > image_len
+        {
+            continue;
+        }
+        let mut data = vec![0u8; size as usize];
+        reader.seek_to(start)?;
+        reader.read_exact(&mut data)?;
+        let mut records = Vec::new();
+        if data.chunks(2048).try_for_each(|sector| parse_iso_sector(sector, root.joliet, &mut records)).is_ok() {
+            directories.insert(location, records);
+        }
+    }
+    Ok(directories)
+}
+
+/// Enregistrements du répertoire `location`, lus d'avance ou, à défaut, dans l'image.
+/// Un répertoire déjà parcouru (`visited`) trahit une boucle forgée dans l'arborescence.
+fn iso_directory(
+    file: &mut File,
+    directories: &mut HashMap<u32, Vec<IsoRecord>>,
+    visited: &mut HashSet<u32>,
+    location: u32,
+    size: u32,
+    joliet: bool,
+) -> io::Result<Vec<IsoRecord>> {
+    if !visited.insert(location) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Corrupt ISO image: directory at sector {} is reached twice", location),
+        ));
+    }
+    match directories.remove(&location) {
+        Some(records) => Ok(records),
+        None => read_iso_records(file, location, size, joliet),
+    }
+}
+
+/// Enregistrements d'un secteur de répertoire. Les noms Joliet sont en UTF-16 big-endian,
+/// les noms ISO 9660 en ASCII.
+fn parse_iso_sector(dir_data: &[u8], joliet: bool, records: &mut Vec<IsoRecord>) -> io::Result<()> {
+    let size = dir_data.len();
+    let mut offset = 0;
+    while offset < size {
+        // Zéro : bourrage jusqu'à la fin du secteur
+        let record_length = dir_data[offset] as usize;
+        if record_length == 0 {
+            break;
+        }
+        if record_length < 34 || offset + record_length > size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Corrupt ISO image: directory record of {} bytes at offset {}", record_length, offset),
+            ));
+        }
+
+        let name_length = dir_data[offset + 32] as usize;
+        // Identifiants 0x00 et 0x01 : entrées « . » et « .. »
+        let is_self_or_parent = name_length == 1 && dir_data[offset + 33] <= 1;
+        if name_length > 0 && !is_self_or_parent && offset + 33 + name_length <= offset + record_length {
+            let name_bytes = &dir_data[offset + 33..offset + 33 + name_length];
+
+            let decoded: String = if joliet {
+                let units = name_bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]]));
+                char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+            } else {
+                name_bytes.iter().map(|&b| if b.is_ascii() { b as char } else { '\0' }).collect()
+            };
+
+            // Clean up file name - split off version info and handle special characters
+            let mut name = String::new();
+            let mut version = None;
+            for (i, c) in decoded.char_indices() {
+                if c == ';' {
+                    version = decoded[i + 1..].parse::<u32>().ok();
+                    break;
+                }
+                if !is_illegal_in_filename(c) {
+                    name.push(c);
+                }
+            }
+
+            // Rock Ridge : le nom long (`NM`) remplace l'identifiant ISO et sa version
+            let su_start = (offset + 33 + name_length + (1 - name_length % 2)).min(offset + record_length);
+            let system_use = &dir_data[su_start..offset + record_length];
+            let rock_ridge = parse_rock_ridge(system_use);
+            if let Some(long_name) = rock_ridge.name {
+                name = long_name;
+                version = None;
+            }
+
+            // Skip empty names and special entries
+            if !name.is_empty() && name != "." && name != ".." {
+                let field = |at: usize| u32::from_le_bytes(dir_data[offset + at..offset + at + 4].try_into().unwrap());
+                records.push(IsoRecord {
+                    name,
+                    version,
+                    location: field(2),
+                    size: field(10),
+                    is_dir: dir_data[offset + 25] & 0x02 != 0,
+                    mtime: iso_record_time(&dir_data[offset + 18..offset + 25]),
+                    mode: rock_ridge.mode,
+                });
+            }
+        }
+
+        offset += record_length;
+    }
+    Ok(())
+}
+
+/// Champs Rock Ridge (RRIP) utiles d'une zone System Use
+#[derive(Default)]
+struct RockRidge {
+    name: Option<String>,
+    mode: Option<u32>,
+}
+
+/// Parcourt les entrées SUSP (`signature, longueur, version, données`) d'un
+/// enregistrement. Les zones de continuation (`CE`) ne sont pas suivies.
+fn parse_rock_ridge(mut area: &[u8]) -> RockRidge {
+    let mut rr = RockRidge::default();
+    let mut name: Option<Vec<u8>> = None;
+    while area.len() >= 4 {
+        let len = area[2] as usize;
+        if len < 4 || len > area.len() {
+            break;
+        }
+        let data = &area[4..len];
+        match &area[..2] {
+            // NM : drapeaux puis un morceau du nom (0x01 = suite dans l'entrée NM suivante,
+            // 0x02/0x04 = « . » / « .. »)
+            b"NM" if !data.is_empty() && data[0] & 0x06 == 0 => {
+                name.get_or_insert_with(Vec::new).extend_from_slice(&data[1..]);
+            }
+            b"PX" if data.len() >= 4 => {
+                rr.mode = Some(u32::from_le_bytes(data[..4].try_into().unwrap()));
+            }
+            b"ST" => break,
+            _ => {}
+        }
+        area = &area[len..];
+    }
+    rr.name = name
+        .map(|bytes| String::from_utf8_lossy(&bytes).replace(is_illegal_in_filename, "_"))
+        .filter(|n| !n.is_empty());
+    rr
+}
+
+/// Caractère qu'un nom de fichier ne peut pas contenir sur ce système
+fn is_illegal_in_filename(c: char) -> bool {
+    if cfg!(windows) {
+        c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+    } else {
+        matches!(c, '/' | '\0')
+    }
+}
+
+/// Date d'un enregistrement de répertoire : années depuis 1900, mois, jour, heure,
+/// minute, seconde, puis décalage horaire en quarts d'heure
+fn iso_record_time(date: &[u8]) -> Option<i64> {
+    let month = time::Month::try_from(date[1]).ok()?;
+    let day = time::Date::from_calendar_date(1900 + date[0] as i32, month, date[2]).ok()?;
+    let datetime = day.with_hms(date[3], date[4], date[5]).ok()?.assume_utc();
+    Some(datetime.unix_timestamp() - (date[6] as i8) as i64 * 15 * 60)
+}
+
+/// Entrées d'une image ISO pour `read_entries`, chemins séparés par '/'
+fn iso_entries(path: &Path, buffer_size: usize) -> io::Result<Vec<EntryInfo>> {
+    let mut file = File::open(path)?;
+    let mut pvd = [0u8; 2048];
+    file.seek(SeekFrom::Start(32768))?;
+    file.read_exact(&mut pvd)?;
+    if &pvd[1..6] != b"CD001" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid ISO 9660 signature"));
+    }
+    let root = iso_root(&mut file, &pvd)?;
+    let mut directories = read_iso_directories(&mut file, &root, buffer_size)?;
+
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(String::new(), root.location, root.size)];
+    while let Some((prefix, location, size)) = pending.pop() {
+        for record in iso_directory(&mut file, &mut directories, &mut visited, location, size, root.joliet)? {
+            let path = if prefix.is_empty() { record.name.clone() } else { format!("{}/{}", prefix, record.name) };
+            if record.is_dir {
+                pending.push((path.clone(), record.location, record.size));
+            }
+            entries.push(EntryInfo {
+                path,
+                size: if record.is_dir { 0 } else { record.size as u64 },
+                mtime: record.mtime,
+                is_dir: record.is_dir,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Fichier de l'image retenu pendant le parcours, extrait une fois l'arborescence créée
+struct IsoFile {
+    location: u32,
+    size: u32,
+    output_path: PathBuf,
+    mode: Option<u32>,
+}
+
+/// Parcourt un répertoire : crée ses sous-répertoires et range ses fichiers dans `files`
+#[allow(clippy::too_many_arguments)]
+fn collect_iso_directory(
+    file: &mut File,
+    directories: &mut HashMap<u32, Vec<IsoRecord>>,
+    visited: &mut HashSet<u32>,
+    location: u32,
+    size: u32,
+    joliet: bool,
+    output_base: &Path,
+    current_path: &str,
+    pb: &ProgressBar,
+    extracted_count: &mut u32,
+    files: &mut Vec<IsoFile>,
+    ex: &mut Extractor,
+) -> io::Result<()> {
+    let records = iso_directory(file, directories, visited, location, size, joliet)?;
+
+    // Plusieurs versions d'un même fichier (`NOM;1`, `NOM;2`) : par défaut seule la
+    // plus récente est extraite ; avec --iso-all-versions, chacune garde son suffixe.
+    let mut versions: HashMap<String, (u32, usize)> = HashMap::new();
+    for record in records.iter().filter(|r| !r.is_dir) {
+        let seen = versions.entry(record.name.clone()).or_insert((0, 0));
+        seen.0 = seen.0.max(record.version.unwrap_or(0));
+        seen.1 += 1;
+    }
+
+    for IsoRecord { name, version, location: file_location, size: file_size, is_dir: is_directory, mode, .. } in records {
+        let name = match versions.get(&name).filter(|(_, count)| *count > 1) {
+            Some(_) if ex.opts.iso_all_versions => format!("{};{}", name, version.unwrap_or(0)),
+            Some(&(max, _)) if version.unwrap_or(0) < max => {
+                pb.suspend(|| eprintln!(
+                    "Skipping older version {};{} (newest is ;{}; use --iso-all-versions to keep it)",
+                    name, version.unwrap_or(0), max
+                ));
+                continue;
+            }
+            _ => name,
+        };
+
+        let full_path = if current_path.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", current_path, name)
+        };
+        let Some(output_path) = ex.entry_path(output_base, Path::new(&full_path), is_directory)? else {
+            // Répertoire retiré par --strip-components : son contenu peut rester
+            if is_directory {
+                collect_iso_directory(
+                    file, directories, visited, file_location, file_size, joliet, output_base, &full_path, pb,
+                    extracted_count, files, ex,
+                )?;
+            }
+            continue;
+        };
+        
+        if !is_directory {
+            files.push(IsoFile { location: file_location, size: file_size, output_path, mode });
+            continue;
+        }
+        if let Err(e) = ex.create_dir(&output_path).and_then(|_| {
+            pb.set_message(format!("Created directory: {}", output_path.display()));
+            collect_iso_directory(
+                file,
+                directories,
+                visited,
+                file_location,
+                file_size,
+                joliet,
+                output_base,
+                &full_path,
+                pb,
+                extracted_count,
+                files,
+                ex,
+            )
+        }) {
+            ex.record_failure(&output_path, e);
+            continue;
+        }
+        
+        *extracted_count += 1;
+        pb.inc(1);
+    }
+    
+    Ok(())
+}
+
+/// Les marqueurs de suppression (anti-items) sont-ils fiables ? sevenz-rust 0.6, dont les
+/// versions précédentes de sharky, écrit les bits kAnti inversés : chaque fichier vide et
+/// répertoire y passe pour un anti-item. Quand toutes les entrées sans flux sont marquées,
+/// les marques sont ignorées (une mise à jour faite uniquement de suppressions l'est aussi).
+fn sevenz_anti_items(archive: &sevenz_rust::Archive) -> bool {
+    !archive.files.iter().filter(|e| !e.has_stream()).all(|e| e.is_anti_item())
+}
+
+fn decompress_7z(input: &Path, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    status!(ex.opts, "Attempting 7Z decompression...");
+    
+    let file = File::open(input)?;
+    let file_size = file.metadata()?.len();
+    
+    let mut reader = SevenZReader::new(file, file_size, sevenz_password(ex.opts.password.as_deref()))
+        .map_err(|e| sevenz_error(input, "Failed to open 7Z archive", e))?;
+    let declared_count = reader.archive().files.len();
+    
+    let anti_items = sevenz_anti_items(reader.archive());
+    let pb = extract_progress(ex, "7z", Some(declared_count as u64))?;
+    
+    let mut extracted_count = 0;
+    let wanted = ex.opts.extract_entry.as_deref();
+    // Entrée demandée rencontrée, et si c'est un répertoire
+    let mut found = None;
+    
+    reader.for_each_entries(|entry, reader| {
+        // Marqueur de suppression d'une mise à jour incrémentale : rien à créer
+        if anti_items && entry.is_anti_item() {
+            if ex.opts.verbose {
+                status!(ex.opts, "Skipping anti-item: {}", entry.name);
+            }
+            extracted_count += 1;
+            pb.inc(1);
+            return Ok(true);
+        }
+        // Fichier vide : pas de flux dans l'archive, le fichier est créé sans rien copier
+        let reader: &mut dyn Read = if entry.has_stream() { reader } else { &mut io::empty() };
+        if let Some(name) = wanted {
+            if entry_key(&entry.name) != entry_key(name) {
+                io::copy(reader, &mut io::sink())?;
+                return Ok(true);
+            }
+            found = Some(entry.is_directory());
+            if !entry.is_directory() {
+                ex.extract_named(reader, output, &entry.name, None)?;
+            }
+            return Ok(false);
+        }
+        let Some(entry_path) = ex.entry_path(output, Path::new(&entry.name), entry.is_directory())? else {
+            // Les données d'une entrée ignorée doivent être consommées avant la suivante
+            io::copy(reader, &mut io::sink())?;
+            extracted_count += 1;
+            pb.inc(1);
+            return Ok(true);
+        };
+        
+        pb.set_message(format!("Extracting: {}", entry.name));
+        
+        let result = if entry.is_directory() {
+            ex.create_dir(&entry_path)
+        } else {
+            let result = ex.create_parent(&entry_path).and_then(|()| ex.write_file(reader, &entry_path));
+            if result.is_err() && ex.opts.keep_going {
+                io::copy(reader, &mut io::sink())?;
+            }
+            result
+        };
+        ex.entry_result(&entry_path, result)?;
+        
+        extracted_count += 1;
+        pb.inc(1);
+        Ok(true)
+    }).map_err(|e| sevenz_error(input, "7Z extraction error", e))?;
+    if let Some(name) = wanted {
+        match found {
+            None => return Err(entry_not_found(name)),
+            Some(true) => return Err(entry_not_a_file(name)),
+            Some(false) => {}
+        }
+        pb.finish_with_message(format!("Extracted {}", name));
+        return Ok(());
+    }
+    verify_entry_count(Format::SevenZ, declared_count, extracted_count)?;
+    
+    if extracted_count == 0 {
+        report_empty_archive(ex, &pb);
+        return Ok(());
+    }
+    pb.finish_with_message(format!("7Z decompression done. Extracted {} files/directories.", extracted_count));
+    Ok(())
+}
+
+/// Auto-contrôle des formats à index (répertoire central zip, en-tête 7z) : chaque
+/// entrée annoncée doit avoir été extraite
+fn verify_entry_count(format: Format, declared: usize, extracted: usize) -> io::Result<()> {
+    if declared == extracted {
+        return Ok(());
+    }
+    Err(SharkyError::EntryCountMismatch { format: format.name(), declared, extracted }.into())
+}
+
+/// Flux concaténés (`cat a.gz b.gz > c.gz`) : un autre membre du même format suit-il ?
+/// Les sorties gz, bz2, xz, lzma et zst décodent ainsi tous les membres à la suite, comme
+/// `gzip -d` ; le décodeur zstd enchaîne de lui-même les trames.
+fn next_member<R: BufRead>(reader: &mut R, format: Format) -> io::Result<bool> {
+    let head = reader.fill_buf()?;
+    Ok(match format {
+        Format::Lzma => is_lzma_header(head),
+        _ => Format::from_magic(head) == Some(format),
+    })
+}
+
+/// En-tête `.lzma` plausible, selon les critères de `xz` (le format n'a pas de signature) :
+/// propriétés lc/lp/pb valides, dictionnaire de 2^n ou 2^n + 2^(n-1) octets, taille
+/// décompressée inconnue ou inférieure à 256 Gio
+fn is_lzma_header(head: &[u8]) -> bool {
+    if head.len() < 13 || head[0] >= 9 * 5 * 5 {
+        return false;
+    }
+    let dict = u32::from_le_bytes(head[1..5].try_into().unwrap());
+    let size = u64::from_le_bytes(head[5..13].try_into().unwrap());
+    let rounded = dict.checked_next_power_of_two().unwrap_or(0);
+    let valid_dict = dict != 0 && (dict == rounded || dict == rounded / 2 + rounded / 4 || dict == u32::MAX);
+    valid_dict && (size == u64::MAX || size < 1 << 38)
+}
+
+/// Décodeur `.lzma` (lzma_alone) lisant au fil de l'eau, pour les tar.lzma. Il ne sait pas
+/// s'arrêter sur des données qui suivent le flux : les fichiers seuls passent par `lzma_members`.
+fn lzma_reader<R: BufRead>(reader: R) -> io::Result<xz2::bufread::XzDecoder<R>> {
+    let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)?;
+    Ok(xz2::bufread::XzDecoder::new_stream(reader, stream))
+}
+
+/// Décode un flux `.lzma` puis ceux qui lui sont concaténés, en pilotant le `Stream` comme
+/// pour le xz : la mémoire reste bornée par le dictionnaire et le lecteur s'arrête juste
+/// après le dernier flux, pour `check_trailing_data`.
+fn lzma_members<R: BufRead, W: Write>(reader: &mut R, output: &mut W) -> io::Result<()> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)?;
+    loop {
+        let input_buf = reader.fill_buf()?;
+        let eof = input_buf.is_empty();
+        let (before_in, before_out) = (stream.total_in(), stream.total_out());
+        let action = if eof { xz2::stream::Action::Finish } else { xz2::stream::Action::Run };
+        let status = stream.process(input_buf, &mut buf, action)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("LZMA decompression error: {}", e)))?;
+        let consumed = (stream.total_in() - before_in) as usize;
+        let produced = (stream.total_out() - before_out) as usize;
+        reader.consume(consumed);
+        output.write_all(&buf[..produced])?;
+        if status == xz2::stream::Status::StreamEnd {
+            if !next_member(reader, Format::Lzma)? {
+                return Ok(());
+            }
+            stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)?;
+            continue;
+        }
+        if eof && produced == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "premature eof in LZMA stream"));
+        }
+    }
+}
+
+/// Vérifie qu'il ne reste rien après la fin du flux compressé : des octets en
+/// trop signalent souvent une corruption ou un second flux concaténé.
+fn check_trailing_data<R: BufRead>(mut rest: R, input: &Path, label: &str, strict: bool) -> io::Result<()> {
+    if rest.fill_buf()?.is_empty() {
+        return Ok(());
+    }
+    let trailing = io::copy(&mut rest, &mut io::sink())?;
+    let msg = format!("{:?}: {} bytes of trailing data after the end of the {} stream", input, trailing, label);
+    if strict {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    eprintln!("Warning: {} (use --strict to treat this as an error)", msg);
+    Ok(())
+}
+
+/// Le nom (FNAME) et la date (MTIME) de l'en-tête gzip priment sur le nom de l'archive
+fn decompress_single_file_gz(input: &Path, output: &Path, strict: bool, ex: &Extractor) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut decoder = GzBufDecoder::new(&mut reader);
+    
+    let stored_name = decoder.header().and_then(gz_stored_name);
+    let output_name = stored_name.as_deref().or_else(|| input.file_stem().and_then(|s| s.to_str()))
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    let mtime = decoder.header().map(|h| h.mtime()).filter(|&t| t != 0);
+    // Fichier gardé par --no-clobber : sa date ne doit pas changer
+    let kept = ex.opts.existing == ExistingFiles::NoClobber && fs::symlink_metadata(&output_file_path).is_ok();
+    
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    io::copy(&mut decoder, &mut output_file)?;
+    drop(decoder);
+    while next_member(&mut reader, Format::Gz)? {
+        io::copy(&mut GzBufDecoder::new(&mut reader), &mut output_file)?;
+    }
+    drop(output_file);
+    check_trailing_data(reader, input, "GZ", strict)?;
+    if !ex.opts.writes_stdout() && !kept {
+        ex.set_metadata(&output_file_path, None, mtime.map(i64::from))?;
+    }
+    
+    ex.stream_done("GZ", &output_file_path);
+    Ok(())
+}
+
+/// Nom de fichier de l'en-tête gzip, réduit à son dernier composant ; écarté s'il est vide
+/// ou désigne un répertoire (`.`, `..`)
+fn gz_stored_name(header: &flate2::GzHeader) -> Option<String> {
+    let name = String::from_utf8_lossy(header.filename()?).into_owned();
+    let name = Path::new(&name).file_name()?.to_str()?.to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+fn decompress_single_file_bz2(input: &Path, output: &Path, strict: bool, ex: &Extractor) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    // Premier flux toujours décodé (une entrée vide y signale son erreur), puis les suivants
+    loop {
+        io::copy(&mut bzip2::bufread::BzDecoder::new(&mut reader), &mut output_file)?;
+        if !next_member(&mut reader, Format::Bz2)? {
+            break;
+        }
+    }
+    check_trailing_data(reader, input, "BZ2", strict)?;
+    
+    ex.stream_done("BZ2", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_xz(input: &Path, output: &Path, strict: bool, ex: &Extractor) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    // Le décodeur xz2 renvoie "corrupt xz stream" s'il est relu après la fin du
+    // flux : on pilote le `Stream` nous-mêmes pour s'arrêter sur StreamEnd, puis on
+    // repart sur un nouveau `Stream` si un autre flux xz suit.
+    let mut buf = vec![0u8; 64 * 1024];
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    let mut stream = xz2::stream::Stream::new_stream_decoder(u64::MAX, 0)?;
+    loop {
+        let input_buf = reader.fill_buf()?;
+        let eof = input_buf.is_empty();
+        let (before_in, before_out) = (stream.total_in(), stream.total_out());
+        let action = if eof { xz2::stream::Action::Finish } else { xz2::stream::Action::Run };
+        let status = stream.process(input_buf, &mut buf, action)?;
+        let consumed = (stream.total_in() - before_in) as usize;
+        let produced = (stream.total_out() - before_out) as usize;
+        reader.consume(consumed);
+        output_file.write_all(&buf[..produced])?;
+        if status == xz2::stream::Status::StreamEnd {
+            if !next_member(&mut reader, Format::Xz)? {
+                break;
+            }
+            stream = xz2::stream::Stream::new_stream_decoder(u64::MAX, 0)?;
+            continue;
+        }
+        if eof && produced == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "premature eof in XZ stream"));
+        }
+    }
+    check_trailing_data(reader, input, "XZ", strict)?;
+    
+    ex.stream_done("XZ", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_zstd(input: &Path, output: &Path, strict: bool, ex: &Extractor) -> io::Result<()> {
+    let input_file = File::open(input)?;
+    let mut decoder = zstd_decoder(BufReader::new(input_file))?;
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    io::copy(&mut decoder, &mut output_file)?;
+    check_trailing_data(decoder.finish(), input, "ZSTD", strict)?;
+    
+    ex.stream_done("ZSTD", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_lzma(input: &Path, output: &Path, strict: bool, ex: &Extractor) -> io::Result<()> {
+    let mut reader = BufReader::with_capacity(ex.opts.buffer_size, File::open(input)?);
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    let mut output_file = BufWriter::new(ex.create_stream_output(&output_file_path)?);
+    lzma_members(&mut reader, &mut output_file)?;
+    output_file.flush()?;
+    drop(output_file);
+    check_trailing_data(reader, input, "LZMA", strict)?;
+    
+    ex.stream_done("LZMA", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_brotli(input: &Path, output: &Path, ex: &Extractor) -> io::Result<()> {
+    let input_file = File::open(input)?;
+    let mut decoder = BrotliDecoder::new(input_file, 4096);
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    io::copy(&mut decoder, &mut output_file)?;
+    
+    ex.stream_done("Brotli", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_lzfse(input: &Path, output: &Path, ex: &Extractor) -> io::Result<()> {
+    let mut decoder = LzfseRingDecoder::default();
+    let mut reader = decoder.reader(File::open(input)?);
+
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+
+    let mut output_file = ex.create_stream_output(&output_file_path)?;
+    io::copy(&mut reader, &mut output_file)?;
+
+    ex.stream_done("LZFSE", &output_file_path);
+    Ok(())
+}
+
+fn decompress_single_file_lz4(input: &Path, output: &Path, ex: &Extractor) -> io::Result<()> {
+    // Pour LZ4, nous utiliserons une implémentation simple
+    // Vous devrez ajouter la crate lz4_flex à vos dépendances
+    let input_data = fs::read(input)?;
+    
+    // Décompression LZ4 (nécessite lz4_flex crate)
+    let decompressed = lz4_flex::decompress_size_prepended(&input_data)
+        .map_err(|e| io::Error::other(format!("LZ4 decompression error: {}", e)))?;
+    
+    let output_name = input.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("decompressed");
+    let output_file_path = output.join(output_name);
+    
+    ex.create_stream_output(&output_file_path)?.write_all(&decompressed)?;
+    
+    ex.stream_done("LZ4", &output_file_path);
+    Ok(())
+}
+
+fn decompress_cab(_input: &Path, _output: &Path) -> io::Result<()> {
+    // Pour les fichiers CAB, il faudrait une crate comme `cab` ou un outil externe
+    Err(unsupported("CAB decompression is not implemented; consider using external tools like 'cabextract'"))
+}
+
+
+/// Barre de progression en octets, dimensionnée sur la taille des fichiers de l'entrée
+/// (hors exclusions) et avancée par les lectures de l'archivage.
+/// Le même parcours calcule l'horodatage `--archive-time` (en secondes Unix) s'il est demandé.
+fn build_progress(opts: &CompressOptions, filter: &ExcludeFilter) -> io::Result<(ProgressBar, Option<u64>)> {
+    let mut total = 0u64;
+    let mut stamp: Option<u64> = None;
+    let walker = WalkDir::new(opts.input())
+        .max_depth(opts.max_depth.unwrap_or(usize::MAX))
+        .follow_links(follows_links(opts));
+    for entry in walker.into_iter().filter_map(Result::ok) {
+        if filter.is_excluded(entry.path()) { continue }
+        // Un lien archivé comme lien ne lit aucune donnée
+        if !entry.file_type().is_dir() && !entry.file_type().is_symlink() {
+            let size = file_size(entry.path());
+            if size_excluded(opts, size) { continue }
+            total += size;
+        }
+        let Some(which) = opts.archive_time else { continue };
+        let Some(mtime) = entry.metadata().ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+        else { continue };
+        stamp = Some(match (stamp, which) {
+            (None, _) => mtime,
+            (Some(s), ArchiveTime::Newest) => s.max(mtime),
+            (Some(s), ArchiveTime::Oldest) => s.min(mtime),
+        });
+    }
+    if let (Some(stamp), true) = (stamp, opts.verbose) {
+        status!(opts, "Archive time: {} (unix time) for every entry", stamp);
+    }
+    // Préfixé du format produit, comme les barres d'extraction
+    let label = output_format(opts).map_or("sharky", |format| format.name());
+    let pb = ProgressBar::new(total.max(1)).with_prefix(label);
+    let style = ProgressStyle::with_template(
+        "{spinner:.green} {prefix} [{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta}) {wide_msg}"
+    )
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    pb.set_style(style.progress_chars("#>-"));
+    if opts.writes_stdout() || opts.hides_progress() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    Ok((pb, stamp))
+}
+
+/// `--skip-errors` : erreur d'un fichier (ou répertoire) supprimé ou devenu illisible depuis
+/// l'énumération, vérifié avant que son en-tête ne parte dans l'archive. Une erreur en
+/// cours de lecture reste fatale : l'entrée serait déjà à moitié écrite.
+fn unreadable(opts: &CompressOptions, path: &Path, is_dir: bool) -> Option<io::Error> {
+    if !opts.skip_errors {
+        return None;
+    }
+    if is_dir { fs::metadata(path).err() } else { File::open(path).err() }
+}
+
+/// Taille hors des bornes `--exclude-larger-than` / `--exclude-smaller-than`
+fn size_excluded(opts: &CompressOptions, size: u64) -> bool {
+    opts.exclude_larger_than.is_some_and(|max| size > max) || opts.exclude_smaller_than.is_some_and(|min| size < min)
+}
+
+/// Fichier seul en entrée, écarté par sa taille : une erreur plutôt qu'une archive vide
+fn check_input_size(opts: &CompressOptions) -> io::Result<()> {
+    let input = opts.input();
+    if input.is_file() && size_excluded(opts, file_size(input)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{:?} ({} bytes) is excluded by --exclude-larger-than / --exclude-smaller-than; nothing to compress",
+                input, file_size(input)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Écarte un fichier par sa taille : signalé sous `--verbose`, compté pour le bilan.
+/// Les répertoires et les liens archivés comme liens ne sont jamais écartés.
+fn skip_by_size(opts: &CompressOptions, log: &mut EventLog, path: &Path, file_type: fs::FileType) -> bool {
+    if !file_type.is_file() || (opts.exclude_larger_than.is_none() && opts.exclude_smaller_than.is_none()) {
+        return false;
+    }
+    let size = file_size(path);
+    if !size_excluded(opts, size) {
+        return false;
+    }
+    if opts.verbose {
+        status!(opts, "Excluded by size: {} ({} bytes)", path.display(), size);
+    }
+    log.size_excluded += 1;
+    log.size_excluded_bytes += size;
+    true
+}
+
+/// Liens symboliques suivis au parcours : `--dereference`, ou sortie zip / 7z
+fn follows_links(opts: &CompressOptions) -> bool {
+    opts.dereference || output_format(opts).is_ok_and(|f| matches!(f, Format::Zip | Format::SevenZ))
+}
+
+/// Entrée d'un parcours ; une boucle de liens suivis est signalée puis sautée, les autres
+/// erreurs de parcours ignorées comme avant
+fn walk_entry(pb: &ProgressBar, entry: walkdir::Result<DirEntry>) -> Option<DirEntry> {
+    match entry {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            if let (Some(path), Some(ancestor)) = (e.path(), e.loop_ancestor()) {
+                pb.suspend(|| eprintln!("Warning: skipping symlink loop {:?} → {:?}", path, ancestor));
+            }
+            None
+        }
+    }
+}
+
+/// Écarte un fichier illisible sous `--skip-errors`, en le comptant comme traité
+fn skip_unreadable(pb: &ProgressBar, log: &mut EventLog, path: &Path, error: io::Error) -> io::Result<()> {
+    pb.suspend(|| eprintln!("Warning: skipping {:?}: {}", path, error));
+    pb.inc(file_size(path));
+    log.skip(path, error)
+}
+
+/// Taille comptée par la barre de progression : celle du fichier lu, liens suivis
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |m| m.len())
+}
+
+/// Ajoute un fichier ou un répertoire et le consigne dans `--log-file`
+fn append_entry(
+    builder: &mut Builder<impl Write>,
+    name: &Path,
+    path: &Path,
+    is_dir: bool,
+    opts: AppendOptions,
+    log: &mut EventLog,
+) -> io::Result<()> {
+    let result = append_header(builder, name, path, is_dir, opts);
+    let size = if is_dir { 0 } else { fs::metadata(path).map(|m| m.len()).unwrap_or(0) };
+    let written = if result.is_ok() { size } else { 0 };
+    let (result, digest) = match result {
+        Ok(digest) => (Ok(()), digest),
+        Err(e) => (Err(e), None),
+    };
+    log.record("add", path, size, written, &result)?;
+    if result.is_ok() {
+        log.manifest(name, size, digest.as_deref())?;
+    }
+    result
+}
+
+/// Lien symbolique archivé comme lien (sans `--dereference`), consigné dans `--log-file`
+fn append_symlink(builder: &mut Builder<impl Write>, name: &Path, path: &Path, mtime: Option<u64>, log: &mut EventLog) -> io::Result<()> {
+    let result = fs::read_link(path).and_then(|target| {
+        let mut header = Header::new_gnu();
+        header.set_metadata(&fs::symlink_metadata(path)?);
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        if let Some(mtime) = mtime {
+            header.set_mtime(mtime);
+        }
+        builder.append_link(&mut header, name, target)
+    });
+    log.record("add", path, 0, 0, &result)?;
+    result
+}
+
+/// Réglages appliqués à chaque entrée tar
+#[derive(Clone, Copy)]
+struct AppendOptions<'a> {
+    /// Horodatage imposé (`--archive-time`)
+    mtime: Option<u64>,
+    /// Seuil des trous pour `--detect-sparse`
+    sparse_threshold: Option<u64>,
+    /// Filtres externes `--file-transform`
+    transforms: &'a [FileTransform],
+    /// En-tête PAX `SHARKY.sha256` avant chaque fichier
+    embed_checksums: bool,
+    /// SHA-256 des fichiers pour `--manifest`
+    hash: bool,
+    /// Nouvelles tentatives après une erreur d'E/S passagère (`--io-retries`)
+    io_retries: u32,
+    /// Barre avancée des octets lus
+    progress: &'a ProgressBar,
+}
+
+/// `--file-transform EXT:COMMANDE` : les fichiers d'extension EXT passent par COMMANDE
+/// (entrée standard → sortie standard) avant d'être archivés
+struct FileTransform {
+    ext: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl FileTransform {
+    fn parse(spec: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid --file-transform '{}' (expected EXT:COMMAND)", spec),
+        );
+        let (ext, command) = spec.split_once(':').ok_or_else(invalid)?;
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words.next().ok_or_else(invalid)?;
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        if ext.is_empty() {
+            return Err(invalid());
+        }
+        Ok(FileTransform { ext, program, args: words.collect() })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|e| e.to_string_lossy().to_lowercase() == self.ext)
+    }
+
+    /// Fait passer le fichier par la commande et renvoie sa sortie. La commande est lancée
+    /// directement (sans shell) ; un code de retour non nul fait échouer l'entrée.
+    fn apply(&self, path: &Path) -> io::Result<Vec<u8>> {
+        use std::process::{Command, Stdio};
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("Cannot run transform '{}': {}", self.program, e)))?;
+        // Écriture de l'entrée dans un thread : la commande peut remplir stdout avant d'avoir tout lu
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let mut input = File::open(path)?;
+        let feeder = std::thread::spawn(move || io::copy(&mut input, &mut stdin).map(|_| ()));
+        let output = child.wait_with_output()?;
+        let fed = feeder.join().map_err(|_| io::Error::other("transform input thread panicked"))?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "Transform '{}' failed on {:?} ({}): {}",
+                self.program, path, output.status, String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        // Une commande qui sort sans lire toute son entrée (EPIPE) reste acceptée si elle a réussi
+        if let Err(e) = fed && e.kind() != io::ErrorKind::BrokenPipe {
+            return Err(e);
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Avec un horodatage imposé, l'en-tête est construit à la main pour
+/// remplacer le mtime du système de fichiers. Renvoie le SHA-256 des données archivées
+/// d'un fichier si `embed_checksums` ou `hash` le demandent.
+///
+/// Les chemins (et cibles de liens) de plus de 100 octets ne tiennent pas dans l'en-tête :
+/// `append_data`, `append_dir` et `append_link` les écrivent alors en entier dans un
+/// enregistrement GNU `././@LongLink` qui précède l'en-tête, sans troncature.
+fn append_header(
+    builder: &mut Builder<impl Write>,
+    name: &Path,
+    path: &Path,
+    is_dir: bool,
+    opts: AppendOptions,
+) -> io::Result<Option<String>> {
+    if let (Some(transform), false) = (opts.transforms.iter().find(|t| t.matches(path)), is_dir) {
+        let data = transform.apply(path)?;
+        let mut digest = None;
+        if opts.embed_checksums || opts.hash {
+            let mut hasher = Sha256::new();
+            hasher.update(&data);
+            digest = Some(hex_digest(hasher));
+        }
+        if let (true, Some(digest)) = (opts.embed_checksums, &digest) {
+            append_pax_checksum(builder, digest)?;
+        }
+        let mut header = Header::new_gnu();
+        header.set_metadata(&fs::metadata(path)?);
+        header.set_size(data.len() as u64);
+        if let Some(mtime) = opts.mtime {
+            header.set_mtime(mtime);
+        }
+        builder.append_data(&mut header, name, data.as_slice())?;
+        opts.progress.inc(file_size(path));
+        return Ok(digest);
+    }
+    let mut digest = None;
+    if opts.embed_checksums && !is_dir {
+        // L'en-tête précède les données : une première lecture calcule la somme
+        let sum = file_digest(path, opts.io_retries)?;
+        append_pax_checksum(builder, &sum)?;
+        digest = Some(sum);
+    }
+    if let (Some(threshold), false) = (opts.sparse_threshold, is_dir) {
+        let mut f = retry_io(opts.io_retries, || File::open(path))?;
+        let meta = f.metadata()?;
+        if let Some(segments) = sparse_segments(&mut f, meta.len(), threshold)? {
+            // Seules les plages de données sont lues à l'archivage : la somme demande sa propre lecture
+            if opts.hash && digest.is_none() {
+                digest = Some(file_digest(path, opts.io_retries)?);
+            }
+            append_sparse(builder, name, f, &meta, segments, opts.mtime)?;
+            opts.progress.inc(meta.len());
+            return Ok(digest);
+        }
+    }
+    if opts.hash && digest.is_none() && !is_dir {
+        // Somme calculée pendant la lecture qui alimente l'archive
+        let f = RetryReader::open(path, opts.io_retries)?;
+        let mut header = Header::new_gnu();
+        header.set_metadata(&f.inner.metadata()?);
+        if let Some(mtime) = opts.mtime {
+            header.set_mtime(mtime);
+        }
+        let mut hashing = HashingReader { inner: opts.progress.wrap_read(f), hasher: Sha256::new() };
+        builder.append_data(&mut header, name, &mut hashing)?;
+        return Ok(Some(hex_digest(hashing.hasher)));
+    }
+    let appended = match (opts.mtime, is_dir) {
+        (None, true) => builder.append_dir(name, path),
+        (Some(mtime), true) => {
+            let mut header = Header::new_gnu();
+            header.set_metadata(&fs::metadata(path)?);
+            header.set_mtime(mtime);
+            builder.append_data(&mut header, name, io::empty())
+        }
+        // En-tête de `append_file`, mais données lues à travers la barre de progression
+        (mtime, false) => {
+            let f = RetryReader::open(path, opts.io_retries)?;
+            let mut header = Header::new_gnu();
+            header.set_metadata(&f.inner.metadata()?);
+            if let Some(mtime) = mtime {
+                header.set_mtime(mtime);
+            }
+            builder.append_data(&mut header, name, opts.progress.wrap_read(f))
+        }
+    };
+    appended.map(|()| digest)
+}
+
+fn file_digest(path: &Path, retries: u32) -> io::Result<String> {
+    let mut hashing = HashingReader { inner: RetryReader::open(path, retries)?, hasher: Sha256::new() };
+    io::copy(&mut hashing, &mut io::sink())?;
+    Ok(hex_digest(hashing.hasher))
+}
+
+/// Somme `SHARKY.sha256` de l'en-tête PAX d'une entrée, si présente
+fn pax_checksum<R: Read>(entry: &mut tar::Entry<R>) -> io::Result<Option<String>> {
+    Ok(entry.pax_extensions()?.and_then(|exts| {
+        exts.filter_map(Result::ok)
+            .find(|ext| ext.key_bytes() == PAX_SHA256.as_bytes())
+            .map(|ext| String::from_utf8_lossy(ext.value_bytes()).into_owned())
+    }))
+}
+
+/// Entrée PAX locale (`x`) portant la somme de l'entrée qui suit ; les autres outils
+/// ignorent les clés qu'ils ne connaissent pas
+fn append_pax_checksum(builder: &mut Builder<impl Write>, digest: &str) -> io::Result<()> {
+    // Chaque enregistrement est « LONGUEUR clé=valeur\n », LONGUEUR comptant ses propres chiffres
+    let body = format!(" {}={}\n", PAX_SHA256, digest);
+    let mut len = body.len() + 1;
+    while len.to_string().len() + body.len() != len {
+        len += 1;
+    }
+    let record = format!("{}{}", len, body);
+    let mut header = Header::new_ustar();
+    header.set_entry_type(tar::EntryType::XHeader);
+    header.set_size(record.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, "././@PaxHeader", record.as_bytes())
+}
+
+/// Plages de données d'un fichier dont les suites de blocs nuls d'au moins
+/// `threshold` octets deviennent des trous. `None` s'il n'y a aucun trou.
+fn sparse_segments(file: &mut File, len: u64, threshold: u64) -> io::Result<Option<Vec<(u64, u64)>>> {
+    const BLOCK: u64 = 512;
+    let threshold = threshold.max(1).div_ceil(BLOCK) * BLOCK;
+    if len < threshold {
+        return Ok(None);
+    }
+    let mut holes = Vec::new();
+    let mut run_start = None;
+    let mut pos = 0u64;
+    let mut buf = vec![0u8; 128 * BLOCK as usize];
+    loop {
+        let n = read_head(file, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for block in buf[..n].chunks(BLOCK as usize) {
+            if block.iter().all(|&b| b == 0) {
+                run_start.get_or_insert(pos);
+            } else if let Some(start) = run_start.take()
+                && pos - start >= threshold
+            {
+                holes.push((start, pos));
+            }
+            pos += block.len() as u64;
+        }
+    }
+    if let Some(start) = run_start
+        && pos - start >= threshold
+    {
+        holes.push((start, pos));
+    }
+    if holes.is_empty() {
+        return Ok(None);
+    }
+
+    let mut segments = Vec::new();
+    let mut cur = 0;
+    for (start, end) in holes {
+        if start > cur {
+            segments.push((cur, start - cur));
+        }
+        cur = end;
+    }
+    // Un trou final est marqué par un segment vide à la taille réelle
+    segments.push((cur, pos - cur));
+    Ok(Some(segments))
+}
+
+/// Écrit une entrée GNU sparse : les segments au-delà des 4 de l'en-tête vont
+/// dans des blocs d'extension (21 par bloc) placés avant les données.
+fn append_sparse(
+    builder: &mut Builder<impl Write>,
+    name: &Path,
+    file: File,
+    meta: &fs::Metadata,
+    segments: Vec<(u64, u64)>,
+    mtime: Option<u64>,
+) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_metadata(meta);
+    header.set_entry_type(tar::EntryType::GNUSparse);
+    header.set_size(segments.iter().map(|&(_, len)| len).sum());
+    if let Some(mtime) = mtime {
+        header.set_mtime(mtime);
+    }
+
+    let (inline, rest) = segments.split_at(segments.len().min(4));
+    let gnu = header.as_gnu_mut().expect("GNU header");
+    gnu.set_real_size(meta.len());
+    for (slot, &(offset, len)) in gnu.sparse.iter_mut().zip(inline) {
+        slot.set_offset(offset);
+        slot.set_length(len);
+    }
+    gnu.set_is_extended(!rest.is_empty());
+
+    let mut extensions = Vec::new();
+    let chunks: Vec<_> = rest.chunks(21).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut ext = tar::GnuExtSparseHeader::new();
+        for (slot, &(offset, len)) in ext.sparse.iter_mut().zip(chunk.iter()) {
+            slot.set_offset(offset);
+            slot.set_length(len);
+        }
+        ext.set_is_extended(i + 1 < chunks.len());
+        extensions.extend_from_slice(ext.as_bytes());
+    }
+
+    let data = SparseData { file, segments: segments.into_iter(), remaining: 0 };
+    builder.append_data(&mut header, name, io::Cursor::new(extensions).chain(data))
+}
+
+/// Lit à la suite les plages de données d'un fichier creux
+struct SparseData {
+    file: File,
+    segments: std::vec::IntoIter<(u64, u64)>,
+    remaining: u64,
+}
+
+impl Read for SparseData {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let Some((offset, len)) = self.segments.next() else { return Ok(0) };
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.remaining = len;
+        }
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.file.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file shrank while being archived"));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Reprise `--resume` : nombre d'entrées déjà archivées à sauter, et rappel appelé après
+/// chaque entrée avec le nombre d'entrées traitées depuis le début du parcours
+type ResumeHook<'a, W> = (usize, &'a mut dyn FnMut(&mut Builder<W>, usize) -> io::Result<()>);
+
+/// Ajoute l'arborescence `--input` au tar et renvoie le nombre d'entrées du parcours
+fn traverse_and_append<W: Write>(
+    builder: &mut Builder<W>,
+    pb: &ProgressBar,
+    filter: &ExcludeFilter,
+    opts: &CompressOptions,
+    mtime: Option<u64>,
+    log: &mut EventLog,
+    mut resume: Option<ResumeHook<'_, W>>,
+) -> io::Result<usize> {
+    let input = opts.input();
+    let skip = |p: &Path| filter.is_excluded(p);
+    let mut entries_done = 0;
+    // Les entrées déjà archivées avant l'interruption sont sautées, leur taille comptée comme faite
+    let mut next_entry = |builder: &mut Builder<W>, size: u64, append: &mut dyn FnMut(&mut Builder<W>) -> io::Result<()>| {
+        entries_done += 1;
+        match &mut resume {
+            Some((already, _)) if entries_done <= *already => {
+                pb.inc(size);
+                Ok(())
+            }
+            Some((_, after_entry)) => {
+                append(builder)?;
+                after_entry(builder, entries_done)
+            }
+            None => append(builder),
+        }
+    };
+    let transforms = opts.file_transform.iter().map(|spec| FileTransform::parse(spec)).collect::<io::Result<Vec<_>>>()?;
+    let append_opts = AppendOptions {
+        mtime,
+        sparse_threshold: opts.detect_sparse.then_some(opts.sparse_threshold),
+        transforms: &transforms,
+        embed_checksums: opts.embed_checksums,
+        hash: opts.manifest.is_some(),
+        io_retries: opts.io_retries,
+        progress: pb,
+    };
+    let max_depth = opts.max_depth;
+    if input.is_dir() {
+        let root = archive_root(opts);
+        if !opts.no_wrap {
+            compressing(opts, pb, root.display());
+            next_entry(builder, 0, &mut |b| append_entry(b, root, input, true, append_opts, log))?;
+        }
+        let mut truncated_dirs = 0;
+        let mut walker = WalkDir::new(input)
+            .min_depth(1)
+            .max_depth(max_depth.unwrap_or(usize::MAX))
+            .follow_links(opts.dereference);
+        if opts.resume {
+            walker = walker.sort_by_file_name();
+        }
+        let entries = walker.into_iter().filter_map(|e| walk_entry(pb, e));
+        let entries: Box<dyn Iterator<Item = DirEntry>> = if opts.similarity_sort {
+            Box::new(similarity_order(entries.collect()).into_iter())
+        } else {
+            Box::new(entries)
+        };
+        for entry in entries {
+            let path = entry.path().to_path_buf();
+            if skip(&path) || skip_by_size(opts, log, &path, entry.file_type()) { continue }
+            let rel = path.strip_prefix(input).unwrap();
+            let tp = if opts.no_wrap { rel.to_path_buf() } else { root.join(rel) };
+            compressing(opts, pb, tp.display());
+            // Sans --dereference, le parcours ne suit pas les liens : ils restent des liens
+            if entry.file_type().is_symlink() {
+                next_entry(builder, 0, &mut |b| append_symlink(b, &tp, &path, mtime, log))?;
+                continue;
+            }
+            if let Some(e) = unreadable(opts, &path, entry.file_type().is_dir()) {
+                skip_unreadable(pb, log, &path, e)?;
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                next_entry(builder, 0, &mut |b| append_entry(b, &tp, &path, true, append_opts, log))?;
+                if Some(entry.depth()) == max_depth
+                    && fs::read_dir(&path).is_ok_and(|mut d| d.next().is_some())
+                {
+                    truncated_dirs += 1;
+                }
+            } else {
+                next_entry(builder, file_size(&path), &mut |b| append_entry(b, &tp, &path, false, append_opts, log))?;
+            }
+        }
+        if truncated_dirs > 0 {
+            pb.suspend(|| eprintln!(
+                "Warning: {} directories beyond --max-depth were not descended into",
+                truncated_dirs
+            ));
+        }
+    } else if !skip(input) {
+        compressing(opts, pb, input.display());
+        if let Some(e) = unreadable(opts, input, false) {
+            skip_unreadable(pb, log, input, e)?;
+            return Ok(entries_done);
+        }
+        let name = archive_root(opts);
+        next_entry(builder, file_size(input), &mut |b| append_entry(b, name, input, false, append_opts, log))?;
+    }
+    Ok(entries_done)
+}
+
+/// Entrée en cours d'archivage : message de la barre, ou ligne à part sous `--verbose`
+fn compressing(opts: &CompressOptions, pb: &ProgressBar, name: impl std::fmt::Display) {
+    if opts.verbose {
+        status!(opts, "{}", name);
+    } else {
+        pb.set_message(format!("Compressing: {}", name));
+    }
+}
+
+/// Ordonne les entrées pour rapprocher les contenus similaires : les répertoires
+/// d'abord (dans l'ordre du parcours), puis les fichiers groupés par extension et taille.
+fn similarity_order(mut entries: Vec<DirEntry>) -> Vec<DirEntry> {
+    entries.sort_by_cached_key(|e| {
+        if e.file_type().is_dir() {
+            return (false, String::new(), 0);
+        }
+        let ext = e.path()
+            .extension()
+            .map(|x| x.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+        (true, ext, size)
+    });
+    entries
+}
+
+/// Extrait un flux tar éventuellement compressé, selon le format `tar.*` donné
+fn decompress_tar_stream<R: BufRead>(format: Format, reader: R, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    format.codec().decode(Box::new(reader), &mut |tar| decompress_tar_plain(tar, output, ex))
+}
+
+/// Paquet Debian : archive `ar` contenant `debian-binary`, `control.tar.*` et `data.tar.*`.
+/// Les tar internes sont extraits dans `control/` et `data/`.
+fn decompress_deb<R: Read>(reader: R, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    let mut archive = ar::Archive::new(reader);
+    let mut members = 0;
+    while let Some(entry) = archive.next_entry() {
+        let mut entry = entry?;
+        let name = String::from_utf8_lossy(entry.header().identifier())
+            .trim_end_matches('/')
+            .to_string();
+        members += 1;
+
+        if name.starts_with("control.tar") || name.starts_with("data.tar") {
+            let dir = if name.starts_with("control") { "control" } else { "data" };
+            status!(ex.opts, "Extracting {} → {}/", name, dir);
+            let target = output.join(dir);
+            ex.create_parent(&target.join("-"))?;
+            let format = Format::from_extension(Path::new(&name)).unwrap_or(Format::Tar);
+            decompress_tar_stream(format, BufReader::new(entry), &target, ex)?;
+        } else {
+            // Les autres membres (debian-binary, signatures) sont copiés tels quels
+            let file_name = Path::new(&name).file_name().ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid ar member name '{}'", name),
+            ))?;
+            let outpath = output.join(file_name);
+            let result = ex.make_room(&outpath).and_then(|keep| {
+                if keep {
+                    io::copy(&mut entry, &mut ex.create_file(&outpath)?)?;
+                }
+                Ok(())
+            });
+            ex.entry_result(&outpath, result)?;
+        }
+    }
+    if members == 0 {
+        status!(ex.opts, "Archive is empty, nothing to extract.");
+    }
+    Ok(())
+}
+
+/// Paquet RPM : lead (96 octets), en-tête de signature aligné sur 8 octets,
+/// en-tête principal, puis une charge utile cpio compressée.
+fn decompress_rpm<R: BufRead>(mut reader: R, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    let mut lead = [0u8; 96];
+    reader.read_exact(&mut lead)?;
+    if lead[..4] != [0xed, 0xab, 0xee, 0xdb] {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid RPM lead signature"));
+    }
+
+    let signature_len = skip_rpm_header(&mut reader)?;
+    let padding = (8 - signature_len % 8) % 8;
+    io::copy(&mut reader.by_ref().take(padding), &mut io::sink())?;
+    skip_rpm_header(&mut reader)?;
+
+    let head = reader.fill_buf()?;
+    if head.starts_with(b"0707") {
+        return decompress_cpio(reader, output, ex);
+    }
+    match Format::from_magic(head) {
+        Some(format) if format.is_single_stream() => {
+            format.codec().decode(Box::new(reader), &mut |payload| decompress_cpio(payload, output, ex))
+        }
+        _ => Err(unsupported("Unsupported RPM payload compression")),
+    }
+}
+
+/// Saute une structure d'en-tête RPM et renvoie sa taille totale en octets
+fn skip_rpm_header<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut intro = [0u8; 16];
+    reader.read_exact(&mut intro)?;
+    if intro[..3] != [0x8e, 0xad, 0xe8] {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid RPM header magic"));
+    }
+    let index_count = u32::from_be_bytes([intro[8], intro[9], intro[10], intro[11]]) as u64;
+    let data_size = u32::from_be_bytes([intro[12], intro[13], intro[14], intro[15]]) as u64;
+    let len = index_count * 16 + data_size;
+    if io::copy(&mut reader.take(len), &mut io::sink())? != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated RPM header"));
+    }
+    Ok(16 + len)
+}
+
+/// Taille maximale d'un nom cpio, NUL final compris (PATH_MAX) : au-delà, l'en-tête est
+/// corrompu ou forgé pour faire allouer des gigaoctets
+const MAX_CPIO_NAME_SIZE: usize = 4096;
+
+/// Extraction d'un flux cpio au format « newc » (070701/070702)
+fn decompress_cpio<R: Read>(mut reader: R, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    let pb = extract_progress(ex, "cpio", None)?;
+
+    let mut extracted_count = 0;
+    let mut skipped_special = 0;
+    loop {
+        let mut header = [0u8; 110];
+        reader.read_exact(&mut header)?;
+        if &header[..6] != b"070701" && &header[..6] != b"070702" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported cpio header (only newc is supported)"));
+        }
+        let field = |i: usize| -> io::Result<u64> {
+            std::str::from_utf8(&header[6 + i * 8..14 + i * 8])
+                .ok()
+                .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid cpio header field"))
+        };
+        let mode = field(1)?;
+        let file_size = field(6)?;
+        let name_size = field(11)? as usize;
+        if name_size > MAX_CPIO_NAME_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Corrupt cpio header: name of {} bytes (at most {})", name_size, MAX_CPIO_NAME_SIZE),
+            ));
+        }
+
+        let mut name = vec![0u8; name_size];
+        reader.read_exact(&mut name)?;
+        let name_padding = (4 - (110 + name_size) % 4) % 4;
+        io::copy(&mut reader.by_ref().take(name_padding as u64), &mut io::sink())?;
+        let name = String::from_utf8_lossy(&name[..name_size.saturating_sub(1)]).into_owned();
+        if name == "TRAILER!!!" {
+            break;
+        }
+
+        let rel = name.trim_start_matches("./").trim_start_matches('/');
+        let outpath = ex.entry_path(output, Path::new(rel), mode & 0o170000 == 0o040000)?;
+        pb.set_message(format!("Extracting: {}", rel));
+        let mut data = reader.by_ref().take(file_size);
+        match (mode & 0o170000, outpath) {
+            (_, None) => {}
+            (0o040000, Some(outpath)) => {
+                let result = ex.create_dir(&outpath);
+                ex.entry_result(&outpath, result)?;
+            }
+            (0o100000, Some(outpath)) => {
+                let result = ex.create_parent(&outpath).and_then(|()| ex.write_file(&mut data, &outpath));
+                ex.entry_result(&outpath, result)?;
+            }
+            _ => skipped_special += 1,
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        let data_padding = (4 - file_size % 4) % 4;
+        io::copy(&mut reader.by_ref().take(data_padding), &mut io::sink())?;
+
+        extracted_count += 1;
+        pb.inc(1);
+    }
+
+    if extracted_count == 0 {
+        report_empty_archive(ex, &pb);
+        return Ok(());
+    }
+    pb.finish_with_message(format!("CPIO extraction done. Extracted {} files/directories.", extracted_count));
+    if skipped_special > 0 {
+        eprintln!("Warning: skipped {} symlink/special cpio entries", skipped_special);
+    }
+    Ok(())
+}
+
+fn decompress_tar_plain<R: Read>(reader: R, output: &Path, ex: &mut Extractor) -> io::Result<()> {
+    decompress_tar_entries(reader, output, ex, None)
+}
+
+/// Nombre d'entrées d'un tar non compressé, lu en sautant les données ; `None` si l'archive
+/// est illisible (l'extraction signalera l'erreur)
+fn tar_entry_count(path: &Path) -> Option<u64> {
+    let mut archive = Archive::new(File::open(path).ok()?);
+    let mut count = 0;
+    for entry in archive.entries_with_seek().ok()? {
+        entry.ok()?;
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Extraction des entrées d'un flux tar ; `total` (s'il est connu) donne une barre avec ETA
+fn decompress_tar_entries<R: Read>(reader: R, output: &Path, ex: &mut Extractor, total: Option<u64>) -> io::Result<()> {
+    let mut archive = Archive::new(reader);
+    let pb = extract_progress(ex, "tar", total)?;
+
+    let mut extracted_count = 0;
+    let wanted = ex.opts.extract_entry.as_deref();
+    for entry in archive.entries()? {
+        let mut file = entry?;
+        let path = file.path()?.into_owned();
+        if let Some(name) = wanted {
+            if entry_key(&path.to_string_lossy()) != entry_key(name) {
+                continue;
+            }
+            let kind = file.header().entry_type();
+            if kind.is_dir() || (ex.opts.writes_stdout() && !kind.is_file()) {
+                return Err(entry_not_a_file(name));
+            }
+            if ex.opts.writes_stdout() {
+                ex.extract_named(&mut file, output, name, None)?;
+            } else {
+                ex.extract_tar_entry(&mut file, output)?;
+            }
+            pb.finish_with_message(format!("Extracted {}", name));
+            return Ok(());
+        }
+        pb.set_message(format!("Extracting: {}", path.display()));
+        let result = ex.extract_tar_entry(&mut file, output);
+        ex.entry_result(&path, result)?;
+        extracted_count += 1;
+        pb.inc(1);
+    }
+    if let Some(name) = wanted {
+        return Err(entry_not_found(name));
+    }
+    ex.restore_dir_times()?;
+
+    if extracted_count == 0 {
+        report_empty_archive(ex, &pb);
+        return Ok(());
+    }
+    pb.finish_with_message("TAR extraction complete");
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_traversal(result: io::Result<PathBuf>) -> bool {
+        result.is_err_and(|e| is_path_refusal(&e) && e.kind() == io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn sanitize_entry_path_keeps_relative_paths_under_base() {
+        let base = Path::new("out");
+        assert_eq!(sanitize_entry_path(base, Path::new("a/./b.txt")).unwrap(), base.join("a/b.txt"));
+        assert_eq!(sanitize_entry_path(base, Path::new("a/../b.txt")).unwrap(), base.join("b.txt"));
+    }
+
+    #[test]
+    fn sanitize_entry_path_rejects_escapes() {
+        let base = Path::new("out");
+        assert!(is_traversal(sanitize_entry_path(base, Path::new("../evil"))));
+        assert!(is_traversal(sanitize_entry_path(base, Path::new("a/../../x"))));
+        assert!(is_traversal(sanitize_entry_path(base, Path::new("/etc/passwd"))));
+        #[cfg(windows)]
+        assert!(is_traversal(sanitize_entry_path(base, Path::new(r"C:\Windows\evil"))));
+    }
+
+    #[test]
+    fn zip_drive_prefixes_are_dropped_before_sanitizing() {
+        let base = Path::new("out");
+        assert_eq!(zip_entry_path(r"C:\dir\f.txt"), Path::new("dir/f.txt"));
+        assert_eq!(sanitize_entry_path(base, &zip_entry_path(r"C:\dir\f.txt")).unwrap(), base.join("dir/f.txt"));
+        assert!(is_traversal(sanitize_entry_path(base, &zip_entry_path(r"C:\..\evil"))));
+        assert!(is_traversal(sanitize_entry_path(base, &zip_entry_path(r"\\server\share\..\..\evil"))));
+    }
+
+    /// Lecteur qui échoue une fois avec `error` avant de rendre `data`
+    struct FlakyReader {
+        error: Option<io::ErrorKind>,
+        data: io::Cursor<Vec<u8>>,
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.error.take() {
+                Some(kind) => Err(io::Error::new(kind, "flaky")),
+                None => self.data.read(buf),
+            }
+        }
+    }
+
+    fn flaky(kind: io::ErrorKind) -> FlakyReader {
+        FlakyReader { error: Some(kind), data: io::Cursor::new(b"payload".to_vec()) }
+    }
+
+    #[test]
+    fn retry_reader_resumes_after_transient_errors() {
+        for kind in [io::ErrorKind::Interrupted, io::ErrorKind::TimedOut] {
+            // `read` et non `read_to_end`, qui relance lui-même les `Interrupted`
+            let mut reader = RetryReader { inner: flaky(kind), retries: 1 };
+            let mut buf = [0; 16];
+            assert_eq!(reader.read(&mut buf).unwrap(), 7);
+            assert_eq!(&buf[..7], b"payload");
+        }
+    }
+
+    #[test]
+    fn retry_io_gives_up_without_retries_or_on_lasting_errors() {
+        let mut reader = flaky(io::ErrorKind::TimedOut);
+        let e = retry_io(0, || reader.read(&mut [0; 16])).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+
+        let mut reader = flaky(io::ErrorKind::PermissionDenied);
+        let e = retry_io(3, || reader.read(&mut [0; 16])).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(reader.read(&mut [0; 16]).unwrap(), 7);
+    }
+
+    #[test]
+    fn retry_io_retries_interrupted_reads_without_using_the_budget() {
+        let mut reader = flaky(io::ErrorKind::Interrupted);
+        assert_eq!(retry_io(0, || reader.read(&mut [0; 16])).unwrap(), 7);
+    }
+
+    #[test]
+    fn bytes_round_trip_through_every_single_stream_format() {
+        // xorshift : incompressible, avec une moitié répétitive pour que chaque codec travaille
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut data: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        data.extend(b"sharky ".repeat(8 * 1024));
+
+        let formats: Vec<Format> = codecs().iter().filter(|c| c.is_single_stream()).map(|c| c.format()).collect();
+        assert!(formats.len() >= 6);
+        for format in formats {
+            match compress_bytes(&data, format, 6) {
+                Ok(packed) => assert_eq!(decompress_bytes(&packed).unwrap(), data, "{}", format.name()),
+                // lzma et lzfse se lisent mais compress_bytes ne les écrit pas
+                Err(e) => {
+                    assert!(matches!(format, Format::Lzma | Format::Lzfse), "{}: {}", format.name(), e);
+                    assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+                }
+            }
+            let empty = compress_bytes(b"", format, 6);
+            if let Ok(packed) = empty {
+                assert_eq!(decompress_bytes(&packed).unwrap(), b"", "{}", format.name());
+            }
+        }
+    }
+
+    #[test]
+    fn rar_volumes_follow_part_numbering() {
+        let volumes = RarVolumes::parse(Path::new("dl/set.part02.rar")).unwrap();
+        assert_eq!(volumes.index, 1);
+        assert_eq!(volumes.path(0), Path::new("dl/set.part01.rar"));
+        assert_eq!(volumes.path(9), Path::new("dl/set.part10.rar"));
+        assert_eq!(RarVolumes::parse(Path::new("set.part1.rar")).unwrap().path(1), Path::new("set.part2.rar"));
+        assert!(RarVolumes::parse(Path::new("set.part0.rar")).is_none());
+    }
+
+    #[test]
+    fn rar_volumes_follow_old_style_numbering() {
+        let first = RarVolumes::parse(Path::new("dl/set.rar")).unwrap();
+        assert_eq!(first.index, 0);
+        assert_eq!(first.path(1), Path::new("dl/set.r00"));
+        let third = RarVolumes::parse(Path::new("dl/set.r01")).unwrap();
+        assert_eq!(third.index, 2);
+        assert_eq!(third.path(0), Path::new("dl/set.rar"));
+        assert!(RarVolumes::parse(Path::new("dl/set.zip")).is_none());
+    }
+
+    #[test]
+    fn rar_volumes_name_the_first_missing_volume() {
+        let dir = std::env::temp_dir().join(format!("sharky-rar-volumes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.part1.rar", "a.part2.rar", "a.part4.rar", "b.rar", "b.r00"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let parts = RarVolumes::parse(&dir.join("a.part1.rar")).unwrap();
+        assert_eq!(parts.present(), 2);
+        assert_eq!(parts.first_missing(), dir.join("a.part3.rar"));
+        let old = RarVolumes::parse(&dir.join("b.r00")).unwrap();
+        assert_eq!(old.first_missing(), dir.join("b.r01"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,50 +1,19 @@
-use std::{
-    fs::{self, File},
-    io::{self, BufReader, BufWriter, Read, Write, Seek, SeekFrom},
-    path::PathBuf,
-    time::{Duration, Instant},
+use std::{io, path::{Component, Path, PathBuf}, time::Instant};
+
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use sharky::{
+    ArchiveTime, CompressOptions, DecompressOptions, ExistingFiles, LineEnding, STORE_EXTENSIONS, VerifyMode, ZipNameEncoding, batch_extract,
+    compress, decompress,
+    SharkyError, compare_levels, list_formats, print_histogram, print_largest, print_listing, probe_file, read_entries, report_skipped, train_dict,
 };
 
-use clap::{CommandFactory, Parser};
-use indicatif::{ProgressBar, ProgressStyle};
-use walkdir::WalkDir;
-
-use bzip2::read::BzDecoder;
-use flate2::read::GzDecoder;
-use tar::{Archive, Builder};
-use xz2::read::XzDecoder;
-use xz2::write::XzEncoder;
-use zip::ZipArchive;
-use zstd::stream::read::Decoder as ZstdDecoder;
-use zstd::stream::write::Encoder as ZstdEncoder;
-use unrar::Archive as UnrarArchive;
-use sevenz_rust::SevenZReader;
-use lzma_rs::lzma_decompress;
-use brotli::Decompressor as BrotliDecoder;
-
-// Structures pour le support ISO
-struct IsoDirectory {
-    name: String,
-    entries: Vec<IsoEntry>,
-}
-
-struct IsoFile {
-    name: String,
-    size: u32,
-    location: u32,
-}
-
-enum IsoEntry {
-    Directory(IsoDirectory),
-    File(IsoFile),
-}
-
 /// Outil de compression/décompression multi-format
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     author,
     version,
-    about = "Outil de compression/décompression supportant ZIP, RAR, 7Z, ISO, TAR, GZ, BZ2, XZ, ZSTD, LZMA, BROTLI"
+    about = "Outil de compression/décompression supportant ZIP, RAR, 7Z, ISO, TAR, GZ, BZ2, XZ, ZSTD, LZMA, BROTLI, LZFSE, DEB, RPM"
 )]
 struct Args {
     #[arg(short = 'c', long = "compress", conflicts_with = "decompress")]
@@ -53,754 +22,621 @@ struct Args {
     #[arg(short = 'd', long = "decompress", conflicts_with = "compress")]
     decompress: bool,
 
-    #[arg(short, long, value_name = "PATH")]
-    input: PathBuf,
+    #[arg(short, long, value_name = "PATH", required_unless_present_any = ["probe", "list_formats"])]
+    input: Option<PathBuf>,
+
+    #[arg(short, long, value_name = "PATH", required_unless_present_any = ["probe", "list_formats", "list", "histogram", "largest", "train_dict", "test", "compare_levels"])]
+    output: Option<PathBuf>,
+
+    /// Identifier le format d'un fichier et afficher ses métadonnées, sans extraire
+    #[arg(long = "probe", value_name = "FILE", conflicts_with_all = ["compress", "decompress"])]
+    probe: Option<PathBuf>,
+
+    /// Extraire chaque archive du répertoire --input dans son propre sous-répertoire de --output
+    #[arg(long = "batch-extract", conflicts_with_all = ["compress", "decompress"])]
+    batch_extract: bool,
 
-    #[arg(short, long, value_name = "PATH")]
-    output: PathBuf,
+    /// Lister le contenu de l'archive --input (chemin, taille, date) sans extraire
+    #[arg(short = 'l', long = "list", conflicts_with_all = ["compress", "decompress"])]
+    list: bool,
+
+    /// Afficher l'histogramme des tailles d'entrées de l'archive --input, sans extraire
+    #[arg(long = "histogram", alias = "entry-size-histogram", conflicts_with_all = ["compress", "decompress"])]
+    histogram: bool,
+
+    /// Afficher les N plus grosses entrées de l'archive --input, sans extraire
+    #[arg(long = "largest", alias = "list-largest", value_name = "N", conflicts_with_all = ["compress", "decompress"])]
+    largest: Option<usize>,
+
+    /// Lister les formats pris en charge (lecture / écriture)
+    #[arg(long = "list-formats", conflicts_with_all = ["compress", "decompress", "probe"])]
+    list_formats: bool,
+
+    /// Niveau de compression commun (0–9), ramené sur l'échelle de chaque codec :
+    /// zstd 1–19 (2n+1), xz et deflate 0–9, bzip2 1–9, brotli 0–11.
+    /// `--zstd-level`, `--xz-preset` et `--brotli-quality` l'emportent s'ils sont donnés.
+    #[arg(long = "level", value_name = "N", requires = "compress")]
+    level: Option<u32>,
 
     /// Niveau Zstd (0–22)
     #[arg(short = 'z', long = "zstd-level", default_value_t = 19)]
     zstd_level: i32,
 
-    /// Niveau XZ preset (0–9)
+    /// Correspondances longue distance zstd, fenêtre en log2 d'octets (10–31, 27 par défaut).
+    /// La décompression réserve une fenêtre de cette taille (log 27 : 128 Mio, 31 : 2 Gio) ;
+    /// `zstd -d` demande `--long=N` au-delà de 27.
+    #[arg(long = "zstd-long", value_name = "LOG", num_args = 0..=1, default_missing_value = "27")]
+    zstd_long: Option<u32>,
+
+    /// Niveau XZ preset (0–9), aussi niveau des sorties bz2 et tar.bz2 (1–9)
     #[arg(short = 'x', long = "xz-preset", default_value_t = 9)]
     xz_preset: u32,
 
+    /// Qualité Brotli (0–11)
+    #[arg(long = "brotli-quality", value_name = "Q", default_value_t = 11)]
+    brotli_quality: u32,
+
+    /// Fenêtre Brotli, en log2 d'octets (10–24)
+    #[arg(long = "brotli-window", value_name = "LGWIN", default_value_t = 22)]
+    brotli_window: u32,
+
+    /// Format sharky : abandonner la couche xz si, sur un échantillon de l'entrée, elle ne réduit pas la taille
+    #[arg(long = "smart-pipeline")]
+    smart_pipeline: bool,
+
+    /// Threads des encodeurs zstd et xz, ou d'extraction zip avec -d (0 = autant que de cœurs)
+    #[arg(long = "threads", value_name = "N", default_value_t = 1)]
+    threads: u32,
+
     /// Fichier dictionnaire Zstd (optionnel)
     #[arg(long = "dict", value_name = "FILE")]
     dict: Option<PathBuf>,
 
-    /// Motifs d'exclusion
+    /// Entraîner un dictionnaire zstd sur les fichiers de --input (hors exclusions) et l'écrire
+    /// dans FILE, à passer ensuite à --dict. Il faut au moins 5 fichiers, idéalement des
+    /// centaines de petits fichiers semblables totalisant ~100 fois --dict-size
+    #[arg(long = "train-dict", value_name = "FILE", conflicts_with_all = ["compress", "decompress", "output", "dict"])]
+    train_dict: Option<PathBuf>,
+
+    /// Taille maximale du dictionnaire entraîné (suffixes k, M acceptés)
+    #[arg(long = "dict-size", value_name = "SIZE", default_value = "110k", value_parser = parse_dict_size, requires = "train_dict")]
+    dict_size: usize,
+
+    /// Compresser --input à plusieurs niveaux du codec de sortie (`--format` ou extension de
+    /// -o ; preset xz pour le format sharky) et afficher taille, ratio et durée de chacun, sans
+    /// rien écrire. Sans liste, une sélection de niveaux du codec (zstd 1,3,9,15,19,22)
+    #[arg(long = "compare-levels", value_name = "LEVEL,...", value_delimiter = ',', num_args = 0..=1,
+        requires = "compress", conflicts_with_all = ["resume", "append", "dry_run"])]
+    compare_levels: Option<Vec<u32>>,
+
+    /// Motifs glob d'exclusion (`*.log`, `build/**`) : sans `/`, comparés au nom de chaque
+    /// fichier ou répertoire ; avec `/`, au chemin relatif à l'entrée (ou dans l'archive avec -d)
     #[arg(long = "exclude", value_name = "PATTERN")]
     exclude: Vec<String>,
 
-    /// Taille du tampon en octets
-    #[arg(long = "buffer-size", default_value_t = 4 * 1024 * 1024)]
+    /// Extraction : n'extraire que les entrées correspondant à l'un de ces motifs (même syntaxe
+    /// que --exclude, qui reste prioritaire). Les répertoires vides ne sont alors pas recréés.
+    #[arg(long = "include", value_name = "PATTERN", conflicts_with = "compress")]
+    include: Vec<String>,
+
+    /// Extraction (zip, 7z, tar) : n'extraire que cette entrée, par son chemin dans l'archive ;
+    /// avec `-o -`, son contenu part sur la sortie standard
+    #[arg(long = "extract-entry", value_name = "PATH", conflicts_with_all = ["compress", "batch_extract", "include"])]
+    extract_entry: Option<String>,
+
+    /// Fichier de motifs au format gitignore (ancrés à la racine de l'entrée). S'ajoute à
+    /// --exclude et --exclude-backups : ses motifs `!` ne rattrapent pas leurs exclusions
+    #[arg(long = "ignore-file", value_name = "FILE", requires = "compress")]
+    ignore_file: Option<PathBuf>,
+
+    /// Compression : écarter les fichiers de plus de SIZE octets (suffixes k, M, G acceptés)
+    #[arg(long = "exclude-larger-than", value_name = "SIZE", value_parser = parse_file_size, requires = "compress")]
+    exclude_larger_than: Option<u64>,
+
+    /// Compression : écarter les fichiers de moins de SIZE octets (suffixes k, M, G acceptés)
+    #[arg(long = "exclude-smaller-than", value_name = "SIZE", value_parser = parse_file_size, requires = "compress")]
+    exclude_smaller_than: Option<u64>,
+
+    /// Exclure les fichiers de sauvegarde/temporaires d'éditeurs (*~, *.bak, *.swp, ...)
+    #[arg(long = "exclude-backups")]
+    exclude_backups: bool,
+
+    /// Profondeur maximale de parcours, comptée depuis la racine de l'entrée (1 = enfants directs)
+    #[arg(long = "max-depth", value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Archiver le contenu du répertoire sans le dossier racine (équivalent à `--base-dir .`)
+    #[arg(long = "no-wrap")]
+    no_wrap: bool,
+
+    /// Nom du dossier racine dans l'archive (`release` pour archiver `./build` sous `release/`),
+    /// ou nom d'entrée d'un fichier seul ; chemin relatif sans `..`
+    #[arg(long = "archive-root", value_name = "NAME", requires = "compress", conflicts_with = "no_wrap")]
+    archive_root: Option<PathBuf>,
+
+    /// Méthode 7z utilisée à la création d'une archive .7z (lzma2, lzma, ppmd, bcj+lzma2)
+    #[arg(long = "7z-method", value_name = "METHOD", default_value = "lzma2")]
+    sevenz_method: String,
+
+    /// Ignorer (avec un avertissement) les archives dont le format n'est pas pris en charge
+    #[arg(long = "continue-on-unsupported")]
+    continue_on_unsupported: bool,
+
+    /// Regrouper les fichiers similaires (extension puis taille) dans le flux tar pour un meilleur ratio.
+    /// Nécessite de parcourir toute l'arborescence avant de commencer l'écriture.
+    #[arg(long = "similarity-sort")]
+    similarity_sort: bool,
+
+    /// Format de l'entrée (ex. `tar.gz`), indispensable pour certains flux lus sur stdin (`-i -`).
+    /// Prioritaire sur l'extension et la détection par signature.
+    #[arg(long = "input-format-hint", value_name = "FORMAT")]
+    input_format_hint: Option<String>,
+
+    /// Ne garder la sortie que si elle est plus petite que l'entrée (sinon elle est supprimée) ;
+    /// en zip, chaque fichier que le deflate ne réduit pas est stocké sans compression
+    #[arg(long = "compress-if-smaller")]
+    compress_if_smaller: bool,
+
+    /// Convertir les fins de ligne des fichiers texte extraits (hors RAR)
+    #[arg(long = "normalize-line-endings", value_name = "MODE", value_enum)]
+    normalize_line_endings: Option<LineEnding>,
+
+    /// Format de sortie pour -c, sinon déduit de l'extension de sortie (sharky si elle est inconnue) :
+    /// sharky, tar, tar.gz, tar.bz2, tar.xz, tar.zst, 7z, lzfse, tar.lzfse, lz4, tar.lz4, br, tar.br, gz, bz2, xz, zst (fichier seul)
+    #[arg(long = "format", value_name = "FORMAT")]
+    format: Option<String>,
+
+    /// Journal JSON lines : une ligne par entrée compressée ou extraite
+    #[arg(long = "log-file", value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Stocker les longues plages de zéros comme trous (entrées tar GNU sparse)
+    #[arg(long = "detect-sparse", alias = "rezero-sparse")]
+    detect_sparse: bool,
+
+    /// Taille minimale d'une plage de zéros pour --detect-sparse (octets, arrondie à 512)
+    #[arg(long = "sparse-threshold", value_name = "BYTES", default_value_t = 4096)]
+    sparse_threshold: u64,
+
+    /// Horodatage unique de toutes les entrées, pris sur le fichier source le plus récent ou le plus ancien
+    #[arg(long = "archive-time", value_name = "WHICH", value_enum)]
+    archive_time: Option<ArchiveTime>,
+
+    /// ISO : extraire toutes les versions d'un fichier (`NOM;1`, `NOM;2`) au lieu de la plus récente
+    #[arg(long = "iso-all-versions")]
+    iso_all_versions: bool,
+
+    /// Données après la fin d'un flux compressé mono-fichier : erreur au lieu d'un avertissement
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Extraire tous les fichiers à plat, les séparateurs de chemin remplacés par DELIM (`a/b/c.txt` → `a_b_c.txt`)
+    #[arg(long = "flatten-prefix", value_name = "DELIM", num_args = 0..=1, default_missing_value = "_")]
+    flatten_prefix: Option<String>,
+
+    /// Faire passer les fichiers d'extension EXT par COMMANDE (stdin → stdout) avant de les
+    /// archiver (sorties tar). Exécute des programmes externes : commandes de confiance uniquement
+    #[arg(long = "file-transform", value_name = "EXT:COMMAND")]
+    file_transform: Vec<String>,
+
+    /// Tar : SHA-256 de chaque fichier dans un en-tête PAX, vérifié à l'extraction
+    #[arg(long = "embed-checksums", requires = "compress")]
+    embed_checksums: bool,
+
+    /// Tar : manifeste des entrées archivées, une ligne `sha256  taille  chemin` par entrée
+    #[arg(long = "manifest", value_name = "FILE", requires = "compress")]
+    manifest: Option<PathBuf>,
+
+    /// Bilan JSON de la compression ou de l'extraction (octets d'entrée et de sortie, ratio,
+    /// durée, nombre d'entrées, format, niveau) écrit dans PATH, `-` pour la sortie standard
+    #[arg(long = "compression-stats-json", alias = "stats-json", value_name = "PATH",
+        conflicts_with_all = ["list", "histogram", "largest", "train_dict", "batch_extract", "probe", "list_formats", "compare_levels"])]
+    stats_json: Option<PathBuf>,
+
+    /// Tar : ne pas restaurer les permissions et dates des fichiers extraits
+    #[arg(long = "no-preserve-permissions")]
+    no_preserve_permissions: bool,
+
+    /// Mot de passe des archives chiffrées (zip ZipCrypto ou AES, 7z AES) ; avec -c, chiffre
+    /// une sortie 7z en AES-256
+    #[arg(long = "password", value_name = "PASSWORD")]
+    password: Option<String>,
+
+    /// Relire chaque fichier extrait et comparer sa somme (crc32 par défaut) à celle de l'archive ;
+    /// `sha256` affiche en plus un manifeste `chemin  somme`
+    #[arg(long = "verify", value_name = "ALGO", value_enum, num_args = 0..=1, default_missing_value = "crc32",
+        conflicts_with_all = ["compress", "dry_run", "normalize_line_endings"])]
+    verify: Option<VerifyMode>,
+
+    /// Retirer les N premiers composants du chemin des entrées extraites (entrées plus courtes ignorées)
+    #[arg(long = "strip-components", value_name = "N", default_value_t = 0, conflicts_with = "compress")]
+    strip_components: usize,
+
+    /// Simulation : affiche les fichiers et répertoires qui seraient créés, sans rien écrire
+    #[arg(long = "dry-run", conflicts_with = "compress")]
+    dry_run: bool,
+
+    /// Tester l'archive --input : décoder toutes les entrées (CRC zip, fin de flux) sans rien
+    /// écrire, puis afficher OK ou la première corruption (code de sortie non nul)
+    #[arg(short = 't', long = "test", conflicts_with_all = ["compress", "output", "dry_run", "verify", "no_clobber", "backup"])]
+    test: bool,
+
+    /// Extraction : remplacer les fichiers déjà présents (comportement par défaut)
+    #[arg(long = "overwrite", conflicts_with_all = ["compress", "no_clobber", "backup"])]
+    overwrite: bool,
+
+    /// Extraction : garder les fichiers déjà présents et passer les entrées correspondantes
+    #[arg(long = "no-clobber", conflicts_with_all = ["compress", "backup"])]
+    no_clobber: bool,
+
+    /// Extraction : renommer les fichiers déjà présents en `nom~` avant d'écrire
+    #[arg(long = "backup", conflicts_with = "compress")]
+    backup: bool,
+
+    /// Extraction : une entrée impossible à écrire est signalée puis sautée ; bilan et code
+    /// de sortie non nul à la fin
+    #[arg(long = "keep-going", conflicts_with = "compress")]
+    keep_going: bool,
+
+    /// Encodage des noms d'entrées des zip créés : utf8 (drapeau UTF-8) ou cp437 (anciens outils Windows)
+    #[arg(long = "zip-name-encoding", value_name = "ENCODING", value_enum, default_value_t = ZipNameEncoding::Utf8)]
+    zip_name_encoding: ZipNameEncoding,
+
+    /// Zip : stocker sans compression les fichiers de ces extensions (`jpg,mp4,zip`), déjà
+    /// compressés ; sans liste, images, audio, vidéo et archives courants. Sans effet sur les
+    /// sorties tar, compressées d'un seul tenant
+    #[arg(long = "store-extensions", value_name = "EXT,...", value_delimiter = ',', num_args = 0..=1, requires = "compress")]
+    store_extensions: Option<Vec<String>>,
+
+    /// Compression reprenable (tar, tar.zst) : points de contrôle dans `<sortie>.checkpoint`,
+    /// reprise depuis le dernier si le fichier existe
+    #[arg(long = "resume", requires = "compress", conflicts_with = "manifest")]
+    resume: bool,
+
+    /// Ajouter les entrées à une archive tar existante (tar en place, tar compressés réécrits)
+    #[arg(long = "append", requires = "compress", conflicts_with_all = ["resume", "force", "compress_if_smaller"])]
+    append: bool,
+
+    /// Continuer en signalant les fichiers supprimés ou illisibles pendant la compression
+    #[arg(long = "skip-errors", requires = "compress", conflicts_with = "resume")]
+    skip_errors: bool,
+
+    /// Nouvelles tentatives, avec une attente croissante, après une erreur d'E/S passagère
+    /// (délai dépassé, appel interrompu) à l'ouverture ou la lecture d'un fichier source
+    /// ou d'une entrée extraite. Fichier absent ou accès refusé : échec immédiat
+    #[arg(long = "io-retries", value_name = "N", default_value_t = 3)]
+    io_retries: u32,
+
+    /// Archiver le contenu pointé par les liens symboliques (répertoires liés parcourus, boucles
+    /// sautées) au lieu des liens eux-mêmes ; zip et 7z suivent toujours les liens
+    #[arg(long = "dereference", requires = "compress")]
+    dereference: bool,
+
+    /// Compression : remplacer l'archive de sortie si elle existe déjà.
+    /// Extraction : accepter un répertoire de sortie qui contient l'archive
+    #[arg(short = 'f', long = "force", alias = "replace-existing-archive")]
+    force: bool,
+
+    /// Affichage détaillé : chaque entrée archivée ou extraite sur sa ligne, sans barre de progression
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Silencieux : seulement les erreurs, avertissements et le bilan final (sur stderr)
+    #[arg(short = 'q', long = "quiet", conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Taille du tampon, en octets ou avec un suffixe binaire (`512k`, `16M`, `1G`) ; de 4 Kio à 1 Gio
+    #[arg(long = "buffer-size", value_name = "SIZE", default_value = "4M", value_parser = parse_buffer_size)]
     buffer_size: usize,
+
+    /// Options de niveau de codec passées explicitement sur la ligne de commande
+    #[arg(skip)]
+    codec_flags_given: Vec<&'static str>,
+}
+
+/// Options de niveau propres à un codec : (identifiant clap, option affichée)
+const CODEC_FLAGS: [(&str, &str); 10] = [
+    ("level", "--level"),
+    ("zstd_level", "--zstd-level"),
+    ("zstd_long", "--zstd-long"),
+    ("threads", "--threads"),
+    ("xz_preset", "--xz-preset"),
+    ("brotli_quality", "--brotli-quality"),
+    ("brotli_window", "--brotli-window"),
+    ("dict", "--dict"),
+    ("sevenz_method", "--7z-method"),
+    ("store_extensions", "--store-extensions"),
+];
+
+impl Args {
+    /// Chemin d'entrée ; toujours présent hors `--probe` (garanti par clap)
+    fn input(&self) -> &Path {
+        self.input.as_deref().expect("--input is required")
+    }
+
+    fn compress_options(&self) -> CompressOptions {
+        CompressOptions {
+            input: self.input().to_path_buf(),
+            // `--train-dict` écrit son dictionnaire ailleurs que dans --output
+            output: self.output.clone().unwrap_or_default(),
+            format: self.format.clone(),
+            level: self.level,
+            zstd_level: self.zstd_level,
+            xz_preset: self.xz_preset,
+            brotli_quality: self.brotli_quality,
+            brotli_window: self.brotli_window,
+            smart_pipeline: self.smart_pipeline,
+            threads: self.threads,
+            zstd_long: self.zstd_long,
+            dict: self.dict.clone(),
+            exclude: self.exclude.clone(),
+            exclude_backups: self.exclude_backups,
+            ignore_file: self.ignore_file.clone(),
+            exclude_larger_than: self.exclude_larger_than,
+            exclude_smaller_than: self.exclude_smaller_than,
+            max_depth: self.max_depth,
+            no_wrap: self.no_wrap,
+            archive_root: self.archive_root.as_ref().map(|root| root.components().collect()),
+            sevenz_method: self.sevenz_method.clone(),
+            password: self.password.clone(),
+            similarity_sort: self.similarity_sort,
+            compress_if_smaller: self.compress_if_smaller,
+            log_file: self.log_file.clone(),
+            detect_sparse: self.detect_sparse,
+            sparse_threshold: self.sparse_threshold,
+            archive_time: self.archive_time,
+            file_transform: self.file_transform.clone(),
+            embed_checksums: self.embed_checksums,
+            manifest: self.manifest.clone(),
+            stats_json: self.stats_json.clone(),
+            zip_name_encoding: self.zip_name_encoding,
+            store_extensions: match &self.store_extensions {
+                Some(list) if list.is_empty() => STORE_EXTENSIONS.iter().map(ToString::to_string).collect(),
+                Some(list) => list.iter().map(|ext| ext.trim().trim_start_matches('.').to_string()).collect(),
+                None => Vec::new(),
+            },
+            resume: self.resume,
+            append: self.append,
+            skip_errors: self.skip_errors,
+            io_retries: self.io_retries,
+            dereference: self.dereference,
+            force: self.force,
+            quiet: self.quiet,
+            verbose: self.verbose,
+            buffer_size: self.buffer_size,
+            codec_flags_given: self.codec_flags_given.clone(),
+        }
+    }
+
+    /// `--list` et `--histogram` n'ont pas de répertoire de sortie
+    fn decompress_options(&self) -> DecompressOptions {
+        DecompressOptions {
+            input: self.input().to_path_buf(),
+            output: self.output.clone().unwrap_or_default(),
+            input_format_hint: self.input_format_hint.clone(),
+            continue_on_unsupported: self.continue_on_unsupported,
+            normalize_line_endings: self.normalize_line_endings,
+            log_file: self.log_file.clone(),
+            iso_all_versions: self.iso_all_versions,
+            strict: self.strict,
+            flatten_prefix: self.flatten_prefix.clone(),
+            no_preserve_permissions: self.no_preserve_permissions,
+            password: self.password.clone(),
+            dry_run: self.dry_run,
+            test: self.test,
+            existing: if self.no_clobber {
+                ExistingFiles::NoClobber
+            } else if self.backup {
+                ExistingFiles::Backup
+            } else {
+                ExistingFiles::Overwrite
+            },
+            keep_going: self.keep_going,
+            io_retries: self.io_retries,
+            stats_json: self.stats_json.clone(),
+            threads: self.threads,
+            strip_components: self.strip_components,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            force: self.force,
+            extract_entry: self.extract_entry.clone(),
+            verify: self.verify,
+            quiet: self.quiet,
+            verbose: self.verbose,
+            buffer_size: self.buffer_size,
+        }
+    }
 }
 
 fn main() -> io::Result<()> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    args.codec_flags_given = CODEC_FLAGS
+        .iter()
+        .filter(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .map(|&(_, flag)| flag)
+        .collect();
+    if let Some(level) = args.level && level > 9 {
+        eprintln!("Level must be between 0 and 9");
+        std::process::exit(1);
+    }
     if args.compress && !(0..=22).contains(&args.zstd_level) {
         eprintln!("Zstd level must be between 0 and 22");
         std::process::exit(1);
     }
+    if let Some(window_log) = args.zstd_long && !(10..=31).contains(&window_log) {
+        eprintln!("Zstd long-distance window log must be between 10 and 31");
+        std::process::exit(1);
+    }
     if args.compress && !(0..=9).contains(&args.xz_preset) {
         eprintln!("XZ preset must be between 0 and 9");
         std::process::exit(1);
     }
-
-    let start = Instant::now();
-    let res = if args.compress {
-        compress_path(&args)
-    } else if args.decompress {
-        decompress_path(&args)
-    } else {
-        let mut cmd = Args::command();
-        cmd.print_help()?;
-        return Ok(());
-    };
-    res.map_err(|e| { eprintln!("Error: {}", e); e })?;
-
-    println!("Total time: {:.2?}", start.elapsed());
-    Ok(())
-}
-
-fn compress_path(args: &Args) -> io::Result<()> {
-    println!("© 2025, Matheo Simard");
-    println!(
-        "Compression: {:?} → {:?} (XZ preset {}, Zstd lvl {})",
-        args.input, args.output, args.xz_preset, args.zstd_level
-    );
-
-    let outfile = BufWriter::with_capacity(args.buffer_size, File::create(&args.output)?);
-    let mut zstd_encoder = if let Some(dic) = &args.dict {
-        let dict_data = fs::read(dic)?;
-        ZstdEncoder::with_dictionary(outfile, args.zstd_level, &dict_data)?
-    } else {
-        ZstdEncoder::new(outfile, args.zstd_level)?
-    };
-
-    let mut xz_encoder = XzEncoder::new(&mut zstd_encoder, args.xz_preset);
-    {
-        let mut tar_builder = Builder::new(&mut xz_encoder);
-        let pb = build_progress(&args.input)?;
-        traverse_and_append(&args.input, &mut tar_builder, &pb, &args.exclude)?;
-        pb.finish_and_clear();
+    if args.compress && !(0..=11).contains(&args.brotli_quality) {
+        eprintln!("Brotli quality must be between 0 and 11");
+        std::process::exit(1);
     }
-    xz_encoder.finish()?;
-    zstd_encoder.finish()?;
-
-    let size = fs::metadata(&args.output)?.len();
-    println!("Output size: {} bytes", size);
-    Ok(())
-}
-
-fn decompress_path(args: &Args) -> io::Result<()> {
-    println!("© 2025, Matheo Simard");
-    println!("Decompressing {:?} → {:?}", args.input, args.output);
-    fs::create_dir_all(&args.output)?;
-
-    let input_path_str = args.input.to_string_lossy();
-    let ext = args.input.extension().and_then(|s| s.to_str()).unwrap_or("");
-
-    match ext.to_lowercase().as_str() {
-        "zip" => decompress_zip(&args.input, &args.output, args.buffer_size),
-        "rar" => decompress_rar(&args.input, &args.output),
-        "7z" => decompress_7z(&args.input, &args.output),
-        "iso" => decompress_iso(&args.input, &args.output, args.buffer_size),
-        "tar" => decompress_tar_plain(File::open(&args.input)?, &args.output),
-        "gz" => {
-            if input_path_str.ends_with(".tar.gz") {
-                let f = File::open(&args.input)?;
-                let gz = GzDecoder::new(f);
-                decompress_tar_plain(gz, &args.output)
-            } else {
-                decompress_single_file_gz(&args.input, &args.output)
-            }
-        },
-        "tgz" => {
-            let f = File::open(&args.input)?;
-            let gz = GzDecoder::new(f);
-            decompress_tar_plain(gz, &args.output)
-        },
-        "bz2" => {
-            if input_path_str.ends_with(".tar.bz2") {
-                let f = File::open(&args.input)?;
-                let bz = BzDecoder::new(f);
-                decompress_tar_plain(bz, &args.output)
-            } else {
-                decompress_single_file_bz2(&args.input, &args.output)
-            }
-        },
-        "xz" => {
-            if input_path_str.ends_with(".tar.xz") {
-                let f = File::open(&args.input)?;
-                let xz = XzDecoder::new(f);
-                decompress_tar_plain(xz, &args.output)
-            } else {
-                decompress_single_file_xz(&args.input, &args.output)
-            }
-        },
-        "zst" | "zstd" => {
-            if input_path_str.ends_with(".tar.zst") || input_path_str.ends_with(".tar.zstd") {
-                let f = File::open(&args.input)?;
-                let zstd = ZstdDecoder::new(f)?;
-                decompress_tar_plain(zstd, &args.output)
-            } else {
-                decompress_single_file_zstd(&args.input, &args.output)
-            }
-        },
-        "lzma" => decompress_single_file_lzma(&args.input, &args.output),
-        "br" => decompress_single_file_brotli(&args.input, &args.output),
-        "lz4" => decompress_single_file_lz4(&args.input, &args.output),
-        "cab" => decompress_cab(&args.input, &args.output),
-        _ => {
-            let infile_count = BufReader::with_capacity(args.buffer_size, File::open(&args.input)?);
-            let zstd_count = ZstdDecoder::new(infile_count)?;
-            let xz_count = XzDecoder::new(zstd_count);
-            let mut archive_count = Archive::new(xz_count);
-
-            let entry_count = archive_count.entries()?.count();
-            let pb = ProgressBar::new(entry_count as u64);
-            pb.set_style(
-                ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len}")
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-                    .progress_chars("#>-"),
-            );
-
-            let infile_decompress = BufReader::with_capacity(args.buffer_size, File::open(&args.input)?);
-            let zstd_decompress = ZstdDecoder::new(infile_decompress)?;
-            let xz_decompress = XzDecoder::new(zstd_decompress);
-            let mut archive_decompress = Archive::new(xz_decompress);
-
-            for file in archive_decompress.entries()? {
-                let mut file = file?;
-                let path = file.path()?.to_path_buf();
-                let outpath = args.output.join(path);
-
-                if file.header().entry_type().is_dir() {
-                    fs::create_dir_all(&outpath)?;
-                } else {
-                    if let Some(parent) = outpath.parent() {
-                        fs::create_dir_all(parent)?;
-                    }
-                    let mut outfile = File::create(&outpath)?;
-                    io::copy(&mut file, &mut outfile)?;
-                }
-                pb.inc(1);
-            }
-            pb.finish_with_message("Decompression done");
-            Ok(())
-        }
+    if args.compress && !(10..=24).contains(&args.brotli_window) {
+        eprintln!("Brotli window must be between 10 and 24");
+        std::process::exit(1);
     }
-}
 
-fn decompress_zip(input: &PathBuf, output: &PathBuf, _bufsize: usize) -> io::Result<()> {
-    let f = File::open(input)?;
-    let mut archive = ZipArchive::new(f)?;
-    let pb = ProgressBar::new(archive.len() as u64);
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-            .progress_chars("#>-"),
-    );
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let outpath = output.join(file.name());
-        if file.is_dir() {
-            fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                fs::create_dir_all(p)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
-        }
-        pb.inc(1);
+    if let (Some(min), Some(max)) = (args.exclude_smaller_than, args.exclude_larger_than) && min > max {
+        eprintln!("--exclude-smaller-than ({} bytes) must not exceed --exclude-larger-than ({} bytes)", min, max);
+        std::process::exit(1);
     }
-    pb.finish_with_message("Zip decompression done.");
-    Ok(())
-}
 
-fn decompress_rar(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    println!("Attempting RAR decompression (requires external unrar library)...");
-
-    let mut archive = UnrarArchive::new(input.as_path())
-        .open_for_processing()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open RAR archive: {}", e)))?;
-
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-
-    let mut extracted_count = 0;
-
-    loop {
-        let current_filename_display;
-
-        let next_archive_state = {
-            match archive.read_header() {
-                Ok(Some(open_archive_with_entry)) => {
-                    let entry = open_archive_with_entry.entry();
-                    let entry_path = output.join(&entry.filename);
-                    current_filename_display = entry.filename.display().to_string();
-
-                    if entry.is_directory() {
-                        fs::create_dir_all(&entry_path)?;
-                        open_archive_with_entry.skip()
-                            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to skip RAR directory entry: {}", e)))?
-                    } else {
-                        if let Some(parent) = entry_path.parent() {
-                            fs::create_dir_all(parent)?;
-                        }
-                        open_archive_with_entry.extract_to(&entry_path)
-                            .map_err(|e| {
-                                io::Error::new(io::ErrorKind::Other, format!("Failed to extract RAR file '{}': {}", current_filename_display, e))
-                            })?
-                    }
-                }
-                Ok(None) => break,
-                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("Error reading RAR header: {}", e))),
-            }
-        };
-
-        archive = next_archive_state;
-        extracted_count += 1;
-        pb.set_message(format!("Extracting: {}", current_filename_display));
-        pb.inc(1);
+    let stdout = Some(Path::new("-"));
+    if args.stats_json.as_deref() == stdout && args.output.as_deref() == stdout {
+        eprintln!("--compression-stats-json - cannot share stdout with -o -");
+        std::process::exit(1);
     }
 
-    pb.finish_with_message(format!("RAR decompression done. Extracted {} files/directories.", extracted_count));
-    Ok(())
-}
+    if let Some(root) = &args.archive_root
+        && (root.as_os_str().is_empty() || !root.components().all(|c| matches!(c, Component::Normal(_))))
+    {
+        eprintln!("--archive-root must be a relative path without '..'");
+        std::process::exit(1);
+    }
 
-fn decompress_iso(input: &PathBuf, output: &PathBuf, buffer_size: usize) -> io::Result<()> {
-    println!("Attempting ISO decompression...");
-    
-    let mut file = File::open(input)?;
-    
-    // Vérifier la signature ISO 9660
-    let mut buffer = [0u8; 8];
-    file.seek(SeekFrom::Start(32768))?; // Volume descriptor commence à 32KB
-    file.read_exact(&mut buffer)?;
-    
-    if &buffer[1..6] != b"CD001" {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Invalid ISO 9660 signature"
-        ));
+    if args.list_formats {
+        list_formats();
+        return Ok(());
     }
-    
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_message("Reading ISO structure...");
-    
-    // Lire le Primary Volume Descriptor
-    let mut pvd = [0u8; 2048];
-    file.seek(SeekFrom::Start(32768))?;
-    file.read_exact(&mut pvd)?;
-    
-    // Extraire les informations du répertoire racine
-    let root_dir_location = u32::from_le_bytes([pvd[158], pvd[159], pvd[160], pvd[161]]);
-    let root_dir_size = u32::from_le_bytes([pvd[166], pvd[167], pvd[168], pvd[169]]);
-    
-    pb.set_message("Extracting files...");
-    
-    let mut extracted_count = 0;
-    extract_iso_directory(
-        &mut file, 
-        root_dir_location, 
-        root_dir_size, 
-        output, 
-        "",
-        &pb,
-        &mut extracted_count,
-        buffer_size
-    )?;
-    
-    pb.finish_with_message(format!("ISO decompression done. Extracted {} files/directories.", extracted_count));
-    Ok(())
-}
 
-fn extract_iso_directory(
-    file: &mut File,
-    location: u32,
-    size: u32,
-    output_base: &PathBuf,
-    current_path: &str,
-    pb: &ProgressBar,
-    extracted_count: &mut u32,
-    buffer_size: usize,
-) -> io::Result<()> {
-    let sector_size = 2048u32;
-    let start_pos = (location as u64) * (sector_size as u64);
-    
-    file.seek(SeekFrom::Start(start_pos))?;
-    let mut dir_data = vec![0u8; size as usize];
-    file.read_exact(&mut dir_data)?;
-    
-    let mut offset = 0;
-    while offset < size as usize {
-        if dir_data[offset] == 0 {
-            break;
+    if let Some(path) = &args.probe {
+        let recognized = probe_file(path).unwrap_or_else(|e| fail(e));
+        if !recognized {
+            std::process::exit(2);
         }
-        
-        let record_length = dir_data[offset] as usize;
-        if record_length == 0 || offset + record_length > size as usize {
-            break;
-        }
-        
-        let name_length = dir_data[offset + 32] as usize;
-        if name_length > 0 && offset + 33 + name_length <= size as usize {
-            let name_bytes = &dir_data[offset + 33..offset + 33 + name_length];
-            
-            // Clean up file name - remove version info and handle special characters
-            let mut name = String::new();
-            for &b in name_bytes {
-                if b == b';' {
-                    break;
-                }
-                // Replace NUL and other problematic characters
-                if b >= 32 && b < 127 && b != b'<' && b != b'>' && b != b':' && b != b'"' 
-                    && b != b'/' && b != b'\\' && b != b'|' && b != b'?' && b != b'*' {
-                    name.push(b as char);
-                }
-            }
-            
-            // Skip empty names and special entries
-            if !name.is_empty() && name != "." && name != ".." {
-                let file_location = u32::from_le_bytes([
-                    dir_data[offset + 2],
-                    dir_data[offset + 3],
-                    dir_data[offset + 4],
-                    dir_data[offset + 5]
-                ]);
-                
-                let file_size = u32::from_le_bytes([
-                    dir_data[offset + 10],
-                    dir_data[offset + 11],
-                    dir_data[offset + 12],
-                    dir_data[offset + 13]
-                ]);
-                
-                let flags = dir_data[offset + 25];
-                let is_directory = (flags & 0x02) != 0;
-                
-                let full_path = if current_path.is_empty() {
-                    name.clone()
-                } else {
-                    format!("{}/{}", current_path, name)
-                };
-                
-                // Convert path to safe Windows format
-                let safe_path = full_path.replace('/', "\\");
-                let output_path = output_base.join(safe_path);
-                
-                if let Err(e) = if is_directory {
-                    fs::create_dir_all(&output_path).and_then(|_| {
-                        pb.set_message(format!("Created directory: {}", output_path.display()));
-                        extract_iso_directory(
-                            file,
-                            file_location,
-                            file_size,
-                            output_base,
-                            &full_path,
-                            pb,
-                            extracted_count,
-                            buffer_size
-                        )
-                    })
-                } else {
-                    if let Some(parent) = output_path.parent() {
-                        fs::create_dir_all(parent)?;
-                    }
-                    
-                    pb.set_message(format!("Extracting: {}", output_path.display()));
-                    
-                    let file_start = (file_location as u64) * (sector_size as u64);
-                    file.seek(SeekFrom::Start(file_start))?;
-                    
-                    let mut output_file = File::create(&output_path)?;
-                    let mut remaining = file_size as u64;
-                    let mut buffer = vec![0u8; buffer_size.min(remaining as usize)];
-                    
-                    while remaining > 0 {
-                        let to_read = buffer_size.min(remaining as usize);
-                        let bytes_read = file.read(&mut buffer[..to_read])?;
-                        if bytes_read == 0 {
-                            break;
-                        }
-                        output_file.write_all(&buffer[..bytes_read])?;
-                        remaining -= bytes_read as u64;
-                    }
-                    Ok(())
-                } {
-                    eprintln!("Warning: Failed to extract '{}': {}", output_path.display(), e);
-                    continue;
-                }
-                
-                *extracted_count += 1;
-                pb.inc(1);
-            }
-        }
-        
-        offset += record_length;
+        return Ok(());
     }
-    
-    Ok(())
-}
 
-fn decompress_7z(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    println!("Attempting 7Z decompression...");
-    
-    let file = File::open(input)?;
-    let file_size = file.metadata()?.len();
-    
-    let mut reader = SevenZReader::new(file, file_size, sevenz_rust::Password::empty())
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to open 7Z archive: {}", e)))?;
-    
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-    
-    let mut extracted_count = 0;
-    
-    reader.for_each_entries(|entry, reader| {
-        let entry_path = output.join(&entry.name);
-        
-        pb.set_message(format!("Extracting: {}", entry.name));
-        
-        if entry.is_directory() {
-            fs::create_dir_all(&entry_path)?;
-        } else {
-            if let Some(parent) = entry_path.parent() {
-                fs::create_dir_all(parent)?;
+    let start = Instant::now();
+    let mut skipped = Vec::new();
+    let res = if args.list || args.histogram || args.largest.is_some() {
+        read_entries(&args.decompress_options()).map(|entries| {
+            if args.list {
+                print_listing(&entries);
+            }
+            if args.histogram {
+                print_histogram(&entries);
             }
-            
-            let mut output_file = File::create(&entry_path)?;
-            io::copy(reader, &mut output_file)?;
+            if let Some(n) = args.largest {
+                print_largest(&entries, n);
+            }
+        })
+    } else if let Some(dict) = &args.train_dict {
+        train_dict(&args.compress_options(), dict, args.dict_size)
+    } else if args.batch_extract {
+        batch_extract(&args.decompress_options(), &mut skipped)
+    } else if let Some(levels) = &args.compare_levels {
+        compare_levels(&args.compress_options(), levels)
+    } else if args.compress {
+        compress(&args.compress_options())
+    } else if args.decompress || args.test {
+        decompress(&args.decompress_options())
+    } else {
+        let mut cmd = Args::command();
+        cmd.print_help()?;
+        return Ok(());
+    };
+    let res = match res {
+        Err(e) if args.decompress && args.continue_on_unsupported && e.is_unsupported() => {
+            skipped.push((args.input().to_path_buf(), e.to_string()));
+            Ok(())
         }
-        
-        extracted_count += 1;
-        pb.inc(1);
-        Ok(true)
-    }).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("7Z extraction error: {}", e)))?;
-    
-    pb.finish_with_message(format!("7Z decompression done. Extracted {} files/directories.", extracted_count));
-    Ok(())
-}
-
-fn decompress_single_file_gz(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_file = File::open(input)?;
-    let mut decoder = GzDecoder::new(input_file);
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
+        other => other,
+    };
+    if let Err(e) = res {
+        fail(e);
     }
-    
-    let mut output_file = File::create(&output_file_path)?;
-    io::copy(&mut decoder, &mut output_file)?;
-    
-    println!("GZ decompression done: {:?}", output_file_path);
-    Ok(())
-}
 
-fn decompress_single_file_bz2(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_file = File::open(input)?;
-    let mut decoder = BzDecoder::new(input_file);
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
+    report_skipped(&skipped);
+    // `-o -` : la sortie standard porte l'archive, ou le flux décompressé, et `--quiet` la laisse vide ;
+    // `--compression-stats-json -` n'y laisse que le bilan JSON
+    let stdout = Some(Path::new("-"));
+    if args.output.as_deref() == stdout || args.stats_json.as_deref() == stdout || args.quiet {
+        eprintln!("Total time: {:.2?}", start.elapsed());
+    } else {
+        println!("Total time: {:.2?}", start.elapsed());
     }
-    
-    let mut output_file = File::create(&output_file_path)?;
-    io::copy(&mut decoder, &mut output_file)?;
-    
-    println!("BZ2 decompression done: {:?}", output_file_path);
     Ok(())
 }
 
-fn decompress_single_file_xz(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_file = File::open(input)?;
-    let mut decoder = XzDecoder::new(input_file);
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    let mut output_file = File::create(&output_file_path)?;
-    io::copy(&mut decoder, &mut output_file)?;
-    
-    println!("XZ decompression done: {:?}", output_file_path);
-    Ok(())
-}
+/// Bornes de `--buffer-size` : en dessous, les lectures se font par miettes ; au-dessus,
+/// le tampon (alloué par flux) n'apporte plus rien
+const BUFFER_SIZE_RANGE: std::ops::RangeInclusive<usize> = 4096..=1 << 30;
 
-fn decompress_single_file_zstd(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_file = File::open(input)?;
-    let mut decoder = ZstdDecoder::new(input_file)?;
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    let mut output_file = File::create(&output_file_path)?;
-    io::copy(&mut decoder, &mut output_file)?;
-    
-    println!("ZSTD decompression done: {:?}", output_file_path);
-    Ok(())
-}
+/// Bornes de `--dict-size` : zstd refuse les dictionnaires de moins de 256 octets
+const DICT_SIZE_RANGE: std::ops::RangeInclusive<usize> = 256..=16 << 20;
 
-fn decompress_single_file_lzma(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_data = fs::read(input)?;
-    let mut output_data = Vec::new();
-    
-    lzma_decompress(&mut input_data.as_slice(), &mut output_data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("LZMA decompression error: {}", e)))?;
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    fs::write(&output_file_path, output_data)?;
-    
-    println!("LZMA decompression done: {:?}", output_file_path);
-    Ok(())
+fn parse_buffer_size(value: &str) -> Result<usize, String> {
+    parse_size(value, BUFFER_SIZE_RANGE)
 }
 
-fn decompress_single_file_brotli(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    let input_file = File::open(input)?;
-    let mut decoder = BrotliDecoder::new(input_file, 4096);
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    let mut output_file = File::create(&output_file_path)?;
-    io::copy(&mut decoder, &mut output_file)?;
-    
-    println!("Brotli decompression done: {:?}", output_file_path);
-    Ok(())
+fn parse_dict_size(value: &str) -> Result<usize, String> {
+    parse_size(value, DICT_SIZE_RANGE)
 }
 
-fn decompress_single_file_lz4(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    // Pour LZ4, nous utiliserons une implémentation simple
-    // Vous devrez ajouter la crate lz4_flex à vos dépendances
-    let input_data = fs::read(input)?;
-    
-    // Décompression LZ4 (nécessite lz4_flex crate)
-    let decompressed = lz4_flex::decompress_size_prepended(&input_data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("LZ4 decompression error: {}", e)))?;
-    
-    let output_name = input.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("decompressed");
-    let output_file_path = output.join(output_name);
-    
-    if let Some(parent) = output_file_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    fs::write(&output_file_path, decompressed)?;
-    
-    println!("LZ4 decompression done: {:?}", output_file_path);
-    Ok(())
+/// Seuils `--exclude-larger-than` / `--exclude-smaller-than`, sans borne
+fn parse_file_size(value: &str) -> Result<u64, String> {
+    parse_size(value, 0..=usize::MAX).map(|size| size as u64)
 }
 
-fn decompress_cab(input: &PathBuf, output: &PathBuf) -> io::Result<()> {
-    println!("CAB decompression not fully implemented - requires external library");
-    // Pour les fichiers CAB, vous pourriez utiliser une crate comme `cab` ou appeler un outil externe
-    // Voici un exemple basique qui nécessiterait l'ajout d'une crate appropriée
-    
-    println!("CAB files require additional implementation. File: {:?}", input);
-    println!("Consider using external tools like 'cabextract' for now.");
-
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-
-    // Initialize the reader variable (example: using a file input)
-    let file = File::open(input)?;
-    let reader = BufReader::new(file);
-
-    let mut archive = Archive::new(reader);
-    for entry in archive.entries()? {
-        let mut file = entry?;
-        let path = file.path()?.to_path_buf();
-        let outpath = output.join(&path);
-        if file.header().entry_type().is_dir() {
-            fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                fs::create_dir_all(p)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
-        }
-        pb.inc(1);
+/// Taille en octets, suffixes `k`, `M`, `G` (puissances de 1024, `B`/`iB` tolérés), bornée par `range`
+fn parse_size(value: &str, range: std::ops::RangeInclusive<usize>) -> Result<usize, String> {
+    let lower = value.trim().to_ascii_lowercase();
+    // Un seul suffixe `B` ou `iB` : « 8Mbbbb » ou « 4kibib » ne sont pas des tailles
+    let number = lower.strip_suffix("ib").or_else(|| lower.strip_suffix('b')).unwrap_or(&lower);
+    let (digits, shift) = match number.char_indices().last() {
+        Some((i, 'k')) => (&number[..i], 10),
+        Some((i, 'm')) => (&number[..i], 20),
+        Some((i, 'g')) => (&number[..i], 30),
+        _ => (number, 0),
+    };
+    let size = digits
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("'{}' is not a size (expected e.g. 65536, 512k, 16M)", value))?;
+    if !range.contains(&size) {
+        return Err(format!(
+            "{} bytes is outside the supported range ({} bytes to {} bytes)",
+            size,
+            range.start(),
+            range.end()
+        ));
     }
-    pb.finish_with_message("Decompression done.");
-    Ok(())
+    Ok(size)
 }
 
-
-fn build_progress(path: &PathBuf) -> io::Result<ProgressBar> {
-    let count = WalkDir::new(path)
-        .into_iter()
-        .filter_map(Result::ok)
-        .count() as u64;
-    let pb = ProgressBar::new(count.max(1));
-    let style = ProgressStyle::with_template(
-        "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len}"
-    )
-    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-    pb.set_style(style.progress_chars("#>-"));
-    Ok(pb)
+/// Affiche l'erreur et quitte avec le code associé (3 : non supporté, 4 : chemin refusé)
+fn fail(e: SharkyError) -> ! {
+    eprintln!("Error: {}", e);
+    std::process::exit(e.exit_code());
 }
 
-fn traverse_and_append(
-    input: &PathBuf,
-    builder: &mut Builder<impl Write>,
-    pb: &ProgressBar,
-    excludes: &[String],
-) -> io::Result<()> {
-    let skip = |p: &PathBuf| excludes.iter().any(|pat| p.to_string_lossy().contains(pat));
-    if input.is_dir() {
-        let root = input.file_name().unwrap();
-        builder.append_dir(root, input)?;
-        pb.inc(1);
-        for entry in WalkDir::new(input).min_depth(1).into_iter().filter_map(Result::ok) {
-            let path = entry.path().to_path_buf();
-            if skip(&path) { continue }
-            let rel = path.strip_prefix(input).unwrap();
-            let tp = PathBuf::from(root).join(rel);
-            if entry.file_type().is_dir() {
-                builder.append_dir(&tp, &path)?;
-            } else {
-                let mut f = File::open(&path)?;
-                builder.append_file(&tp, &mut f)?;
-            }
-            pb.inc(1);
-        }
-    } else if !skip(input) {
-        let mut f = File::open(input)?;
-        builder.append_file(input.file_name().unwrap(), &mut f)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_take_one_optional_byte_suffix() {
+        assert_eq!(parse_file_size("8M"), Ok(8_388_608));
+        assert_eq!(parse_file_size("8MB"), Ok(8_388_608));
+        assert_eq!(parse_file_size("8MiB"), Ok(8_388_608));
+        assert_eq!(parse_file_size("512k"), Ok(524_288));
+        assert_eq!(parse_file_size("100"), Ok(100));
+        assert!(parse_file_size("8Mbbbb").is_err());
+        assert!(parse_file_size("4kibib").is_err());
+        assert!(parse_file_size("M").is_err());
     }
-    Ok(())
-}
 
-fn decompress_tar_plain<R: Read>(reader: R, output: &PathBuf) -> io::Result<()> {
-    let mut archive = Archive::new(reader);
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
-
-    for entry in archive.entries()? {
-        let mut file = entry?;
-        let path = file.path()?.to_path_buf();
-        let outpath = output.join(&path);
-        
-        pb.set_message(format!("Extracting: {}", path.display()));
-
-        if file.header().entry_type().is_dir() {
-            fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
-        }
-        pb.inc(1);
+    #[test]
+    fn sizes_outside_the_range_are_rejected() {
+        assert!(parse_buffer_size("0").is_err());
+        assert!(parse_buffer_size("64k").is_ok());
     }
-    
-    pb.finish_with_message("TAR extraction complete");
-    Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,245 @@
+//! Outils communs aux tests d'intégration : répertoires jetables, lancement du binaire,
+//! lecture des archives produites
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Répertoire temporaire propre à un test, supprimé à la fin
+pub struct Scratch {
+    pub dir: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(name: &str) -> Scratch {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("sharky-test-{}-{}-{}", name, std::process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Scratch { dir }
+    }
+
+    pub fn path(&self, rel: &str) -> PathBuf {
+        self.dir.join(rel)
+    }
+
+    /// Écrit `contents` dans `rel`, répertoires parents compris
+    pub fn write(&self, rel: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn mkdir(&self, rel: &str) -> PathBuf {
+        let path = self.path(rel);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Chemin en argument de ligne de commande
+pub fn arg(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+/// Lance sharky sans stdin
+pub fn sharky(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sharky")).args(args).stdin(std::process::Stdio::null()).output().unwrap()
+}
+
+/// Lance sharky et vérifie qu'il réussit
+pub fn sharky_ok(args: &[&str]) -> Output {
+    let out = sharky(args);
+    assert!(
+        out.status.success(),
+        "sharky {:?} failed ({}):\n{}",
+        args, out.status, String::from_utf8_lossy(&out.stderr)
+    );
+    out
+}
+
+/// Lance sharky et vérifie qu'il échoue ; rend stderr
+pub fn sharky_err(args: &[&str]) -> String {
+    let out = sharky(args);
+    assert!(!out.status.success(), "sharky {:?} unexpectedly succeeded", args);
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+pub fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+/// Octets pseudo-aléatoires reproductibles (xorshift)
+pub fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Chemins des entrées d'un tar non compressé, dans l'ordre de l'archive
+pub fn tar_names<R: Read>(reader: R) -> Vec<String> {
+    let mut archive = tar::Archive::new(reader);
+    archive.entries().unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Chemins des entrées d'une archive zip
+pub fn zip_names(path: &Path) -> Vec<String> {
+    let archive = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+    archive.file_names().map(str::to_string).collect()
+}
+
+/// Fichiers sous `dir` (chemins relatifs, `/`) et leur contenu, triés
+pub fn tree(dir: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<_> = walkdir::WalkDir::new(dir).into_iter()
+        .map(Result::unwrap)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            let rel = e.path().strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/");
+            (rel, fs::read(e.path()).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Petite arborescence de test : deux fichiers, dont un dans un sous-répertoire
+pub fn sample_tree(scratch: &Scratch, rel: &str) -> PathBuf {
+    scratch.write(&format!("{}/a.txt", rel), "alpha\n");
+    scratch.write(&format!("{}/sub/b.txt", rel), "bravo\n".repeat(100));
+    scratch.path(rel)
+}
+
+/// Tar brut écrit sans les vérifications de chemin de `tar::Builder`, pour simuler une
+/// archive malveillante : entrées `(chemin, type, cible du lien, données)`
+pub fn crafted_tar(entries: &[(&str, tar::EntryType, &str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for &(path, kind, link, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        let old = header.as_old_mut();
+        old.name[..path.len()].copy_from_slice(path.as_bytes());
+        old.linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(kind);
+        header.set_mode(if kind.is_dir() { 0o755 } else { 0o644 });
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+/// Enregistrement de répertoire ISO 9660 : extent (secteur, taille), drapeau répertoire,
+/// identifiant et zone System Use (Rock Ridge)
+pub fn iso_record(location: u32, size: u32, is_dir: bool, name: &[u8], system_use: &[u8]) -> Vec<u8> {
+    let mut record = vec![0u8; 33];
+    record[2..6].copy_from_slice(&location.to_le_bytes());
+    record[6..10].copy_from_slice(&location.to_be_bytes());
+    record[10..14].copy_from_slice(&size.to_le_bytes());
+    record[14..18].copy_from_slice(&size.to_be_bytes());
+    // 2024-01-02 03:04:05 UTC
+    record[18..25].copy_from_slice(&[124, 1, 2, 3, 4, 5, 0]);
+    record[25] = if is_dir { 0x02 } else { 0 };
+    record[28] = 1;
+    record[31] = 1;
+    record[32] = name.len() as u8;
+    record.extend_from_slice(name);
+    if name.len().is_multiple_of(2) {
+        record.push(0);
+    }
+    record.extend_from_slice(system_use);
+    if record.len() % 2 == 1 {
+        record.push(0);
+    }
+    record[0] = record.len() as u8;
+    record
+}
+
+/// Image ISO 9660 montée extent par extent : descripteurs aux secteurs 16 à 18, données
+/// à partir du secteur 19, sans table des chemins
+pub struct IsoImage {
+    data: Vec<u8>,
+}
+
+impl Default for IsoImage {
+    fn default() -> IsoImage {
+        IsoImage { data: vec![0; 19 * 2048] }
+    }
+}
+
+impl IsoImage {
+
+    /// Secteur du prochain extent ajouté
+    pub fn next_sector(&self) -> u32 {
+        (self.data.len() / 2048) as u32
+    }
+
+    /// Ajoute un extent (bourré au secteur) ; rend son secteur et sa taille
+    pub fn add(&mut self, contents: &[u8]) -> (u32, u32) {
+        let location = self.next_sector();
+        self.data.extend_from_slice(contents);
+        self.data.resize(self.data.len().div_ceil(2048).max(location as usize + 1) * 2048, 0);
+        (location, contents.len() as u32)
+    }
+
+    /// Ajoute un répertoire : `.` et `..` puis `records`, sans chevaucher deux secteurs
+    pub fn dir(&mut self, records: &[Vec<u8>]) -> (u32, u32) {
+        let location = self.next_sector();
+        let mut extent = Vec::new();
+        let dots = [iso_record(location, 0, true, &[0], &[]), iso_record(location, 0, true, &[1], &[])];
+        for record in dots.iter().chain(records) {
+            if extent.len() % 2048 + record.len() > 2048 {
+                extent.resize(extent.len().div_ceil(2048) * 2048, 0);
+            }
+            extent.extend_from_slice(record);
+        }
+        let size = extent.len().div_ceil(2048) as u32 * 2048;
+        self.add(&extent);
+        (location, size)
+    }
+
+    /// Écrit les descripteurs : `root` pour le volume primaire, `joliet` pour un volume
+    /// supplémentaire Joliet (UCS-2 niveau 3)
+    pub fn finish(mut self, root: (u32, u32), joliet: Option<(u32, u32)>) -> Vec<u8> {
+        let mut descriptor = |sector: usize, kind: u8, root: (u32, u32)| {
+            let vd = &mut self.data[sector * 2048..(sector + 1) * 2048];
+            vd[0] = kind;
+            vd[1..6].copy_from_slice(b"CD001");
+            vd[6] = 1;
+            vd[156..190].copy_from_slice(&iso_record(root.0, root.1, true, &[0], &[]));
+            if kind == 2 {
+                vd[88..91].copy_from_slice(b"%/E");
+            }
+        };
+        descriptor(16, 1, root);
+        let mut terminator = 17;
+        if let Some(joliet) = joliet {
+            descriptor(17, 2, joliet);
+            terminator = 18;
+        }
+        let vd = &mut self.data[terminator * 2048..];
+        vd[0] = 255;
+        vd[1..6].copy_from_slice(b"CD001");
+        vd[6] = 1;
+        self.data
+    }
+}
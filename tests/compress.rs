@@ -0,0 +1,569 @@
+//! Compression de bout en bout par le binaire : options de parcours et d'organisation des archives
+
+mod common;
+
+use common::*;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+
+#[test]
+fn no_wrap_archives_directory_contents_at_top_level() {
+    let s = Scratch::new("no-wrap");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--no-wrap", "-q"]);
+
+    let names = tar_names(File::open(&archive).unwrap());
+    assert!(names.iter().any(|n| n.trim_end_matches('/') == "sub/b.txt"), "{:?}", names);
+    assert!(names.iter().all(|n| !n.starts_with("project")), "wrapper prefix left in {:?}", names);
+
+    let out = s.path("extracted");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out), tree(&input));
+}
+
+#[test]
+fn zip_no_wrap_keeps_a_single_file_input() {
+    let s = Scratch::new("zip-no-wrap-file");
+    let input = s.write("a.txt", "alpha\n");
+    for flags in [&[][..], &["--no-wrap"][..]] {
+        let archive = s.path("a.zip");
+        let mut args = vec!["-c", "-i", arg(&input), "-o", arg(&archive), "-q", "--force"];
+        args.extend_from_slice(flags);
+        sharky_ok(&args);
+        assert_eq!(zip_names(&archive), ["a.txt"], "flags {:?}", flags);
+    }
+}
+
+#[test]
+fn sevenz_no_wrap_keeps_a_single_file_input() {
+    let s = Scratch::new("7z-no-wrap-file");
+    let input = s.write("a.txt", "alpha\n");
+    for flags in [&[][..], &["--no-wrap"][..]] {
+        let archive = s.path("a.7z");
+        let mut args = vec!["-c", "-i", arg(&input), "-o", arg(&archive), "-q", "--force"];
+        args.extend_from_slice(flags);
+        sharky_ok(&args);
+        let listing = stdout(&sharky_ok(&["-l", "-i", arg(&archive)]));
+        assert!(listing.lines().any(|l| l.ends_with("  a.txt")), "flags {:?}:\n{}", flags, listing);
+        assert!(listing.contains("1 entries"), "flags {:?}:\n{}", flags, listing);
+    }
+}
+
+#[test]
+fn append_to_smart_pipeline_sharky_without_xz_layer() {
+    let s = Scratch::new("append-smart");
+    // Données aléatoires : la couche xz n'aide pas, l'archive est un simple tar.zst
+    s.write("first/noise.bin", random_bytes(64 * 1024, 1));
+    s.write("second/c.txt", "gamma\n");
+    let archive = s.path("out.sharky");
+    sharky_ok(&["-c", "-i", arg(&s.path("first")), "-o", arg(&archive), "--smart-pipeline", "-q"]);
+    sharky_ok(&["-c", "-i", arg(&s.path("second")), "-o", arg(&archive), "--append", "--smart-pipeline", "-q"]);
+
+    let out = s.path("extracted");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    let names: Vec<String> = tree(&out).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["first/noise.bin", "second/c.txt"]);
+}
+
+#[test]
+fn append_round_trips_tar_and_tar_zst() {
+    let s = Scratch::new("append");
+    let first = sample_tree(&s, "first");
+    s.write("second/c.txt", "gamma\n");
+    for name in ["out.tar", "out.tar.zst"] {
+        let archive = s.path(name);
+        sharky_ok(&["-c", "-i", arg(&first), "-o", arg(&archive), "-q"]);
+        sharky_ok(&["-c", "-i", arg(&s.path("second")), "-o", arg(&archive), "--append", "-q"]);
+
+        let out = s.path(&format!("{}.d", name));
+        sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+        assert_eq!(tree(&out.join("first")), tree(&first), "{}", name);
+        assert_eq!(fs::read(out.join("second/c.txt")).unwrap(), b"gamma\n", "{}", name);
+    }
+    let zip = s.path("out.zip");
+    sharky_ok(&["-c", "-i", arg(&first), "-o", arg(&zip), "-q"]);
+    let stderr = sharky_err(&["-c", "-i", arg(&s.path("second")), "-o", arg(&zip), "--append", "-q"]);
+    assert!(stderr.contains("only tar-based formats support --append"), "{}", stderr);
+}
+
+#[test]
+fn sevenz_keeps_zero_byte_files_and_empty_dirs() {
+    let s = Scratch::new("7z-empty");
+    let input = sample_tree(&s, "project");
+    s.write("project/empty.txt", "");
+    s.mkdir("project/hollow");
+    let archive = s.path("out.7z");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+    // Aucune entrée ne doit passer pour une suppression (kAnti) auprès de 7-Zip
+    let reader = sevenz_rust::SevenZReader::open(&archive, sevenz_rust::Password::empty()).unwrap();
+    assert!(reader.archive().files.iter().all(|e| !e.is_anti_item()));
+
+    let out = s.path("extracted");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out.join("project")), tree(&input));
+    assert_eq!(fs::metadata(out.join("project/empty.txt")).unwrap().len(), 0);
+    assert!(out.join("project/hollow").is_dir());
+}
+
+#[test]
+fn exclude_larger_than_drops_big_files_only() {
+    let s = Scratch::new("exclude-larger");
+    s.write("project/big.bin", vec![0u8; 10 << 20]);
+    s.write("project/small.bin", random_bytes(100 << 10, 3));
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&s.path("project")), "-o", arg(&archive), "--exclude-larger-than", "1M", "-q"]);
+
+    let names = tar_names(File::open(&archive).unwrap());
+    assert!(names.iter().any(|n| n == "project/small.bin"), "{:?}", names);
+    assert!(!names.iter().any(|n| n.ends_with("big.bin")), "{:?}", names);
+}
+
+#[test]
+fn threaded_zstd_output_matches_single_threaded() {
+    let s = Scratch::new("threads");
+    s.write("project/random.bin", random_bytes(3 << 20, 11));
+    s.write("project/text.txt", "lorem ipsum dolor sit amet\n".repeat(200_000));
+    let input = s.path("project");
+    for threads in ["1", "4"] {
+        let archive = s.path(&format!("t{}.tar.zst", threads));
+        sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--threads", threads, "-z", "3", "-q"]);
+        let out = s.path(&format!("out{}", threads));
+        sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+        assert_eq!(tree(&out.join("project")), tree(&input), "--threads {}", threads);
+    }
+}
+
+#[test]
+fn manifest_lists_every_entry_with_its_size() {
+    let s = Scratch::new("manifest");
+    let input = sample_tree(&s, "project");
+    let manifest = s.path("manifest.txt");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&s.path("out.tar")), "--manifest", arg(&manifest), "-q"]);
+
+    let text = fs::read_to_string(&manifest).unwrap();
+    let mut sizes: Vec<(String, u64)> = text.lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.splitn(3, "  ").collect();
+            assert_eq!(fields.len(), 3, "{}", line);
+            (fields[2].to_string(), fields[1].parse().unwrap())
+        })
+        .collect();
+    sizes.sort();
+    let expected = [("project", 0), ("project/a.txt", 6), ("project/sub", 0), ("project/sub/b.txt", 600)];
+    assert_eq!(sizes, expected.map(|(path, size)| (path.to_string(), size)));
+    // sha256("alpha\n")
+    assert!(text.contains("b6a98d9ce9a2d9149288fa3df42d377c3e42737afdcdaf714e33c0a100b51060  6  project/a.txt"));
+}
+
+#[test]
+fn ignore_file_and_exclude_globs_filter_the_walk() {
+    let s = Scratch::new("ignore-file");
+    let input = sample_tree(&s, "project");
+    s.write("project/debug.log", "noise");
+    s.write("project/keep.log", "kept");
+    s.write("project/build/out.o", "object");
+    s.write("project/sub/build.txt", "not a directory");
+    s.write("project/scratch.tmp", "temp");
+    let ignore = s.write(".sharkyignore", "*.log\n!keep.log\nbuild/\n");
+    let archive = s.path("out.tar");
+    sharky_ok(&[
+        "-c", "-i", arg(&input), "-o", arg(&archive), "--ignore-file", arg(&ignore), "--exclude", "*.tmp", "-q",
+    ]);
+
+    let mut names = tar_names(File::open(&archive).unwrap());
+    names.sort();
+    assert_eq!(names, [
+        "project", "project/a.txt", "project/keep.log", "project/sub", "project/sub/b.txt", "project/sub/build.txt",
+    ]);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_archived_as_links_unless_dereferenced() {
+    let s = Scratch::new("dereference");
+    let input = sample_tree(&s, "project");
+    std::os::unix::fs::symlink("a.txt", input.join("link")).unwrap();
+    let link_entry = |archive: &std::path::Path| {
+        let mut archive = tar::Archive::new(File::open(archive).unwrap());
+        let entry = archive.entries().unwrap().map(Result::unwrap)
+            .find(|e| e.path().unwrap().ends_with("link"))
+            .unwrap();
+        let target = entry.link_name().unwrap().map(|t| t.into_owned());
+        (entry.header().entry_type(), target, entry.size())
+    };
+
+    let plain = s.path("plain.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&plain), "-q"]);
+    assert_eq!(link_entry(&plain), (tar::EntryType::Symlink, Some("a.txt".into()), 0));
+
+    let followed = s.path("followed.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&followed), "--dereference", "-q"]);
+    assert_eq!(link_entry(&followed), (tar::EntryType::Regular, None, 6));
+}
+
+#[test]
+fn tar_keeps_paths_longer_than_100_bytes() {
+    let s = Scratch::new("long-paths");
+    let deep = format!("project/{}/{}/{}", "d".repeat(80), "e".repeat(80), "f".repeat(60));
+    let long_file = format!("{}/{}.txt", deep, "n".repeat(20));
+    let long_name = format!("project/{}.txt", "w".repeat(200));
+    s.write(&long_file, "deep\n");
+    s.write(&long_name, "wide\n");
+    assert!(long_file.len() > 250 && long_name.len() > 200);
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&s.path("project")), "-o", arg(&archive), "-q"]);
+
+    let names = tar_names(File::open(&archive).unwrap());
+    assert!(names.contains(&long_file) && names.contains(&long_name), "{:?}", names);
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join(&long_file)).unwrap(), b"deep\n");
+    assert_eq!(fs::read(out.join(&long_name)).unwrap(), b"wide\n");
+}
+
+/// Bilan `--compression-stats-json` d'une compression avec `extra`
+fn compress_stats(s: &Scratch, input: &std::path::Path, output: &str, extra: &[&str]) -> serde_json::Value {
+    let stats = s.path("stats.json");
+    let archive = s.path(output);
+    let _ = fs::remove_file(&archive);
+    let mut args = vec!["-c", "-i", arg(input), "-o", arg(&archive), "--compression-stats-json", arg(&stats), "-q"];
+    args.extend_from_slice(extra);
+    sharky_ok(&args);
+    serde_json::from_str(&fs::read_to_string(&stats).unwrap()).unwrap()
+}
+
+#[test]
+fn level_maps_onto_the_zstd_scale() {
+    let s = Scratch::new("level-zstd");
+    let input = s.write("data.txt", "level mapping\n".repeat(1000));
+    for (level, zstd) in [("0", 1), ("4", 9), ("9", 19)] {
+        let stats = compress_stats(&s, &input, "data.txt.zst", &["--level", level]);
+        assert_eq!(stats["level"], zstd, "--level {}", level);
+    }
+    let stats = compress_stats(&s, &input, "data.txt.zst", &["--level", "4", "--zstd-level", "5"]);
+    assert_eq!(stats["level"], 5);
+}
+
+#[test]
+fn quiet_prints_only_the_summary_and_verbose_lists_entries() {
+    let s = Scratch::new("quiet-verbose");
+    let input = sample_tree(&s, "project");
+
+    let quiet = sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&s.path("q.tar")), "-q"]);
+    assert_eq!(stdout(&quiet), "");
+    let stderr = String::from_utf8_lossy(&quiet.stderr);
+    assert!(stderr.contains("Total time"), "{}", stderr);
+    assert!(!stderr.contains("Compression:") && !stderr.contains("project/a.txt"), "{}", stderr);
+
+    let verbose = sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&s.path("v.tar")), "-v"]);
+    let text = stdout(&verbose) + &String::from_utf8_lossy(&verbose.stderr);
+    for entry in ["project/a.txt", "project/sub/b.txt"] {
+        assert!(text.lines().any(|line| line == entry), "{} missing from:\n{}", entry, text);
+    }
+
+    let extracted = sharky_ok(&["-d", "-i", arg(&s.path("q.tar")), "-o", arg(&s.path("out")), "-v"]);
+    let text = stdout(&extracted) + &String::from_utf8_lossy(&extracted.stderr);
+    assert!(text.lines().any(|line| line == "project/sub/b.txt"), "{}", text);
+}
+
+#[test]
+fn stats_json_reports_sizes_ratio_and_entries() {
+    let s = Scratch::new("stats-json");
+    let input = sample_tree(&s, "project");
+    let stats = compress_stats(&s, &input, "out.tar.zst", &[]);
+    let mut keys: Vec<&str> = stats.as_object().unwrap().keys().map(String::as_str).collect();
+    keys.sort();
+    assert_eq!(keys, ["elapsed_seconds", "entries", "format", "input_bytes", "level", "operation", "output_bytes", "ratio"]);
+    assert_eq!(stats["operation"], "compress");
+    assert_eq!(stats["format"], "tar.zst");
+    assert_eq!(stats["input_bytes"], 606);
+    assert_eq!(stats["entries"], 4);
+    let output = fs::metadata(s.path("out.tar.zst")).unwrap().len();
+    assert_eq!(stats["output_bytes"], output);
+    assert!((stats["ratio"].as_f64().unwrap() - output as f64 / 606.0).abs() < 1e-9);
+
+    let run = sharky_ok(&[
+        "-d", "-i", arg(&s.path("out.tar.zst")), "-o", arg(&s.path("out")), "--compression-stats-json", "-", "-q",
+    ]);
+    let stats: serde_json::Value = serde_json::from_str(stdout(&run).trim()).unwrap();
+    assert_eq!(stats["operation"], "decompress");
+    assert_eq!(stats["level"], serde_json::Value::Null);
+    assert_eq!(stats["output_bytes"], 606);
+}
+
+#[test]
+fn compress_if_smaller_stores_incompressible_zip_entries() {
+    let s = Scratch::new("if-smaller-zip");
+    s.write("project/noise.bin", random_bytes(64 << 10, 5));
+    s.write("project/text.txt", "compressible text\n".repeat(4096));
+    let archive = s.path("out.zip");
+    sharky_ok(&["-c", "-i", arg(&s.path("project")), "-o", arg(&archive), "--compress-if-smaller", "-q"]);
+
+    let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+    assert_eq!(zip.by_name("project/noise.bin").unwrap().compression(), zip::CompressionMethod::Stored);
+    assert_eq!(zip.by_name("project/text.txt").unwrap().compression(), zip::CompressionMethod::Deflated);
+}
+
+#[test]
+fn compress_if_smaller_drops_whole_archives_that_grow() {
+    let s = Scratch::new("if-smaller-tar");
+    let noise = s.write("noise.bin", random_bytes(64 << 10, 6));
+    let archive = s.path("noise.bin.gz");
+    let out = sharky_ok(&["-c", "-i", arg(&noise), "-o", arg(&archive), "--compress-if-smaller", "-q"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Compression skipped"));
+    assert!(!archive.exists());
+
+    let text = s.write("text.txt", "compressible text\n".repeat(4096));
+    let archive = s.path("text.txt.gz");
+    sharky_ok(&["-c", "-i", arg(&text), "-o", arg(&archive), "--compress-if-smaller", "-q"]);
+    assert!(archive.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_transform_needs_a_tar_based_output() {
+    let s = Scratch::new("file-transform");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.tar.gz");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--file-transform", "txt:tr a-z A-Z", "-q"]);
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("project/a.txt")).unwrap(), b"ALPHA\n");
+
+    let file = s.path("project/a.txt");
+    for name in ["a.txt.br", "a.txt.lz4", "a.txt.lzfse", "a.txt.gz", "out.zip"] {
+        let stderr = sharky_err(&["-c", "-i", arg(&file), "-o", arg(&s.path(name)), "--file-transform", "txt:cat", "-q"]);
+        assert!(stderr.contains("only supported for tar-based outputs"), "{}: {}", name, stderr);
+    }
+}
+
+#[test]
+fn embed_checksums_needs_a_tar_based_output() {
+    let s = Scratch::new("embed-checksums");
+    let file = s.write("a.txt", "alpha\n");
+    for name in ["a.txt.br", "a.txt.lz4", "a.txt.lzfse", "a.txt.xz", "a.7z"] {
+        let stderr = sharky_err(&["-c", "-i", arg(&file), "-o", arg(&s.path(name)), "--embed-checksums", "-q"]);
+        assert!(stderr.contains("--embed-checksums is only supported for tar-based outputs"), "{}: {}", name, stderr);
+        assert!(!s.path(name).exists(), "{}", name);
+    }
+    sharky_ok(&["-c", "-i", arg(&file), "-o", arg(&s.path("a.tar.lz4")), "--embed-checksums", "-q"]);
+}
+
+#[test]
+fn output_inside_the_input_tree_is_not_archived() {
+    let s = Scratch::new("output-in-input");
+    let input = sample_tree(&s, "project");
+    let archive = input.join("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+    let names = tar_names(File::open(&archive).unwrap());
+    assert!(names.contains(&"project/a.txt".to_string()), "{:?}", names);
+    assert!(!names.iter().any(|n| n.ends_with("out.tar")), "{:?}", names);
+}
+
+#[test]
+fn compressing_a_file_onto_itself_is_refused() {
+    let s = Scratch::new("output-is-input");
+    let input = s.write("data.bin", "payload");
+    let stderr = sharky_err(&["-c", "-i", arg(&input), "-o", arg(&input), "--format", "gz", "--force", "-q"]);
+    assert!(stderr.contains("onto itself"), "{}", stderr);
+    assert_eq!(fs::read(&input).unwrap(), b"payload");
+}
+
+/// `--format tar.gz` produit une archive standard que `tar` relit seul
+#[test]
+fn format_tar_gz_is_readable_by_system_tar() {
+    if std::process::Command::new("tar").arg("--version").output().is_err() {
+        return;
+    }
+    let s = Scratch::new("format-tar-gz");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.bin");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--format", "tar.gz", "-q"]);
+
+    let out = s.mkdir("extracted");
+    let status = std::process::Command::new("tar").arg("xzf").arg(&archive).arg("-C").arg(&out).status().unwrap();
+    assert!(status.success());
+    assert_eq!(tree(&out.join("project")), tree(&input));
+}
+
+#[test]
+fn store_extensions_keeps_matching_zip_entries_uncompressed() {
+    let s = Scratch::new("store-extensions");
+    s.write("photos/shot.jpg", "not really a jpeg\n".repeat(200));
+    s.write("photos/notes.txt", "caption\n".repeat(200));
+    let input = s.path("photos");
+    for list in [None, Some("jpg")] {
+        let archive = s.path("photos.zip");
+        let mut args = vec!["-c", "-i", arg(&input), "-o", arg(&archive), "--force", "-q", "--store-extensions"];
+        args.extend(list);
+        sharky_ok(&args);
+
+        let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        assert_eq!(zip.by_name("photos/shot.jpg").unwrap().compression(), zip::CompressionMethod::Stored, "{:?}", list);
+        assert_eq!(zip.by_name("photos/notes.txt").unwrap().compression(), zip::CompressionMethod::Deflated, "{:?}", list);
+    }
+}
+
+/// Sans `--format`, l'extension de sortie choisit le conteneur ; une extension inconnue
+/// garde le format historique zstd → xz
+#[test]
+fn output_extension_selects_the_directory_format() {
+    let s = Scratch::new("format-by-extension");
+    let input = sample_tree(&s, "project");
+    for name in ["out.tar.gz", "out.tgz"] {
+        let archive = s.path(name);
+        sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+        let names = tar_names(flate2::read::GzDecoder::new(File::open(&archive).unwrap()));
+        assert!(names.contains(&"project/sub/b.txt".to_string()), "{}: {:?}", name, names);
+    }
+
+    let legacy = s.path("out.sharky");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&legacy), "-q"]);
+    assert!(fs::read(&legacy).unwrap().starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&legacy), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out.join("project")), tree(&input));
+}
+
+/// Un bloc répété à 4 Mio d'écart échappe à la fenêtre du niveau 3 (2 Mio), pas à `--zstd-long`
+#[test]
+fn zstd_long_finds_far_apart_duplicates() {
+    let s = Scratch::new("zstd-long");
+    let block = random_bytes(1 << 20, 23);
+    let data = [&block[..], &random_bytes(3 << 20, 29), &block[..]].concat();
+    let input = s.write("data.bin", &data);
+
+    let plain = s.path("plain.zst");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&plain), "-z", "3", "-q"]);
+    let long = s.path("long.zst");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&long), "-z", "3", "--zstd-long", "-q"]);
+
+    let (plain_size, long_size) = (fs::metadata(&plain).unwrap().len(), fs::metadata(&long).unwrap().len());
+    assert!(long_size + (1 << 19) < plain_size, "long {} vs plain {}", long_size, plain_size);
+    assert_eq!(zstd::stream::decode_all(File::open(&long).unwrap()).unwrap(), data);
+
+    let stderr = sharky_err(&["-c", "-i", arg(&input), "-o", arg(&s.path("bad.zst")), "--zstd-long", "32", "-q"]);
+    assert!(stderr.contains("10") && stderr.contains("31"), "{}", stderr);
+}
+
+/// Un dictionnaire entraîné sur des fichiers semblables réduit la taille d'un nouveau fichier
+#[test]
+fn trained_dictionary_improves_small_file_compression() {
+    let s = Scratch::new("train-dict");
+    let record = |n: usize| format!(
+        "{{\"id\": {}, \"user\": \"user{}\", \"status\": \"active\", \"roles\": [\"reader\", \"writer\"], \"region\": \"eu-west-{}\"}}\n",
+        n, n * 7, n % 3,
+    );
+    for n in 0..300 {
+        s.write(&format!("samples/{:03}.json", n), record(n));
+    }
+    let dict = s.path("records.dict");
+    sharky_ok(&["-i", arg(&s.path("samples")), "--train-dict", arg(&dict), "--dict-size", "4k", "-q"]);
+    assert!(dict.exists());
+
+    let input = s.write("new.json", record(1234));
+    let plain = s.path("plain.zst");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&plain), "-q"]);
+    let trained = s.path("trained.zst");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&trained), "--dict", arg(&dict), "-q"]);
+    assert!(fs::metadata(&trained).unwrap().len() < fs::metadata(&plain).unwrap().len());
+
+    let dictionary = fs::read(&dict).unwrap();
+    let mut decoder = zstd::stream::read::Decoder::with_dictionary(BufReader::new(File::open(&trained).unwrap()), &dictionary).unwrap();
+    let mut decoded = String::new();
+    decoder.read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, record(1234));
+}
+
+/// Estimation affichée avant de compresser `input` vers `output`, relue en octets
+fn estimated_memory(input: &std::path::Path, output: &std::path::Path, extra: &[&str]) -> f64 {
+    let mut args = vec!["-c", "-i", arg(input), "-o", arg(output), "--force"];
+    args.extend(extra);
+    let printed = stdout(&sharky_ok(&args));
+    let line = printed.lines().find_map(|l| l.strip_prefix("Estimated encoder memory: ")).expect(&printed);
+    let (value, unit) = line.split_once(' ').unwrap();
+    let scale = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => panic!("unexpected unit in {:?}", line),
+    };
+    value.parse::<f64>().unwrap() * scale
+}
+
+#[test]
+fn memory_estimate_grows_with_level_and_window() {
+    let s = Scratch::new("memory-estimate");
+    let input = sample_tree(&s, "project");
+    let zst = s.path("out.tar.zst");
+    let levels: Vec<f64> = ["1", "9", "19"].iter().map(|z| estimated_memory(&input, &zst, &["-z", z])).collect();
+    assert!(levels.windows(2).all(|w| w[0] < w[1]), "{:?}", levels);
+    let long = estimated_memory(&input, &zst, &["-z", "19", "--zstd-long", "27"]);
+    assert!(long > levels[2], "{} vs {:?}", long, levels);
+
+    let xz = s.path("out.tar.xz");
+    let presets: Vec<f64> = ["1", "6", "9"].iter().map(|p| estimated_memory(&input, &xz, &["--xz-preset", p])).collect();
+    assert!(presets.windows(2).all(|w| w[0] < w[1]), "{:?}", presets);
+}
+
+#[test]
+fn archive_root_renames_the_top_directory() {
+    let s = Scratch::new("archive-root");
+    let input = sample_tree(&s, "build");
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--archive-root", "release", "-q"]);
+    let names = tar_names(File::open(&archive).unwrap());
+    assert!(names.contains(&"release/sub/b.txt".to_string()), "{:?}", names);
+    assert!(names.iter().all(|n| n.starts_with("release")), "{:?}", names);
+
+    let zip = s.path("out.zip");
+    sharky_ok(&["-c", "-i", arg(&input.join("a.txt")), "-o", arg(&zip), "--archive-root", "notes/first.txt", "-q"]);
+    assert_eq!(zip_names(&zip), vec!["notes/first.txt"]);
+
+    let stderr = sharky_err(&["-c", "-i", arg(&input), "-o", arg(&s.path("bad.tar")), "--archive-root", "../up", "-q"]);
+    assert!(stderr.contains("without '..'"), "{}", stderr);
+}
+
+/// Un socket Unix est énuméré par le parcours mais refuse l'ouverture (ENXIO), comme un
+/// fichier supprimé entre l'énumération et la lecture, sans dépendre d'une course
+#[cfg(unix)]
+#[test]
+fn skip_errors_continues_past_files_that_cannot_be_opened() {
+    let s = Scratch::new("skip-errors");
+    let input = sample_tree(&s, "project");
+    let _listener = std::os::unix::net::UnixListener::bind(input.join("gone.sock")).unwrap();
+    for ext in ["tar", "zip", "7z"] {
+        let archive = s.path(&format!("out.{}", ext));
+        let stderr = sharky_err(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+        assert!(stderr.contains("No such device or address"), "{}: {}", ext, stderr);
+
+        let result = sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--skip-errors", "--force", "-q"]);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("Skipped 1 unreadable file(s):") && stderr.contains("gone.sock"), "{}: {}", ext, stderr);
+
+        let out = s.path(&format!("out-{}", ext));
+        sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+        assert_eq!(tree(&out.join("project")), tree(&input), "{}", ext);
+    }
+}
+
+#[test]
+fn compare_levels_prints_a_size_table() {
+    let s = Scratch::new("compare-levels");
+    let input = sample_tree(&s, "project");
+    s.write("project/log.txt", (0..2000).map(|n| format!("line {} status ok\n", n % 97)).collect::<String>());
+    for format in ["tar.zst", "tar.gz"] {
+        let printed = stdout(&sharky_ok(&["-c", "-i", arg(&input), "--compare-levels", "--format", format]));
+        let mut lines = printed.lines().skip_while(|l| !l.trim_start().starts_with("Level"));
+        assert_eq!(lines.next().unwrap().split_whitespace().collect::<Vec<_>>(), ["Level", "Size", "Ratio", "Time"], "{}", printed);
+        let sizes: Vec<u64> = lines
+            .map_while(|l| l.split_whitespace().nth(1)?.parse().ok())
+            .collect();
+        assert!(sizes.len() >= 3, "{}: {}", format, printed);
+        // Tendance décroissante, à quelques octets près d'un niveau au suivant
+        assert!(sizes.windows(2).all(|w| w[1] <= w[0] + w[0] / 20), "{}: {:?}", format, sizes);
+        assert!(sizes.last() < sizes.first(), "{}: {:?}", format, sizes);
+    }
+}
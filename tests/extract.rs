@@ -0,0 +1,350 @@
+//! Extraction de bout en bout par le binaire : liens, chemins hostiles
+
+mod common;
+
+use common::*;
+use std::fs;
+use tar::EntryType;
+
+#[cfg(unix)]
+#[test]
+fn tar_symlinks_round_trip() {
+    let s = Scratch::new("tar-symlink");
+    let input = sample_tree(&s, "project");
+    std::os::unix::fs::symlink("sub/b.txt", input.join("link")).unwrap();
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let out = s.path("extracted");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    let link = out.join("project/link");
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_link(&link).unwrap(), std::path::Path::new("sub/b.txt"));
+    assert_eq!(fs::read(&link).unwrap(), "bravo\n".repeat(100).as_bytes());
+}
+
+#[test]
+fn tar_parent_dir_entry_is_refused() {
+    let s = Scratch::new("tar-dotdot");
+    let archive = s.write("evil.tar", crafted_tar(&[("../evil.txt", EntryType::Regular, "", b"pwned")]));
+    let out = s.path("out");
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert!(stderr.contains("escapes the output directory"), "{}", stderr);
+    assert!(!s.path("evil.txt").exists());
+}
+
+#[test]
+fn tar_symlink_escaping_target_is_refused() {
+    let s = Scratch::new("tar-symlink-out");
+    let archive = s.write("evil.tar", crafted_tar(&[("up", EntryType::Symlink, "../..", b"")]));
+    let out = s.path("out");
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert!(stderr.contains("target escapes the output directory"), "{}", stderr);
+}
+
+/// `d -> .` puis `e -> d/..` passent la vérification lexicale des cibles, mais `e` mène hors
+/// de la sortie : rien ne doit être écrit à travers
+#[cfg(unix)]
+#[test]
+fn tar_writes_through_extracted_symlinks_are_refused() {
+    let s = Scratch::new("tar-symlink-chain");
+    let archive = s.write("evil.tar", crafted_tar(&[
+        ("d", EntryType::Symlink, ".", b""),
+        ("e", EntryType::Symlink, "d/..", b""),
+        ("e/pwned.txt", EntryType::Regular, "", b"pwned"),
+    ]));
+    let out = s.path("out");
+    let result = sharky(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(result.status.code(), Some(4), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!s.path("pwned.txt").exists());
+}
+
+/// Un lien dur dont la source passe par un lien symbolique tirerait un fichier du dehors
+#[cfg(unix)]
+#[test]
+fn tar_hard_link_through_extracted_symlink_is_refused() {
+    let s = Scratch::new("tar-hardlink-chain");
+    s.write("secret.txt", "secret");
+    let archive = s.write("evil.tar", crafted_tar(&[
+        ("d", EntryType::Symlink, ".", b""),
+        ("e", EntryType::Symlink, "d/..", b""),
+        ("copy.txt", EntryType::Link, "e/secret.txt", b""),
+    ]));
+    let out = s.path("out");
+    let result = sharky(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(result.status.code(), Some(4), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!out.join("copy.txt").exists());
+}
+
+/// Un fichier portant le nom d'un lien déjà extrait remplace le lien au lieu de le suivre
+#[cfg(unix)]
+#[test]
+fn tar_file_over_extracted_symlink_replaces_it() {
+    let s = Scratch::new("tar-file-over-link");
+    s.write("victim.txt", "untouched");
+    let archive = s.write("evil.tar", crafted_tar(&[
+        ("d", EntryType::Symlink, ".", b""),
+        ("e", EntryType::Symlink, "d/..", b""),
+        ("f", EntryType::Symlink, "e/victim.txt", b""),
+        ("f", EntryType::Regular, "", b"pwned"),
+    ]));
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(s.path("victim.txt")).unwrap(), b"untouched");
+    assert_eq!(fs::read(out.join("f")).unwrap(), b"pwned");
+}
+
+#[test]
+fn tar_include_and_exclude_globs_select_entries() {
+    let s = Scratch::new("tar-globs");
+    let input = sample_tree(&s, "project");
+    s.write("project/sub/c.log", "log\n");
+    s.write("project/notes.md", "# notes\n");
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let out = s.path("txt");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--include", "*.txt", "--exclude", "project/sub/**", "-q"]);
+    let names: Vec<String> = tree(&out).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["project/a.txt"]);
+
+    let out = s.path("no-logs");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--exclude", "*.log", "-q"]);
+    let names: Vec<String> = tree(&out).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["project/a.txt", "project/notes.md", "project/sub/b.txt"]);
+}
+
+#[test]
+fn no_clobber_keeps_existing_files_and_backup_renames_them() {
+    let s = Scratch::new("no-clobber");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.tar.gz");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let out = s.path("out");
+    s.write("out/project/a.txt", "local edit\n");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--no-clobber", "-q"]);
+    assert_eq!(fs::read(out.join("project/a.txt")).unwrap(), b"local edit\n");
+    assert_eq!(fs::read(out.join("project/sub/b.txt")).unwrap(), "bravo\n".repeat(100).as_bytes());
+
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--backup", "-q"]);
+    assert_eq!(fs::read(out.join("project/a.txt")).unwrap(), b"alpha\n");
+    assert_eq!(fs::read(out.join("project/a.txt~")).unwrap(), b"local edit\n");
+}
+
+#[test]
+fn keep_going_skips_unwritable_entries_and_fails_at_the_end() {
+    let s = Scratch::new("keep-going");
+    let input = sample_tree(&s, "project");
+    s.write("project/z.txt", "zulu\n");
+    let archive = s.path("out.zip");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    // Un fichier occupe la place du répertoire `sub` : ses entrées ne peuvent pas être écrites
+    let out = s.path("out");
+    s.write("out/project/sub", "in the way");
+    let run = sharky(&["-d", "-i", arg(&archive), "-o", arg(&out), "--keep-going", "-q"]);
+    assert!(!run.status.success());
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    assert!(stderr.contains("could not be extracted"), "{}", stderr);
+    assert!(stderr.contains("sub"), "{}", stderr);
+    assert_eq!(fs::read(out.join("project/a.txt")).unwrap(), b"alpha\n");
+    assert_eq!(fs::read(out.join("project/z.txt")).unwrap(), b"zulu\n");
+}
+
+#[test]
+fn tar_directory_mtimes_survive_their_contents() {
+    let s = Scratch::new("dir-mtime");
+    let input = sample_tree(&s, "project");
+    let old = filetime::FileTime::from_unix_time(1_500_000_000, 0);
+    filetime::set_file_mtime(input.join("sub"), old).unwrap();
+    filetime::set_file_mtime(&input, old).unwrap();
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    for dir in ["project", "project/sub"] {
+        let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(out.join(dir)).unwrap());
+        assert_eq!(mtime.unix_seconds(), 1_500_000_000, "{}", dir);
+    }
+}
+
+#[test]
+fn extracting_into_the_archive_directory_needs_force() {
+    let s = Scratch::new("extract-into-own-dir");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.tar.gz");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+    fs::remove_dir_all(&input).unwrap();
+    let packed = fs::read(&archive).unwrap();
+
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&s.dir), "-q"]);
+    assert!(stderr.contains("which contains the archive"), "{}", stderr);
+    assert!(!input.exists());
+
+    let result = sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&s.dir), "-q", "--force"]);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Warning: extracting into"));
+    assert_eq!(fs::read(input.join("a.txt")).unwrap(), b"alpha\n");
+    assert_eq!(fs::read(&archive).unwrap(), packed);
+}
+
+#[test]
+fn extract_entry_pulls_a_single_file_from_tar_and_zip() {
+    let s = Scratch::new("extract-entry");
+    s.write("data/dir/readme.txt", "read me\n");
+    s.write("data/dir/other.txt", "other\n");
+    s.write("data/top.txt", "top\n");
+    for ext in ["tar", "zip"] {
+        let archive = s.path(&format!("data.{}", ext));
+        sharky_ok(&["-c", "-i", arg(&s.path("data")), "-o", arg(&archive), "--no-wrap", "-q"]);
+
+        let out = s.path(&format!("out-{}", ext));
+        sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--extract-entry", "dir/readme.txt", "-q"]);
+        assert_eq!(tree(&out), vec![("dir/readme.txt".to_string(), b"read me\n".to_vec())], "{}", ext);
+    }
+}
+
+#[test]
+fn empty_archives_extract_cleanly() {
+    let s = Scratch::new("empty-archives");
+    s.write("empty.tar", crafted_tar(&[]));
+    zip::ZipWriter::new(fs::File::create(s.path("empty.zip")).unwrap()).finish().unwrap();
+    sharky_ok(&["-c", "-i", arg(&s.mkdir("nothing")), "-o", arg(&s.path("empty.7z")), "--no-wrap", "-q"]);
+
+    for name in ["empty.tar", "empty.zip", "empty.7z"] {
+        let out = s.path(&format!("out-{}", name));
+        let result = sharky_ok(&["-d", "-i", arg(&s.path(name)), "-o", arg(&out)]);
+        assert!(stdout(&result).contains("Archive is empty, nothing to extract."), "{}: {}", name, stdout(&result));
+        assert!(tree(&out).is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn strip_components_drops_leading_directories() {
+    let s = Scratch::new("strip-components");
+    let archive = s.write("nested.tar", crafted_tar(&[
+        ("project/README", EntryType::Regular, "", b"readme"),
+        ("project/src/main.rs", EntryType::Regular, "", b"fn main() {}"),
+        ("project/src/bin/tool.rs", EntryType::Regular, "", b"tool"),
+    ]));
+
+    let one = s.path("one");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&one), "--strip-components", "1", "-q"]);
+    assert_eq!(tree(&one), vec![
+        ("README".to_string(), b"readme".to_vec()),
+        ("src/bin/tool.rs".to_string(), b"tool".to_vec()),
+        ("src/main.rs".to_string(), b"fn main() {}".to_vec()),
+    ]);
+
+    // `project/README` n'a pas de troisième composant : il disparaît entièrement
+    let two = s.path("two");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&two), "--strip-components", "2", "-q"]);
+    assert_eq!(tree(&two), vec![
+        ("bin/tool.rs".to_string(), b"tool".to_vec()),
+        ("main.rs".to_string(), b"fn main() {}".to_vec()),
+    ]);
+}
+
+#[test]
+fn dry_run_lists_targets_without_writing() {
+    let s = Scratch::new("dry-run");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.tar");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let out = s.path("out");
+    let result = sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--dry-run", "-q"]);
+    let printed = stdout(&result);
+    assert!(printed.contains(&format!("Would write {}", out.join("project/a.txt").display())), "{}", printed);
+    assert!(printed.contains(&format!("Would write {}", out.join("project/sub/b.txt").display())), "{}", printed);
+    assert!(!out.exists());
+}
+
+/// Zip écrit sous Windows : séparateurs `\` et lettre de lecteur
+#[test]
+fn zip_backslash_entries_extract_into_directories() {
+    use std::io::Write;
+
+    let s = Scratch::new("zip-backslash");
+    let archive = s.path("windows.zip");
+    let mut writer = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    writer.start_file("dir\\sub\\f.txt", options).unwrap();
+    writer.write_all(b"nested").unwrap();
+    writer.start_file("C:\\top.txt", options).unwrap();
+    writer.write_all(b"top").unwrap();
+    writer.finish().unwrap();
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out), vec![
+        ("dir/sub/f.txt".to_string(), b"nested".to_vec()),
+        ("top.txt".to_string(), b"top".to_vec()),
+    ]);
+}
+
+#[cfg(unix)]
+#[test]
+fn tar_file_modes_and_mtimes_are_restored() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let s = Scratch::new("tar-modes");
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, mode) in [("run.sh", 0o755), ("private.txt", 0o600)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(mode);
+        header.set_mtime(1_400_000_000);
+        header.set_size(4);
+        header.set_cksum();
+        builder.append_data(&mut header, name, &b"data"[..]).unwrap();
+    }
+    let archive = s.write("modes.tar", builder.into_inner().unwrap());
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    for (name, mode) in [("run.sh", 0o755), ("private.txt", 0o600)] {
+        let meta = fs::metadata(out.join(name)).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, mode, "{}", name);
+        assert_eq!(filetime::FileTime::from_last_modification_time(&meta).unix_seconds(), 1_400_000_000, "{}", name);
+    }
+
+    let plain = s.path("plain");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&plain), "--no-preserve-permissions", "-q"]);
+    let meta = fs::metadata(plain.join("run.sh")).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o111, 0);
+    assert_ne!(filetime::FileTime::from_last_modification_time(&meta).unix_seconds(), 1_400_000_000);
+}
+
+/// Le format d'une archive mal nommée se reconnaît à sa signature
+#[test]
+fn misnamed_archives_are_detected_by_magic() {
+    let s = Scratch::new("sniff");
+    let input = sample_tree(&s, "project");
+    for ext in ["zip", "tar.gz", "7z"] {
+        let archive = s.path(&format!("project.{}", ext));
+        sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+        let renamed = s.path(&format!("download-{}.bin", ext.replace('.', "-")));
+        fs::rename(&archive, &renamed).unwrap();
+
+        let out = s.path(&format!("out-{}", ext));
+        sharky_ok(&["-d", "-i", arg(&renamed), "-o", arg(&out), "-q"]);
+        assert_eq!(tree(&out.join("project")), tree(&input), "{}", ext);
+    }
+}
+
+#[test]
+fn tar_shorthand_extensions_extract() {
+    let s = Scratch::new("tar-shorthands");
+    let input = sample_tree(&s, "project");
+    for (full, short) in [("tar.bz2", "tbz2"), ("tar.bz2", "tbz"), ("tar.xz", "txz"), ("tar.zst", "tzst")] {
+        let archive = s.path(&format!("project.{}", full));
+        sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--force", "-q"]);
+        let renamed = s.path(&format!("project.{}", short));
+        fs::rename(&archive, &renamed).unwrap();
+
+        let out = s.path(&format!("out-{}", short));
+        sharky_ok(&["-d", "-i", arg(&renamed), "-o", arg(&out), "-q"]);
+        assert_eq!(tree(&out.join("project")), tree(&input), "{}", short);
+    }
+}
@@ -0,0 +1,79 @@
+//! Contrôles d'intégrité : `--test` sans rien écrire, `--verify` après extraction
+
+mod common;
+
+use common::*;
+use std::fs;
+use std::io::Write;
+
+/// Archive de `sample_tree` au format de l'extension de `name`
+fn sample_archive(s: &Scratch, name: &str) -> std::path::PathBuf {
+    let input = sample_tree(s, "project");
+    let archive = s.path(name);
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+    archive
+}
+
+#[test]
+fn test_mode_accepts_intact_archives() {
+    let s = Scratch::new("test-ok");
+    for name in ["ok.tar.zst", "ok.zip"] {
+        let archive = sample_archive(&s, name);
+        let out = sharky_ok(&["-t", "-i", arg(&archive)]);
+        let text = stdout(&out) + &String::from_utf8_lossy(&out.stderr);
+        assert!(text.contains("OK"), "{}: {}", name, text);
+    }
+}
+
+#[test]
+fn test_mode_rejects_truncated_archives() {
+    let s = Scratch::new("test-truncated");
+    for name in ["cut.tar.zst", "cut.zip"] {
+        let archive = sample_archive(&s, name);
+        let bytes = fs::read(&archive).unwrap();
+        fs::write(&archive, &bytes[..bytes.len() * 2 / 3]).unwrap();
+        let out = sharky(&["-t", "-i", arg(&archive)]);
+        assert!(!out.status.success(), "{} passed --test", name);
+        assert!(!stdout(&out).contains(": OK"));
+    }
+}
+
+#[test]
+fn verify_reports_a_flipped_byte_in_a_zip() {
+    let s = Scratch::new("verify-flip");
+    let mut writer = zip::ZipWriter::new(fs::File::create(s.path("flip.zip")).unwrap());
+    let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("data.txt", stored).unwrap();
+    writer.write_all(b"the quick brown fox\n").unwrap();
+    writer.finish().unwrap();
+
+    let mut bytes = fs::read(s.path("flip.zip")).unwrap();
+    let at = bytes.windows(5).position(|w| w == b"quick").unwrap();
+    bytes[at] ^= 0x20;
+    let archive = s.write("flip.zip", bytes);
+
+    let out = sharky(&["-d", "-i", arg(&archive), "-o", arg(&s.path("out")), "--verify", "-q"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("Checksum mismatch for"), "{}", stderr);
+    assert!(stderr.contains("data.txt"), "{}", stderr);
+}
+
+#[test]
+fn verify_sha256_manifest_is_printed_once_after_extraction() {
+    let s = Scratch::new("verify-sha256");
+    let archive = sample_archive(&s, "ok.zip");
+    let alpha = "b6a98d9ce9a2d9149288fa3df42d377c3e42737afdcdaf714e33c0a100b51060";
+
+    let out = s.path("out");
+    let run = sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--verify", "sha256", "--threads", "4"]);
+    let lines: Vec<String> = stdout(&run).lines().filter(|l| l.contains("  ")).map(str::to_string).collect();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].ends_with(alpha) && lines[0].contains("a.txt"), "{:?}", lines);
+    assert!(lines[1].contains("b.txt"), "{:?}", lines);
+
+    // -q : rien sur la sortie standard, le manifeste passe sur stderr avec le bilan
+    let run = sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&s.path("quiet")), "--verify", "sha256", "-q"]);
+    assert_eq!(stdout(&run), "");
+    assert!(String::from_utf8_lossy(&run.stderr).contains(alpha));
+}
@@ -0,0 +1,128 @@
+//! Images ISO 9660 montées en mémoire : images tronquées ou forgées
+
+mod common;
+
+use common::*;
+use std::fs;
+
+/// Image d'un fichier `HELLO.TXT;1` de 5000 octets à la racine, rangé après celle-ci
+/// dans les trois derniers secteurs
+fn hello_iso() -> Vec<u8> {
+    let mut iso = IsoImage::default();
+    let root = iso.dir(&[iso_record(iso.next_sector() + 1, 5000, false, b"HELLO.TXT;1", &[])]);
+    iso.add(&random_bytes(5000, 7));
+    iso.finish(root, None)
+}
+
+#[test]
+fn iso_extracts_a_file() {
+    let s = Scratch::new("iso-ok");
+    let image = s.write("disc.iso", hello_iso());
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&image), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("HELLO.TXT")).unwrap(), random_bytes(5000, 7));
+}
+
+#[test]
+fn truncated_iso_fails_without_a_short_file() {
+    let s = Scratch::new("iso-truncated");
+    let mut bytes = hello_iso();
+    bytes.truncate(bytes.len() - 2048);
+    let image = s.write("disc.iso", bytes);
+    let out = s.path("out");
+    let stderr = sharky_err(&["-d", "-i", arg(&image), "-o", arg(&out), "-q"]);
+    assert!(stderr.contains("Truncated ISO image"), "{}", stderr);
+    assert!(!out.join("HELLO.TXT").exists());
+}
+
+#[test]
+fn iso_cut_before_its_root_directory_is_refused() {
+    let s = Scratch::new("iso-no-root");
+    let mut iso = IsoImage::default();
+    let root = iso.dir(&[]);
+    let mut bytes = iso.finish(root, None);
+    bytes.truncate(root.0 as usize * 2048);
+    let image = s.write("disc.iso", bytes);
+    let stderr = sharky_err(&["-d", "-i", arg(&image), "-o", arg(&s.path("out")), "-q"]);
+    assert!(stderr.contains("Corrupt ISO image"), "{}", stderr);
+}
+
+#[test]
+fn self_referencing_iso_directory_is_refused() {
+    let s = Scratch::new("iso-loop");
+    let mut iso = IsoImage::default();
+    let here = iso.next_sector();
+    let root = iso.dir(&[iso_record(here, 2048, true, b"LOOP", &[])]);
+    let image = s.write("loop.iso", iso.finish(root, None));
+
+    let stderr = sharky_err(&["-d", "-i", arg(&image), "-o", arg(&s.path("out")), "-q"]);
+    assert!(stderr.contains("reached twice"), "{}", stderr);
+    let stderr = sharky_err(&["-l", "-i", arg(&image)]);
+    assert!(stderr.contains("reached twice"), "{}", stderr);
+}
+
+/// Entrée SUSP : signature, longueur, version 1, données
+fn susp(signature: &[u8; 2], data: &[u8]) -> Vec<u8> {
+    [&signature[..], &[4 + data.len() as u8, 1], data].concat()
+}
+
+#[test]
+fn rock_ridge_long_names_and_modes_are_used() {
+    let s = Scratch::new("iso-rock-ridge");
+    let mut iso = IsoImage::default();
+    let long_name = "release-notes-for-linux.txt";
+    let px = [&0o100750u32.to_le_bytes()[..], &0o100750u32.to_be_bytes()].concat();
+    let system_use = [susp(b"NM", &[&[0], long_name.as_bytes()].concat()), susp(b"PX", &px)].concat();
+    let root = iso.dir(&[iso_record(iso.next_sector() + 1, 6, false, b"RELEASE_.TXT;1", &system_use)]);
+    iso.add(b"notes\n");
+    let image = s.write("linux.iso", iso.finish(root, None));
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&image), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join(long_name)).unwrap(), b"notes\n");
+    assert!(!out.join("RELEASE_.TXT").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(out.join(long_name)).unwrap().permissions().mode() & 0o777, 0o750);
+    }
+}
+
+/// Identifiant Joliet : UCS-2 big-endian
+fn ucs2(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+#[test]
+fn joliet_names_keep_accented_characters() {
+    let s = Scratch::new("iso-joliet");
+    let mut iso = IsoImage::default();
+    let (location, size) = iso.add("Crème brûlée\n".as_bytes());
+    let root = iso.dir(&[iso_record(location, size, false, b"RECETTE.TXT;1", &[])]);
+    let joliet = iso.dir(&[iso_record(location, size, false, &ucs2("recette crème brûlée.txt;1"), &[])]);
+    let image = s.write("windows.iso", iso.finish(root, Some(joliet)));
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&image), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out), vec![("recette crème brûlée.txt".to_string(), "Crème brûlée\n".as_bytes().to_vec())]);
+}
+
+#[cfg(unix)]
+#[test]
+fn nested_iso_directories_become_nested_paths() {
+    let s = Scratch::new("iso-nested");
+    let mut iso = IsoImage::default();
+    let file = iso.add(b"deep\n");
+    let inner = iso.dir(&[iso_record(file.0, file.1, false, b"FILE.TXT;1", &[])]);
+    let outer = iso.dir(&[iso_record(inner.0, inner.1, true, b"INNER", &[])]);
+    let root = iso.dir(&[iso_record(outer.0, outer.1, true, b"OUTER", &[])]);
+    let image = s.write("nested.iso", iso.finish(root, None));
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&image), "-o", arg(&out), "-q"]);
+    assert!(out.join("OUTER/INNER").is_dir());
+    assert_eq!(tree(&out), vec![("OUTER/INNER/FILE.TXT".to_string(), b"deep\n".to_vec())]);
+    let listing = stdout(&sharky_ok(&["-l", "-i", arg(&image)]));
+    assert!(listing.contains("OUTER/INNER/FILE.TXT"), "{}", listing);
+    assert!(!listing.contains('\\'), "{}", listing);
+}
@@ -0,0 +1,101 @@
+//! Paquets deb et rpm assemblés en mémoire : ar + tar, lead et en-têtes RPM + cpio newc
+
+mod common;
+
+use common::*;
+use std::fs;
+use std::io::Write;
+
+/// Tar non compressé des fichiers `(chemin, contenu)`
+fn tar_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for &(path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(data.len() as u64);
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn ar_member(builder: &mut ar::Builder<Vec<u8>>, name: &str, data: &[u8]) {
+    let header = ar::Header::new(name.as_bytes().to_vec(), data.len() as u64);
+    builder.append(&header, data).unwrap();
+}
+
+/// Flux cpio newc : entrées `(nom, mode, données)` puis `TRAILER!!!`
+fn cpio_of(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let trailer = [("TRAILER!!!", 0, &[][..])];
+    for &(name, mode, data) in entries.iter().chain(&trailer) {
+        let fields = [1, mode, 0, 0, 1, 0, data.len() as u32, 0, 0, 0, 0, name.len() as u32 + 1, 0];
+        out.extend_from_slice(b"070701");
+        for field in fields {
+            out.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        out.resize(out.len().div_ceil(4) * 4, 0);
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(4) * 4, 0);
+    }
+    out
+}
+
+/// Paquet RPM sans étiquettes : lead, en-têtes de signature et principal vides, `payload`
+fn rpm_of(payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![0u8; 96];
+    out[..4].copy_from_slice(&[0xed, 0xab, 0xee, 0xdb]);
+    for _ in 0..2 {
+        out.extend_from_slice(&[0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0]);
+        out.extend_from_slice(&[0; 8]);
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+#[test]
+fn deb_extracts_control_and_data() {
+    let s = Scratch::new("deb");
+    let mut control = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    control.write_all(&tar_of(&[("./control", b"Package: demo\n")])).unwrap();
+    let control = control.finish().unwrap();
+    let data = tar_of(&[("./usr/bin/demo", b"#!/bin/sh\necho demo\n")]);
+
+    let mut builder = ar::Builder::new(Vec::new());
+    ar_member(&mut builder, "debian-binary", b"2.0\n");
+    ar_member(&mut builder, "control.tar.gz", &control);
+    ar_member(&mut builder, "data.tar", &data);
+    let package = s.write("demo.deb", builder.into_inner().unwrap());
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&package), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("debian-binary")).unwrap(), b"2.0\n");
+    assert_eq!(fs::read(out.join("control/control")).unwrap(), b"Package: demo\n");
+    assert_eq!(fs::read(out.join("data/usr/bin/demo")).unwrap(), b"#!/bin/sh\necho demo\n");
+}
+
+#[test]
+fn rpm_extracts_its_cpio_payload() {
+    let s = Scratch::new("rpm");
+    let cpio = cpio_of(&[("./usr", 0o040755, b""), ("./usr/share/demo.txt", 0o100644, b"demo\n")]);
+    let mut payload = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    payload.write_all(&cpio).unwrap();
+    let package = s.write("demo.rpm", rpm_of(&payload.finish().unwrap()));
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&package), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("usr/share/demo.txt")).unwrap(), b"demo\n");
+}
+
+#[test]
+fn rpm_with_an_oversized_cpio_name_is_refused() {
+    let s = Scratch::new("rpm-long-name");
+    let mut cpio = cpio_of(&[("demo.txt", 0o100644, b"demo\n")]);
+    // Taille du nom (12e champ) : 0x7FFFFFFF au lieu de 9
+    cpio[6 + 11 * 8..14 + 11 * 8].copy_from_slice(b"7FFFFFFF");
+    let package = s.write("evil.rpm", rpm_of(&cpio));
+
+    let stderr = sharky_err(&["-d", "-i", arg(&package), "-o", arg(&s.path("out")), "-q"]);
+    assert!(stderr.contains("Corrupt cpio header"), "{}", stderr);
+}
@@ -0,0 +1,73 @@
+//! Archives chiffrées : bon mot de passe, mot de passe absent ou erroné
+
+mod common;
+
+use common::*;
+use std::fs;
+use std::io::Write;
+
+/// Zip d'un seul fichier `secret.txt` chiffré en AES-256
+fn aes_zip(scratch: &Scratch, password: &str) -> std::path::PathBuf {
+    let path = scratch.path("secret.zip");
+    let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let options = zip::write::SimpleFileOptions::default().with_aes_encryption(zip::AesMode::Aes256, password);
+    writer.start_file("secret.txt", options).unwrap();
+    writer.write_all(b"top secret\n").unwrap();
+    writer.finish().unwrap();
+    path
+}
+
+#[test]
+fn zip_aes_right_password_extracts() {
+    let s = Scratch::new("zip-aes-ok");
+    let archive = aes_zip(&s, "hunter2");
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--password", "hunter2", "-q"]);
+    assert_eq!(fs::read(out.join("secret.txt")).unwrap(), b"top secret\n");
+}
+
+#[test]
+fn zip_aes_missing_password_is_reported() {
+    let s = Scratch::new("zip-aes-missing");
+    let archive = aes_zip(&s, "hunter2");
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&s.path("out")), "-q"]);
+    assert!(stderr.contains("pass --password"), "{}", stderr);
+    assert!(!s.path("out/secret.txt").exists());
+}
+
+#[test]
+fn zip_aes_wrong_password_is_reported() {
+    let s = Scratch::new("zip-aes-wrong");
+    let archive = aes_zip(&s, "hunter2");
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&s.path("out")), "--password", "letmein", "-q"]);
+    assert!(stderr.contains("Wrong password"), "{}", stderr);
+    assert!(!s.path("out/secret.txt").exists());
+}
+
+#[test]
+fn sevenz_password_round_trip() {
+    let s = Scratch::new("7z-aes");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("secret.7z");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "--password", "hunter2", "-q"]);
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "--password", "hunter2", "-q"]);
+    assert_eq!(tree(&out.join("project")), tree(&input));
+
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&s.path("none")), "-q"]);
+    assert!(stderr.contains("pass --password"), "{}", stderr);
+    let stderr = sharky_err(&["-d", "-i", arg(&archive), "-o", arg(&s.path("wrong")), "--password", "letmein", "-q"]);
+    assert!(stderr.contains("Wrong password"), "{}", stderr);
+    assert!(!s.path("wrong/project/a.txt").exists());
+}
+
+#[test]
+fn password_needs_a_7z_output() {
+    let s = Scratch::new("zip-password-compress");
+    let input = sample_tree(&s, "project");
+    let archive = s.path("out.zip");
+    let stderr = sharky_err(&["-c", "-i", arg(&input), "-o", arg(&archive), "--password", "hunter2", "-q"]);
+    assert!(stderr.contains("only supported for 7z"), "{}", stderr);
+    assert!(!archive.exists());
+}
@@ -0,0 +1,181 @@
+//! Flux mono-fichier (gz, xz, ...) : membres concaténés, nom d'origine
+
+mod common;
+
+use common::*;
+use std::fs;
+use std::io::{Read, Write};
+
+fn gz_member(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn xz_member(data: &[u8]) -> Vec<u8> {
+    let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Flux `.lzma` (format « alone ») terminé par son marqueur de fin
+fn lzma_member(data: &[u8]) -> Vec<u8> {
+    let options = xz2::stream::LzmaOptions::new_preset(6).unwrap();
+    let stream = xz2::stream::Stream::new_lzma_encoder(&options).unwrap();
+    let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Compresse un fichier vers `name` puis le décompresse ; rend le flux compressé
+fn single_file_round_trip(s: &Scratch, name: &str, data: &[u8]) -> Vec<u8> {
+    let input = s.write("data.bin", data);
+    let stream = s.path(name);
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&stream), "-q"]);
+    let out = s.path(&format!("out-{}", name));
+    sharky_ok(&["-d", "-i", arg(&stream), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("data.bin")).unwrap(), data, "{}", name);
+    fs::read(&stream).unwrap()
+}
+
+/// Aller-retour d'un répertoire par une archive `name` (tar compressé)
+fn directory_round_trip(s: &Scratch, name: &str) {
+    let input = sample_tree(s, "project");
+    let archive = s.path(name);
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+    let out = s.path(&format!("out-{}", name));
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out.join("project")), tree(&input), "{}", name);
+}
+
+#[test]
+fn concatenated_gz_members_are_all_decoded() {
+    let s = Scratch::new("gz-members");
+    let stream = s.write("log.gz", [gz_member(b"first\n"), gz_member(b"second\n")].concat());
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&stream), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("log")).unwrap(), b"first\nsecond\n");
+}
+
+#[test]
+fn concatenated_xz_streams_are_all_decoded() {
+    let s = Scratch::new("xz-streams");
+    let stream = s.write("log.xz", [xz_member(b"first\n"), xz_member(b"second\n")].concat());
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&stream), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("log")).unwrap(), b"first\nsecond\n");
+}
+
+#[test]
+fn concatenated_lzma_streams_are_all_decoded() {
+    let s = Scratch::new("lzma-streams");
+    let stream = s.write("log.lzma", [lzma_member(b"first\n"), lzma_member(b"second\n")].concat());
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&stream), "-o", arg(&out), "-q"]);
+    assert_eq!(fs::read(out.join("log")).unwrap(), b"first\nsecond\n");
+}
+
+#[test]
+fn tar_lzma_extracts_the_directory_structure() {
+    let s = Scratch::new("tar-lzma");
+    let tar = crafted_tar(&[
+        ("project/a.txt", tar::EntryType::Regular, "", b"alpha\n"),
+        ("project/sub/b.txt", tar::EntryType::Regular, "", b"bravo\n"),
+    ]);
+    let archive = s.write("project.tar.lzma", lzma_member(&tar));
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    assert_eq!(tree(&out), vec![
+        ("project/a.txt".to_string(), b"alpha\n".to_vec()),
+        ("project/sub/b.txt".to_string(), b"bravo\n".to_vec()),
+    ]);
+}
+
+#[test]
+fn gz_stores_and_restores_the_original_name_and_mtime() {
+    let s = Scratch::new("gz-fname");
+    let input = s.write("report.txt", "quarterly\n");
+    filetime::set_file_mtime(&input, filetime::FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+    let archive = s.path("upload.gz");
+    sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&archive), "-q"]);
+
+    let decoder = flate2::read::GzDecoder::new(fs::File::open(&archive).unwrap());
+    let header = decoder.header().unwrap();
+    assert_eq!(header.filename(), Some(&b"report.txt"[..]));
+    assert_eq!(header.mtime(), 1_600_000_000);
+
+    let out = s.path("out");
+    sharky_ok(&["-d", "-i", arg(&archive), "-o", arg(&out), "-q"]);
+    let restored = out.join("report.txt");
+    assert_eq!(fs::read(&restored).unwrap(), b"quarterly\n");
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(&restored).unwrap());
+    assert_eq!(mtime.unix_seconds(), 1_600_000_000);
+}
+
+#[test]
+fn lz4_round_trips_files_and_directories() {
+    let s = Scratch::new("lz4-round-trip");
+    let data = [random_bytes(4096, 7), b"lz4 ".repeat(2048)].concat();
+    let stream = single_file_round_trip(&s, "data.bin.lz4", &data);
+    assert_eq!(lz4_flex::decompress_size_prepended(&stream).unwrap(), data);
+    directory_round_trip(&s, "project.tar.lz4");
+}
+
+#[test]
+fn brotli_round_trips_files_and_directories() {
+    let s = Scratch::new("brotli-round-trip");
+    let data = [random_bytes(4096, 11), b"brotli ".repeat(1024)].concat();
+    let stream = single_file_round_trip(&s, "data.bin.br", &data);
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(&stream[..], 4096).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+    directory_round_trip(&s, "project.tar.br");
+}
+
+#[test]
+fn xz_round_trips_a_single_file() {
+    let s = Scratch::new("xz-round-trip");
+    let data = [random_bytes(4096, 13), b"xz ".repeat(2048)].concat();
+    let stream = single_file_round_trip(&s, "data.bin.xz", &data);
+    let mut decoded = Vec::new();
+    xz2::read::XzDecoder::new(&stream[..]).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn lzfse_round_trips_files_and_directories() {
+    let s = Scratch::new("lzfse-round-trip");
+    let data = [random_bytes(4096, 17), b"lzfse ".repeat(1024)].concat();
+    single_file_round_trip(&s, "data.bin.lzfse", &data);
+    directory_round_trip(&s, "project.tar.lzfse");
+}
+
+#[test]
+fn bz2_round_trips_files_and_directories() {
+    let s = Scratch::new("bz2-round-trip");
+    let data = [random_bytes(4096, 19), b"bzip2 ".repeat(1024)].concat();
+    let stream = single_file_round_trip(&s, "data.bin.bz2", &data);
+    let mut decoded = Vec::new();
+    bzip2::read::BzDecoder::new(&stream[..]).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, data);
+    directory_round_trip(&s, "project.tar.bz2");
+
+    let stderr = sharky_err(&["-c", "-i", arg(&s.path("data.bin")), "-o", arg(&s.path("bad.bz2")), "--level", "10", "-q"]);
+    assert!(stderr.contains("Level must be between 0 and 9"), "{}", stderr);
+}
+
+#[test]
+fn single_stream_decompresses_to_stdout() {
+    let s = Scratch::new("stdout");
+    let data = [random_bytes(2048, 31), b"piped\n".repeat(512)].concat();
+    let input = s.write("data.bin", &data);
+    s.write("data.bin.lzma", lzma_member(&data));
+    for ext in ["gz", "xz", "zst", "bz2", "br", "lz4"] {
+        sharky_ok(&["-c", "-i", arg(&input), "-o", arg(&s.path(&format!("data.bin.{}", ext))), "-q"]);
+    }
+    for ext in ["gz", "xz", "zst", "bz2", "br", "lz4", "lzma"] {
+        let result = sharky_ok(&["-d", "-i", arg(&s.path(&format!("data.bin.{}", ext))), "-o", "-", "-q"]);
+        assert!(result.stdout == data, "{}", ext);
+    }
+    assert!(!s.path("-").exists());
+}